@@ -0,0 +1,341 @@
+//! Derive macros for redb's `RedbValue` and `RedbKey` traits
+//!
+//! Structs and enums can derive `RedbValue`, if all their fields implement `RedbValue`, and
+//! deserialize to their own type. For example, integers, `String`, `Vec<u8>`, `Option`s and tuples
+//! of them, or other types that derive `RedbValue`. `RedbKey` can additionally be derived, if all the
+//! fields implement `RedbKey`. `Debug` must also be implemented, since it's required by `RedbValue`
+//!
+//! ```ignore
+//! use redb_derive::{RedbKey, RedbValue};
+//!
+//! #[derive(Debug, RedbValue, RedbKey)]
+//! struct Event {
+//!     tenant_id: u64,
+//!     timestamp: i64,
+//!     name: String,
+//! }
+//! ```
+//!
+//! The fields are serialized in declaration order. Variable width fields are prefixed with their
+//! length, and enums are prefixed with the index of their variant. Enums whose fields are all fixed
+//! width are padded to the width of their largest variant, so that they are fixed width as well.
+//! Keys are compared field by field, in declaration order, and enum variants in declaration order.
+//!
+//! The type name, which is checked when a table is opened, includes the name of the type and the
+//! names and types of all its fields. Renaming or reordering them changes the type of the table
+
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Type};
+
+#[proc_macro_derive(RedbValue)]
+pub fn derive_redb_value(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    value_impl(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+#[proc_macro_derive(RedbKey)]
+pub fn derive_redb_key(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    key_impl(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+// The variants of an enum, with the tag that each one is serialized with
+fn enum_variants(input: &DeriveInput) -> Result<Vec<(u8, &Ident, &Fields)>, Error> {
+    if let Data::Enum(data) = &input.data {
+        if data.variants.is_empty() {
+            return Err(Error::new_spanned(
+                input,
+                "cannot be derived for enums without variants",
+            ));
+        }
+        if data.variants.len() > u8::MAX as usize + 1 {
+            return Err(Error::new_spanned(
+                input,
+                "cannot be derived for enums with more than 256 variants",
+            ));
+        }
+        Ok(data
+            .variants
+            .iter()
+            .enumerate()
+            .map(|(i, variant)| (i as u8, &variant.ident, &variant.fields))
+            .collect())
+    } else {
+        unreachable!()
+    }
+}
+
+fn check_supported(input: &DeriveInput) -> Result<(), Error> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "cannot be derived for types with generic parameters",
+        ));
+    }
+    if let Data::Union(_) = input.data {
+        return Err(Error::new_spanned(input, "cannot be derived for unions"));
+    }
+    Ok(())
+}
+
+// Returns the variable that each field is bound to, along with its type
+fn bindings(fields: &Fields) -> Vec<(Ident, &Type)> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(name) => (name.clone(), &field.ty),
+            None => (format_ident!("field{}", i), &field.ty),
+        })
+        .collect()
+}
+
+// Constructs, or destructures, the fields from their bindings
+fn construct(path: TokenStream2, fields: &Fields) -> TokenStream2 {
+    let names = bindings(fields).into_iter().map(|(name, _)| name);
+    match fields {
+        Fields::Named(_) => quote!(#path { #(#names),* }),
+        Fields::Unnamed(_) => quote!(#path ( #(#names),* )),
+        Fields::Unit => quote!(#path),
+    }
+}
+
+// Expression for the width of the fields, which must be used in a function that returns an Option
+fn fixed_width(fields: &Fields) -> TokenStream2 {
+    let types = bindings(fields).into_iter().map(|(_, ty)| ty);
+    quote! {
+        {
+            let mut width = 0;
+            #(
+                width += <#types as ::redb::RedbValue>::fixed_width()?;
+            )*
+            width
+        }
+    }
+}
+
+// Appends the bound fields to `output`
+fn serialize(fields: &Fields) -> TokenStream2 {
+    let names = bindings(fields).into_iter().map(|(name, _)| name);
+    quote! {
+        #(
+            ::redb::derive_helpers::push_element(&mut output, #names);
+        )*
+    }
+}
+
+// Binds the fields deserialized from `remaining`
+fn deserialize(fields: &Fields) -> TokenStream2 {
+    let (names, types): (Vec<Ident>, Vec<&Type>) = bindings(fields).into_iter().unzip();
+    quote! {
+        #(
+            let (bytes, remaining) = ::redb::derive_helpers::split_element::<#types>(remaining);
+            let #names = <#types as ::redb::RedbValue>::from_bytes(bytes);
+        )*
+    }
+}
+
+// Returns from the enclosing function, if any of the fields in `remaining1` and `remaining2` are
+// not equal
+fn compare(fields: &Fields) -> TokenStream2 {
+    let types = bindings(fields).into_iter().map(|(_, ty)| ty);
+    quote! {
+        #(
+            let (bytes1, remaining1) = ::redb::derive_helpers::split_element::<#types>(remaining1);
+            let (bytes2, remaining2) = ::redb::derive_helpers::split_element::<#types>(remaining2);
+            match <#types as ::redb::RedbKey>::compare(bytes1, bytes2) {
+                ::std::cmp::Ordering::Equal => {}
+                ordering => return ordering,
+            }
+        )*
+    }
+}
+
+// Expression for the names and types of the fields
+fn fields_type_name(fields: &Fields) -> TokenStream2 {
+    let names = bindings(fields).into_iter().map(|(name, ty)| match fields {
+        Fields::Named(_) => {
+            let name = name.to_string();
+            quote!(format!("{}: {}", #name, <#ty as ::redb::RedbValue>::redb_type_name()))
+        }
+        _ => quote!(<#ty as ::redb::RedbValue>::redb_type_name()),
+    });
+    match fields {
+        Fields::Named(_) => {
+            quote!(format!(" {{{}}}", <[String]>::join(&[#(#names),*], ", ")))
+        }
+        Fields::Unnamed(_) => quote!(format!("({})", <[String]>::join(&[#(#names),*], ", "))),
+        Fields::Unit => quote!(String::new()),
+    }
+}
+
+fn value_impl(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    check_supported(input)?;
+    let name = &input.ident;
+    let type_name = name.to_string();
+
+    let (width, from_bytes, as_bytes, redb_type_name) = match &input.data {
+        Data::Struct(data) => {
+            let width = fixed_width(&data.fields);
+            let deserialize = deserialize(&data.fields);
+            let constructor = construct(quote!(Self), &data.fields);
+            let serialize = serialize(&data.fields);
+            let fields_type_name = fields_type_name(&data.fields);
+            (
+                quote!(Some(#width)),
+                quote! {
+                    let remaining = data;
+                    #deserialize
+                    #constructor
+                },
+                quote! {
+                    let #constructor = self;
+                    let mut output = vec![];
+                    #serialize
+                    output
+                },
+                quote!(format!("{}{}", #type_name, #fields_type_name)),
+            )
+        }
+        Data::Enum(_) => {
+            let variants = enum_variants(input)?;
+            let widths = variants.iter().map(|(_, _, fields)| fixed_width(fields));
+            let deserializers = variants.iter().map(|(tag, variant, fields)| {
+                let deserialize = deserialize(fields);
+                let constructor = construct(quote!(Self::#variant), fields);
+                quote! {
+                    #tag => {
+                        #deserialize
+                        #constructor
+                    }
+                }
+            });
+            let serializers = variants.iter().map(|(tag, variant, fields)| {
+                let pattern = construct(quote!(Self::#variant), fields);
+                let serialize = serialize(fields);
+                quote! {
+                    #pattern => {
+                        output.push(#tag);
+                        #serialize
+                    }
+                }
+            });
+            let variant_type_names = variants.iter().map(|(_, variant, fields)| {
+                let variant = variant.to_string();
+                let fields_type_name = fields_type_name(fields);
+                quote!(format!("{}{}", #variant, #fields_type_name))
+            });
+            (
+                quote! {
+                    let mut width = 0;
+                    #(
+                        width = ::std::cmp::max(width, #widths);
+                    )*
+                    Some(width + 1)
+                },
+                quote! {
+                    let remaining = &data[1..];
+                    match data[0] {
+                        #(#deserializers)*
+                        _ => unreachable!(),
+                    }
+                },
+                quote! {
+                    let mut output = vec![];
+                    match self {
+                        #(#serializers)*
+                    }
+                    if let Some(width) = <Self as ::redb::RedbValue>::fixed_width() {
+                        output.resize(width, 0);
+                    }
+                    output
+                },
+                quote! {
+                    let variants: Vec<String> = vec![#(#variant_type_names),*];
+                    format!("{} {{{}}}", #type_name, variants.join(", "))
+                },
+            )
+        }
+        Data::Union(_) => unreachable!(),
+    };
+
+    Ok(quote! {
+        impl ::redb::RedbValue for #name {
+            type View = ::redb::OwnedLifetime<#name>;
+            type ToBytes = ::redb::OwnedAsBytesLifetime<::std::vec::Vec<u8>>;
+
+            fn fixed_width() -> Option<usize> {
+                #width
+            }
+
+            #[allow(unused_variables)]
+            fn from_bytes(data: &[u8]) -> <Self::View as ::redb::WithLifetime<'_>>::Out {
+                #from_bytes
+            }
+
+            #[allow(unused_variables)]
+            fn as_bytes(&self) -> <Self::ToBytes as ::redb::AsBytesWithLifetime<'_>>::Out {
+                #as_bytes
+            }
+
+            fn redb_type_name() -> String {
+                #redb_type_name
+            }
+        }
+    })
+}
+
+fn key_impl(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    check_supported(input)?;
+    let name = &input.ident;
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let compare = compare(&data.fields);
+            quote! {
+                let remaining1 = data1;
+                let remaining2 = data2;
+                #compare
+            }
+        }
+        Data::Enum(_) => {
+            let variants = enum_variants(input)?;
+            let comparators = variants.iter().map(|(tag, _, fields)| {
+                let compare = compare(fields);
+                quote! {
+                    #tag => {
+                        #compare
+                    }
+                }
+            });
+            quote! {
+                if data1[0] != data2[0] {
+                    return data1[0].cmp(&data2[0]);
+                }
+                let remaining1 = &data1[1..];
+                let remaining2 = &data2[1..];
+                match data1[0] {
+                    #(#comparators)*
+                    _ => unreachable!(),
+                }
+            }
+        }
+        Data::Union(_) => unreachable!(),
+    };
+
+    Ok(quote! {
+        impl ::redb::RedbKey for #name {
+            #[allow(unused_variables)]
+            fn compare(data1: &[u8], data2: &[u8]) -> ::std::cmp::Ordering {
+                #body
+                ::std::cmp::Ordering::Equal
+            }
+        }
+    })
+}
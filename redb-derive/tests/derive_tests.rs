@@ -0,0 +1,182 @@
+use redb::{Database, RangeIter, ReadableTable, RedbKey, RedbValue, TableDefinition};
+use redb_derive::{RedbKey, RedbValue};
+use std::cmp::Ordering;
+use std::ops::RangeFull;
+use tempfile::NamedTempFile;
+
+#[derive(Debug, PartialEq, RedbValue, RedbKey)]
+struct Event {
+    tenant_id: u64,
+    timestamp: i64,
+    name: String,
+}
+
+#[derive(Debug, PartialEq, RedbValue, RedbKey)]
+struct Point(i32, i32);
+
+#[derive(Debug, PartialEq, RedbValue, RedbKey)]
+struct Marker;
+
+#[derive(Debug, PartialEq, RedbValue, RedbKey)]
+enum Shape {
+    Empty,
+    Circle { center: Point, radius: u32 },
+    Polygon(Vec<u8>, Option<String>),
+}
+
+#[derive(Debug, PartialEq, RedbValue, RedbKey)]
+enum Direction {
+    Left,
+    Right(u16),
+    Up { distance: u64 },
+}
+
+#[test]
+fn fixed_width() {
+    assert_eq!(Some(8), Point::fixed_width());
+    assert_eq!(Some(0), Marker::fixed_width());
+    assert_eq!(None, Event::fixed_width());
+    assert_eq!(None, Shape::fixed_width());
+    // Padded to the largest variant
+    assert_eq!(Some(9), Direction::fixed_width());
+    assert_eq!(9, Direction::Left.as_bytes().len());
+    assert_eq!(9, Direction::Right(5).as_bytes().len());
+}
+
+#[test]
+fn type_names() {
+    assert_eq!(
+        "Event {tenant_id: u64, timestamp: i64, name: String}",
+        Event::redb_type_name()
+    );
+    assert_eq!("Point(i32, i32)", Point::redb_type_name());
+    assert_eq!("Marker", Marker::redb_type_name());
+    assert_eq!(
+        "Shape {Empty, Circle {center: Point(i32, i32), radius: u32}, Polygon(Vec<u8>, Option<String>)}",
+        Shape::redb_type_name()
+    );
+}
+
+#[test]
+fn round_trip() {
+    let event = Event {
+        tenant_id: 1,
+        timestamp: -5,
+        name: "hello".to_string(),
+    };
+    assert_eq!(event, Event::from_bytes(&event.as_bytes()));
+    assert_eq!(Marker, Marker::from_bytes(&Marker.as_bytes()));
+    for shape in [
+        Shape::Empty,
+        Shape::Circle {
+            center: Point(-1, 2),
+            radius: 3,
+        },
+        Shape::Polygon(vec![1, 2, 3], Some("triangle".to_string())),
+        Shape::Polygon(vec![], None),
+    ] {
+        assert_eq!(shape, Shape::from_bytes(&shape.as_bytes()));
+    }
+    for direction in [
+        Direction::Left,
+        Direction::Right(7),
+        Direction::Up { distance: 100 },
+    ] {
+        assert_eq!(direction, Direction::from_bytes(&direction.as_bytes()));
+    }
+}
+
+#[test]
+fn compare() {
+    let compare = |a: &Event, b: &Event| Event::compare(&a.as_bytes(), &b.as_bytes());
+    let event = |tenant_id, timestamp, name: &str| Event {
+        tenant_id,
+        timestamp,
+        name: name.to_string(),
+    };
+    assert_eq!(
+        Ordering::Less,
+        compare(&event(1, 5, "a"), &event(2, -5, "a"))
+    );
+    assert_eq!(
+        Ordering::Greater,
+        compare(&event(1, 5, "a"), &event(1, -5, "b"))
+    );
+    assert_eq!(
+        Ordering::Less,
+        compare(&event(1, 5, "a"), &event(1, 5, "ab"))
+    );
+    assert_eq!(
+        Ordering::Equal,
+        compare(&event(1, 5, "a"), &event(1, 5, "a"))
+    );
+
+    // Variants are ordered by declaration
+    let compare = |a: &Direction, b: &Direction| Direction::compare(&a.as_bytes(), &b.as_bytes());
+    assert_eq!(
+        Ordering::Less,
+        compare(&Direction::Left, &Direction::Right(0))
+    );
+    assert_eq!(
+        Ordering::Less,
+        compare(&Direction::Right(300), &Direction::Up { distance: 0 })
+    );
+    assert_eq!(
+        Ordering::Greater,
+        compare(&Direction::Right(300), &Direction::Right(2))
+    );
+}
+
+#[test]
+fn table() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+
+    let definition: TableDefinition<Event, Shape> = TableDefinition::new("x");
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        for (i, name) in ["c", "a", "b"].iter().enumerate() {
+            let key = Event {
+                tenant_id: 1,
+                timestamp: i as i64,
+                name: name.to_string(),
+            };
+            table.insert(&key, &Shape::Empty).unwrap();
+        }
+        let key = Event {
+            tenant_id: 0,
+            timestamp: 100,
+            name: "z".to_string(),
+        };
+        let value = Shape::Circle {
+            center: Point(0, 0),
+            radius: 1,
+        };
+        table.insert(&key, &value).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(definition).unwrap();
+    let key = Event {
+        tenant_id: 0,
+        timestamp: 100,
+        name: "z".to_string(),
+    };
+    assert_eq!(
+        Shape::Circle {
+            center: Point(0, 0),
+            radius: 1
+        },
+        table.get(&key).unwrap().unwrap()
+    );
+    let iter: RangeIter<Event, Shape> = table.range::<RangeFull, Event>(..).unwrap();
+    let names: Vec<String> = iter.map(|(key, _)| key.name).collect();
+    assert_eq!(vec!["z", "c", "a", "b"], names);
+
+    // The fields are part of the table's type
+    let definition: TableDefinition<Event, Point> = TableDefinition::new("x");
+    assert!(read_txn.open_table(definition).is_err());
+}
@@ -1,19 +1,27 @@
+use crate::group_commit::GroupCommitQueue;
 use crate::tree_store::{
-    get_db_size, AllPageNumbersBtreeIter, BtreeRangeIter, FreedTableKey, InternalTableDefinition,
-    PageNumber, RawBtree, TransactionalMemory,
+    all_blob_pages, get_db_size, verify_blob_checksums, AllPageNumbersBtreeIter, Btree,
+    BtreeRangeIter, ChangelogKey, Checksum, FreedTableKey, InternalTableDefinition, PageNumber,
+    RawBtree, SnapshotRecord, TableTree, TransactionalMemory,
 };
 use crate::types::{RedbKey, RedbValue};
+use crate::watch::{new_watch, PendingNotifications, Watcher};
 use crate::Error;
-use crate::{ReadTransaction, Result, WriteTransaction};
-use std::collections::btree_set::BTreeSet;
+use crate::{
+    CommitEvent, Durability, ReadTransaction, Result, Watch, WriteBatch, WriteTransaction,
+};
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt::{Display, Formatter};
 use std::fs::{File, OpenOptions};
 use std::io::ErrorKind;
 use std::marker::PhantomData;
-use std::ops::RangeFull;
+use std::ops::{RangeBounds, RangeFull};
 use std::path::Path;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Mutex;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
 use std::{io, panic};
 
 #[cfg(feature = "logging")]
@@ -147,9 +155,22 @@ impl<'a, K: RedbKey + ?Sized, V: RedbKey + ?Sized> Display for MultimapTableDefi
 pub struct Database {
     mem: TransactionalMemory,
     next_transaction_id: AtomicTransactionId,
-    live_read_transactions: Mutex<BTreeSet<TransactionId>>,
+    // Number of live read transactions for each transaction id. Reads of a snapshot use the id
+    // of the snapshot's transaction, so may share it
+    live_read_transactions: Mutex<BTreeMap<TransactionId, usize>>,
     live_write_transaction: Mutex<Option<TransactionId>>,
+    // Signalled when the live write transaction completes, or is leaked
+    live_write_transaction_available: Condvar,
     leaked_write_transaction: Mutex<Option<&'static panic::Location<'static>>>,
+    leaked_write_transaction_detection: bool,
+    max_inline_value_size: Option<usize>,
+    // Senders for the receivers returned by subscribe(). Disconnected senders are pruned when a
+    // commit is published
+    commit_subscribers: Mutex<Vec<Sender<CommitEvent>>>,
+    // Ranges registered by watch(). Watchers whose Watch has been dropped are pruned when a commit
+    // is checked against them
+    watchers: Mutex<Vec<Watcher>>,
+    group_commit_queue: GroupCommitQueue,
 }
 
 impl Database {
@@ -182,7 +203,7 @@ impl Database {
                 .open(path)?
         };
 
-        Database::new(file, db_size, None, None, true, None)
+        Database::new(file, db_size, None, None, true, None, false)
     }
 
     /// Opens an existing redb database.
@@ -194,7 +215,7 @@ impl Database {
         if File::open(path.as_ref())?.metadata()?.len() > 0 {
             let existing_size = get_db_size(path.as_ref())?;
             let file = OpenOptions::new().read(true).write(true).open(path)?;
-            Database::new(file, existing_size, None, None, true, None)
+            Database::new(file, existing_size, None, None, true, None, false)
         } else {
             Err(Error::Io(io::Error::from(ErrorKind::InvalidData)))
         }
@@ -232,10 +253,23 @@ impl Database {
             }
         }
 
+        if let Some((snapshot_root, snapshot_checksum)) = mem.get_snapshot_root() {
+            if !RawBtree::new(
+                Some((snapshot_root, snapshot_checksum)),
+                str::fixed_width(),
+                SnapshotRecord::fixed_width(),
+                mem,
+            )
+            .verify_checksum()
+            {
+                return false;
+            }
+        }
+
         // Iterate over all other tables
-        let mut iter: BtreeRangeIter<str, InternalTableDefinition> =
+        let iter: BtreeRangeIter<str, InternalTableDefinition> =
             BtreeRangeIter::new::<RangeFull, str>(.., Some(root), mem);
-        while let Some(entry) = iter.next() {
+        for entry in iter {
             let definition = InternalTableDefinition::from_bytes(entry.value());
             if let Some((table_root, table_checksum)) = definition.get_root() {
                 if !RawBtree::new(
@@ -249,6 +283,31 @@ impl Database {
                     return false;
                 }
             }
+            if let Some((blob_root, blob_checksum)) = definition.get_blob_root() {
+                if !RawBtree::new(
+                    Some((blob_root, blob_checksum)),
+                    definition.get_fixed_key_size(),
+                    None,
+                    mem,
+                )
+                .verify_checksum()
+                    || !verify_blob_checksums(blob_root, definition.get_fixed_key_size(), mem)
+                {
+                    return false;
+                }
+            }
+            if let Some((changelog_root, changelog_checksum)) = definition.get_changelog_root() {
+                if !RawBtree::new(
+                    Some((changelog_root, changelog_checksum)),
+                    ChangelogKey::fixed_width(),
+                    None,
+                    mem,
+                )
+                .verify_checksum()
+                {
+                    return false;
+                }
+            }
         }
 
         true
@@ -261,6 +320,7 @@ impl Database {
         region_size: Option<usize>,
         dynamic_growth: bool,
         use_checksums: Option<bool>,
+        leaked_write_transaction_detection: bool,
     ) -> Result<Self> {
         #[cfg(feature = "logging")]
         info!(
@@ -286,34 +346,70 @@ impl Database {
                 .expect("Tried to repair an empty database");
 
             // Repair the allocator state
-            // All pages in the master table
-            let mut all_pages_iter: Box<dyn Iterator<Item = PageNumber>> =
-                Box::new(AllPageNumbersBtreeIter::new(root, None, None, &mem));
-
-            // Iterate over all other tables
-            let mut iter: BtreeRangeIter<str, InternalTableDefinition> =
-                BtreeRangeIter::new::<RangeFull, str>(.., Some(root), &mem);
-
-            // Chain all the other tables to the master table iter
-            while let Some(entry) = iter.next() {
-                let definition = InternalTableDefinition::from_bytes(entry.value());
-                if let Some((table_root, _)) = definition.get_root() {
-                    let table_pages_iter = AllPageNumbersBtreeIter::new(
-                        table_root,
-                        definition.get_fixed_key_size(),
-                        definition.get_fixed_value_size(),
+            let transaction_id = mem.get_last_committed_transaction_id()? + 1;
+            if let Some((snapshot_root, snapshot_checksum)) = mem.get_snapshot_root() {
+                // Pages pinned by snapshots may be shared with the current state, and may only be
+                // reclaimed via the freed table once the snapshot is deleted. So keep the freed
+                // table, and deduplicate all the pages which are still referenced
+                let mut all_pages: HashSet<PageNumber> = Self::all_data_pages(root, &mem).collect();
+                all_pages.extend(AllPageNumbersBtreeIter::new(
+                    snapshot_root,
+                    str::fixed_width(),
+                    SnapshotRecord::fixed_width(),
+                    &mem,
+                ));
+                let iter: BtreeRangeIter<str, SnapshotRecord> =
+                    BtreeRangeIter::new::<RangeFull, str>(.., Some(snapshot_root), &mem);
+                for entry in iter {
+                    if let Some((data_root, _)) =
+                        SnapshotRecord::from_bytes(entry.value()).data_root
+                    {
+                        all_pages.extend(Self::all_data_pages(data_root, &mem));
+                    }
+                }
+                let freed_root = mem.get_freed_root();
+                if let Some((freed_root, _)) = freed_root {
+                    all_pages.extend(AllPageNumbersBtreeIter::new(
+                        freed_root,
+                        FreedTableKey::fixed_width(),
+                        None,
                         &mem,
-                    );
-                    all_pages_iter = Box::new(all_pages_iter.chain(table_pages_iter));
+                    ));
+                    let iter: BtreeRangeIter<FreedTableKey, [u8]> =
+                        BtreeRangeIter::new::<RangeFull, FreedTableKey>(.., Some(freed_root), &mem);
+                    for entry in iter {
+                        let value = entry.value();
+                        let length = u64::from_le_bytes(value[..8].try_into().unwrap()) as usize;
+                        // 1..=length because the array is length prefixed
+                        for i in 1..=length {
+                            all_pages.insert(PageNumber::from_le_bytes(
+                                value[i * 8..(i + 1) * 8].try_into().unwrap(),
+                            ));
+                        }
+                    }
                 }
-            }
-
-            mem.repair_allocator(all_pages_iter)?;
 
-            // Clear the freed table. We just rebuilt the allocator state by walking all the
-            // reachable data pages, which implicitly frees the pages for the freed table
-            let transaction_id = mem.get_last_committed_transaction_id()? + 1;
-            mem.commit(Some((root, root_checksum)), None, transaction_id, false)?;
+                mem.repair_allocator(all_pages.into_iter())?;
+                mem.commit(
+                    Some((root, root_checksum)),
+                    freed_root,
+                    Some((snapshot_root, snapshot_checksum)),
+                    transaction_id,
+                    false,
+                )?;
+            } else {
+                mem.repair_allocator(Self::all_data_pages(root, &mem))?;
+
+                // Clear the freed table. We just rebuilt the allocator state by walking all the
+                // reachable data pages, which implicitly frees the pages for the freed table
+                mem.commit(
+                    Some((root, root_checksum)),
+                    None,
+                    None,
+                    transaction_id,
+                    false,
+                )?;
+            }
         }
 
         let next_transaction_id = mem.get_last_committed_transaction_id()? + 1;
@@ -322,17 +418,101 @@ impl Database {
             mem,
             next_transaction_id: AtomicTransactionId::new(next_transaction_id),
             live_write_transaction: Mutex::new(None),
+            live_write_transaction_available: Condvar::new(),
             live_read_transactions: Mutex::new(Default::default()),
             leaked_write_transaction: Mutex::new(Default::default()),
+            leaked_write_transaction_detection,
+            max_inline_value_size: None,
+            commit_subscribers: Mutex::new(vec![]),
+            watchers: Mutex::new(vec![]),
+            group_commit_queue: GroupCommitQueue::new(),
         })
     }
 
+    // Returns all the pages in the master table rooted at root, and in the tables it references
+    fn all_data_pages<'a>(
+        root: PageNumber,
+        mem: &'a TransactionalMemory,
+    ) -> Box<dyn Iterator<Item = PageNumber> + 'a> {
+        // All pages in the master table
+        let mut all_pages_iter: Box<dyn Iterator<Item = PageNumber>> =
+            Box::new(AllPageNumbersBtreeIter::new(root, None, None, mem));
+
+        // Iterate over all other tables
+        let iter: BtreeRangeIter<str, InternalTableDefinition> =
+            BtreeRangeIter::new::<RangeFull, str>(.., Some(root), mem);
+
+        // Chain all the other tables to the master table iter
+        for entry in iter {
+            let definition = InternalTableDefinition::from_bytes(entry.value());
+            if let Some((table_root, _)) = definition.get_root() {
+                let table_pages_iter = AllPageNumbersBtreeIter::new(
+                    table_root,
+                    definition.get_fixed_key_size(),
+                    definition.get_fixed_value_size(),
+                    mem,
+                );
+                all_pages_iter = Box::new(all_pages_iter.chain(table_pages_iter));
+            }
+            if let Some((blob_root, _)) = definition.get_blob_root() {
+                let blob_pages =
+                    all_blob_pages(blob_root, definition.get_fixed_key_size(), mem).into_iter();
+                all_pages_iter = Box::new(all_pages_iter.chain(blob_pages));
+            }
+            if let Some((changelog_root, _)) = definition.get_changelog_root() {
+                let changelog_pages = AllPageNumbersBtreeIter::new(
+                    changelog_root,
+                    ChangelogKey::fixed_width(),
+                    None,
+                    mem,
+                );
+                all_pages_iter = Box::new(all_pages_iter.chain(changelog_pages));
+            }
+        }
+
+        all_pages_iter
+    }
+
+    pub(crate) fn leaked_write_transaction_detection(&self) -> bool {
+        self.leaked_write_transaction_detection
+    }
+
+    pub(crate) fn max_inline_value_size(&self) -> Option<usize> {
+        self.max_inline_value_size
+    }
+
+    pub(crate) fn has_commit_subscribers(&self) -> bool {
+        !self.commit_subscribers.lock().unwrap().is_empty()
+    }
+
+    pub(crate) fn publish_commit(&self, event: CommitEvent) {
+        self.commit_subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    // Checks the watchers against table_tree, which is about to be committed on top of the master
+    // table rooted at base_root
+    pub(crate) fn check_watchers(
+        &self,
+        base_root: Option<(PageNumber, Checksum)>,
+        table_tree: &TableTree,
+    ) -> Result<PendingNotifications> {
+        let base_tree = TableTree::new(base_root, &self.mem, Default::default());
+        PendingNotifications::new(&self.watchers, &base_tree, table_tree, &self.mem)
+    }
+
     pub(crate) fn record_leaked_write_transaction(&self, transaction_id: TransactionId) {
-        assert_eq!(
-            transaction_id,
-            self.live_write_transaction.lock().unwrap().unwrap()
-        );
+        let live_write = self.live_write_transaction.lock().unwrap();
+        assert_eq!(transaction_id, live_write.unwrap());
+        // Blocked writers check the flag while holding the write slot lock, so it must be held
+        // here too. Otherwise the leak could be recorded after a writer checked the flag, but
+        // before it started waiting, and that writer would never be woken
         *self.leaked_write_transaction.lock().unwrap() = Some(panic::Location::caller());
+        // Wake up any blocked writers, so that they can return the error
+        self.live_write_transaction_available.notify_all();
+        drop(live_write);
         #[cfg(feature = "logging")]
         error!(
             "Leaked write transaction from {}",
@@ -341,22 +521,49 @@ impl Database {
     }
 
     pub(crate) fn deallocate_read_transaction(&self, id: TransactionId) {
-        self.live_read_transactions.lock().unwrap().remove(&id);
+        let mut live_reads = self.live_read_transactions.lock().unwrap();
+        let count = live_reads.get_mut(&id).unwrap();
+        *count -= 1;
+        if *count == 0 {
+            live_reads.remove(&id);
+        }
     }
 
     pub(crate) fn deallocate_write_transaction(&self, id: TransactionId) {
         let mut live = self.live_write_transaction.lock().unwrap();
         assert_eq!(Some(id), *live);
         *live = None;
+        self.live_write_transaction_available.notify_one();
     }
 
-    pub(crate) fn oldest_live_read_transaction(&self) -> Option<TransactionId> {
-        self.live_read_transactions
-            .lock()
-            .unwrap()
-            .iter()
+    // Returns the oldest transaction whose state is pinned by a live read, by a committed snapshot,
+    // or by `pending_snapshot`, the oldest snapshot of the transaction which is committing.
+    //
+    // A snapshot deleted by the committing transaction can still be opened by
+    // begin_read_snapshot() until the commit is visible, so committed snapshots are included.
+    // This takes the same lock as begin_read_snapshot(), so any read of such a snapshot is either
+    // registered before this returns, or begins after a later commit has removed the snapshot
+    pub(crate) fn oldest_pinned_transaction(
+        &self,
+        pending_snapshot: Option<TransactionId>,
+    ) -> Option<TransactionId> {
+        let live_reads = self.live_read_transactions.lock().unwrap();
+        live_reads
+            .keys()
             .next()
             .cloned()
+            .into_iter()
+            .chain(pending_snapshot)
+            .chain(self.oldest_committed_snapshot())
+            .min()
+    }
+
+    fn oldest_committed_snapshot(&self) -> Option<TransactionId> {
+        let snapshot_root = self.mem.get_snapshot_root().map(|(p, _)| p);
+        let iter: BtreeRangeIter<str, SnapshotRecord> =
+            BtreeRangeIter::new::<RangeFull, str>(.., snapshot_root, &self.mem);
+        iter.map(|entry| SnapshotRecord::from_bytes(entry.value()).transaction_id)
+            .min()
     }
 
     /// Convenience method for [`DatabaseBuilder::new`]
@@ -367,21 +574,130 @@ impl Database {
     /// Begins a write transaction
     ///
     /// Returns a [`WriteTransaction`] which may be used to read/write to the database. Only a single
-    /// write may be in progress at a time
+    /// write may be in progress at a time, so this method blocks until any write already in progress
+    /// has completed
     pub fn begin_write(&self) -> Result<WriteTransaction> {
-        let guard = self.leaked_write_transaction.lock().unwrap();
-        if let Some(leaked) = *guard {
-            return Err(Error::LeakedWriteTransaction(leaked));
+        self.begin_write_with_timeout(None)
+    }
+
+    /// Begins a write transaction, if no other write is in progress
+    ///
+    /// Returns [`Error::WriteTransactionInProgress`] instead of blocking, if another write is in
+    /// progress
+    pub fn try_begin_write(&self) -> Result<WriteTransaction> {
+        self.begin_write_with_timeout(Some(Duration::ZERO))
+    }
+
+    /// Begins a write transaction, blocking for at most `timeout` until any write already in
+    /// progress has completed
+    ///
+    /// Returns [`Error::WriteTransactionInProgress`] if the other write did not complete in time
+    pub fn begin_write_timeout(&self, timeout: Duration) -> Result<WriteTransaction> {
+        self.begin_write_with_timeout(Some(timeout))
+    }
+
+    // Waits indefinitely, if timeout is None
+    fn begin_write_with_timeout(&self, timeout: Option<Duration>) -> Result<WriteTransaction> {
+        let deadline = timeout.map(|x| Instant::now() + x);
+        let mut live_write = self.live_write_transaction.lock().unwrap();
+        loop {
+            if let Some(leaked) = *self.leaked_write_transaction.lock().unwrap() {
+                return Err(Error::LeakedWriteTransaction(leaked));
+            }
+            if live_write.is_none() {
+                break;
+            }
+            if let Some(deadline) = deadline {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err(Error::WriteTransactionInProgress);
+                }
+                live_write = self
+                    .live_write_transaction_available
+                    .wait_timeout(live_write, deadline - now)
+                    .unwrap()
+                    .0;
+            } else {
+                live_write = self
+                    .live_write_transaction_available
+                    .wait(live_write)
+                    .unwrap();
+            }
         }
-        drop(guard);
 
-        assert!(self.live_write_transaction.lock().unwrap().is_none());
         let id = self.next_transaction_id.fetch_add(1, Ordering::AcqRel);
-        *self.live_write_transaction.lock().unwrap() = Some(id);
-        // Safety: We just asserted there was no previous write in progress
+        *live_write = Some(id);
+        drop(live_write);
         #[cfg(feature = "logging")]
         info!("Beginning write transaction id={}", id);
-        unsafe { WriteTransaction::new(self, id) }
+        // Safety: We just checked there was no previous write in progress, and hold the write slot
+        unsafe { WriteTransaction::new(self, id) }.map_err(|err| {
+            self.deallocate_write_transaction(id);
+            err
+        })
+    }
+
+    /// Subscribes to notifications of commits
+    ///
+    /// A [`CommitEvent`] is sent on the returned channel after each successful
+    /// [`WriteTransaction::commit`], in the order that the transactions committed. The channel is
+    /// unbounded, so a slow subscriber never blocks writers. Drop the receiver to unsubscribe
+    pub fn subscribe(&self) -> Receiver<CommitEvent> {
+        let (sender, receiver) = channel();
+        self.commit_subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// Runs `f` in a write transaction, which is committed along with the work of other threads
+    /// calling this method concurrently
+    ///
+    /// With [`Durability::Immediate`], every commit waits for its own fsync. Here, the work queued
+    /// by concurrent callers is applied sequentially to a single transaction, which is committed
+    /// with one fsync. The transaction is committed with the strongest durability requested by
+    /// any of the callers, so it is at least `durability`. The work of each caller is rolled back
+    /// if it returns an error or panics, without affecting the others, and the error or panic is
+    /// returned to that caller. If the commit fails, every caller in the batch receives the error.
+    ///
+    /// Blocks until the work has been committed. Must not be called from a thread which holds a
+    /// [`WriteTransaction`], or from within `f`, since it would deadlock
+    pub fn group_commit<F: FnOnce(&WriteTransaction) -> Result + Send + 'static>(
+        &self,
+        f: F,
+        durability: Durability,
+    ) -> Result {
+        self.group_commit_queue.run(self, Box::new(f), durability)
+    }
+
+    /// Applies the batch atomically, in a single write transaction committed with `durability`
+    ///
+    /// Blocks until any other write transaction completes. If any operation fails, none of the
+    /// batch is applied
+    pub fn apply(&self, batch: WriteBatch, durability: Durability) -> Result {
+        let mut txn = self.begin_write()?;
+        txn.set_durability(durability);
+        match batch.apply_to(&txn) {
+            Ok(()) => txn.commit(),
+            Err(err) => {
+                txn.abort()?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Watches a range of keys in the given table
+    ///
+    /// [`Watch::wait`] blocks until a transaction which inserts, modifies, or removes an entry in
+    /// `range` has committed. Deleting the table counts as removing all of its entries, and the
+    /// table does not need to exist yet. Each commit which modifies the table reads the pages of the
+    /// watched range which it modified
+    pub fn watch<K: RedbKey + ?Sized, V: RedbValue + ?Sized, T: RangeBounds<KR>, KR: Borrow<K>>(
+        &self,
+        definition: TableDefinition<K, V>,
+        range: T,
+    ) -> Watch {
+        let (watcher, watch) = new_watch(self, definition, range);
+        self.watchers.lock().unwrap().push(watcher);
+        watch
     }
 
     /// Begins a read transaction
@@ -393,10 +709,56 @@ impl Database {
     /// may exist concurrently with writes
     pub fn begin_read(&self) -> Result<ReadTransaction> {
         let id = self.next_transaction_id.fetch_add(1, Ordering::AcqRel);
-        self.live_read_transactions.lock().unwrap().insert(id);
+        *self
+            .live_read_transactions
+            .lock()
+            .unwrap()
+            .entry(id)
+            .or_default() += 1;
         #[cfg(feature = "logging")]
         info!("Beginning read transaction id={}", id);
-        Ok(ReadTransaction::new(self, id))
+        Ok(ReadTransaction::new(self, id, self.mem.get_data_root()))
+    }
+
+    /// Begins a read transaction of the given snapshot
+    ///
+    /// Only data committed before the snapshot was created is visible in the transaction. See
+    /// [`WriteTransaction::create_snapshot`]
+    ///
+    /// Returns [`Error::SnapshotDoesNotExist`] if there is no snapshot with the given name
+    pub fn begin_read_snapshot(&self, name: &str) -> Result<ReadTransaction> {
+        // Hold the lock while looking up the snapshot, so that the reader is registered before a
+        // commit can observe the snapshot's deletion and reclaim its pages. See
+        // oldest_pinned_transaction()
+        let mut live_reads = self.live_read_transactions.lock().unwrap();
+        let record = self
+            .get_snapshot(name)?
+            .ok_or_else(|| Error::SnapshotDoesNotExist(name.to_string()))?;
+        // Register with the snapshot's transaction id, which pins the same pages as the snapshot
+        let id = record.transaction_id;
+        *live_reads.entry(id).or_default() += 1;
+        drop(live_reads);
+        #[cfg(feature = "logging")]
+        info!("Beginning read of snapshot '{}' with id={}", name, id);
+        Ok(ReadTransaction::new(self, id, record.data_root))
+    }
+
+    /// List all the snapshots which have been committed
+    // TODO: should return an iterator of &str, once GATs are available
+    pub fn list_snapshots(&self) -> Result<impl Iterator<Item = String>> {
+        let snapshot_root = self.mem.get_snapshot_root().map(|(p, _)| p);
+        let iter: BtreeRangeIter<str, SnapshotRecord> =
+            BtreeRangeIter::new::<RangeFull, str>(.., snapshot_root, &self.mem);
+        let mut names = vec![];
+        for entry in iter {
+            names.push(str::from_bytes(entry.key()).to_string());
+        }
+        Ok(names.into_iter())
+    }
+
+    fn get_snapshot(&self, name: &str) -> Result<Option<SnapshotRecord>> {
+        let tree: Btree<str, SnapshotRecord> = Btree::new(self.mem.get_snapshot_root(), &self.mem);
+        tree.get(name)
     }
 }
 
@@ -412,6 +774,8 @@ pub struct DatabaseBuilder {
     region_size: Option<usize>,
     dynamic_growth: bool,
     use_checksums: Option<bool>,
+    leaked_write_transaction_detection: bool,
+    max_inline_value_size: Option<usize>,
 }
 
 impl DatabaseBuilder {
@@ -422,6 +786,8 @@ impl DatabaseBuilder {
             region_size: None,
             dynamic_growth: true,
             use_checksums: None,
+            leaked_write_transaction_detection: false,
+            max_inline_value_size: None,
         }
     }
 
@@ -457,6 +823,27 @@ impl DatabaseBuilder {
         self
     }
 
+    /// Whether to treat dropping a [`WriteTransaction`] without calling
+    /// [`WriteTransaction::commit`] or [`WriteTransaction::abort`] as a bug.
+    /// When set to true, all future calls to [`Database::begin_write`] will return
+    /// [`Error::LeakedWriteTransaction`] with the location at which it was dropped, which is useful
+    /// for debugging.
+    /// When set to false, the transaction is aborted when it is dropped
+    /// Defaults to false
+    pub fn set_leaked_write_transaction_detection(&mut self, enabled: bool) -> &mut Self {
+        self.leaked_write_transaction_detection = enabled;
+        self
+    }
+
+    /// Set the maximum length of a value that is stored inline, in the leaf pages of a table.
+    /// Longer values of variable width types are stored in their own pages, which keeps the leaves
+    /// small, and makes lookups and range scans faster when large values are interleaved with small ones
+    /// Defaults to `None`, which stores all values inline
+    pub fn set_max_inline_value_size(&mut self, size: Option<usize>) -> &mut Self {
+        self.max_inline_value_size = size;
+        self
+    }
+
     /// Opens the specified file as a redb database.
     /// * if the file does not exist, or is an empty file, a new database will be initialized in it
     /// * if the file is a valid redb database, it will be opened
@@ -474,13 +861,16 @@ impl DatabaseBuilder {
             .create(true)
             .open(path)?;
 
-        Database::new(
+        let mut db = Database::new(
             file,
             db_size,
             self.page_size,
             self.region_size,
             self.dynamic_growth,
             self.use_checksums,
-        )
+            self.leaked_write_transaction_detection,
+        )?;
+        db.max_inline_value_size = self.max_inline_value_size;
+        Ok(db)
     }
 }
@@ -12,10 +12,18 @@ pub enum Error {
         requested_size: usize,
     },
     TableDoesNotExist(String),
+    SnapshotAlreadyExists(String),
+    SnapshotDoesNotExist(String),
+    ChangelogNotEnabled(String),
     LeakedWriteTransaction(&'static panic::Location<'static>),
+    // Another write transaction is in progress, and did not complete before the timeout
+    WriteTransactionInProgress,
     // Tables cannot be opened for writing multiple times, since they could retrieve immutable &
     // mutable references to the same dirty pages, or multiple mutable references via insert_reserve()
     TableAlreadyOpen(String, &'static panic::Location<'static>),
+    // The savepoint was created by a different transaction, or was invalidated by restoring an
+    // earlier savepoint
+    InvalidSavepoint,
     OutOfSpace,
     Io(io::Error),
     LockPoisoned(&'static panic::Location<'static>),
@@ -27,12 +35,54 @@ impl<T> From<PoisonError<T>> for Error {
     }
 }
 
+impl Error {
+    // Copies the error, so that it can be reported to each caller whose writes were committed
+    // together. io::Errors are recreated from their kind and message
+    pub(crate) fn duplicate(&self) -> Error {
+        match self {
+            Error::Corrupted(msg) => Error::Corrupted(msg.clone()),
+            Error::TableTypeMismatch(msg) => Error::TableTypeMismatch(msg.clone()),
+            Error::DbSizeMismatch {
+                path,
+                size,
+                requested_size,
+            } => Error::DbSizeMismatch {
+                path: path.clone(),
+                size: *size,
+                requested_size: *requested_size,
+            },
+            Error::TableDoesNotExist(table) => Error::TableDoesNotExist(table.clone()),
+            Error::SnapshotAlreadyExists(name) => Error::SnapshotAlreadyExists(name.clone()),
+            Error::SnapshotDoesNotExist(name) => Error::SnapshotDoesNotExist(name.clone()),
+            Error::ChangelogNotEnabled(table) => Error::ChangelogNotEnabled(table.clone()),
+            Error::LeakedWriteTransaction(location) => Error::LeakedWriteTransaction(location),
+            Error::WriteTransactionInProgress => Error::WriteTransactionInProgress,
+            Error::TableAlreadyOpen(name, location) => {
+                Error::TableAlreadyOpen(name.clone(), location)
+            }
+            Error::InvalidSavepoint => Error::InvalidSavepoint,
+            Error::OutOfSpace => Error::OutOfSpace,
+            Error::Io(err) => Error::Io(io::Error::new(err.kind(), err.to_string())),
+            Error::LockPoisoned(location) => Error::LockPoisoned(location),
+        }
+    }
+}
+
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Error {
         Error::Io(err)
     }
 }
 
+impl From<Error> for io::Error {
+    fn from(err: Error) -> io::Error {
+        match err {
+            Error::Io(err) => err,
+            err => io::Error::new(io::ErrorKind::Other, err),
+        }
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -56,12 +106,27 @@ impl Display for Error {
             Error::TableDoesNotExist(table) => {
                 write!(f, "Table '{}' does not exist", table)
             }
+            Error::SnapshotAlreadyExists(name) => {
+                write!(f, "Snapshot '{}' already exists", name)
+            }
+            Error::SnapshotDoesNotExist(name) => {
+                write!(f, "Snapshot '{}' does not exist", name)
+            }
+            Error::ChangelogNotEnabled(table) => {
+                write!(f, "Table '{}' does not have a changelog", table)
+            }
             Error::LeakedWriteTransaction(location) => {
                 write!(f, "Leaked write transaction: {}", location)
             }
+            Error::WriteTransactionInProgress => {
+                write!(f, "A write transaction is already in progress")
+            }
             Error::TableAlreadyOpen(name, location) => {
                 write!(f, "Table '{}' already opened at: {}", name, location)
             }
+            Error::InvalidSavepoint => {
+                write!(f, "Savepoint is invalid or cannot be restored")
+            }
             Error::OutOfSpace => {
                 write!(f, "Database is out of space")
             }
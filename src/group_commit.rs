@@ -0,0 +1,174 @@
+use crate::{Database, Durability, Result, WriteTransaction};
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{channel, Sender, TryRecvError};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+
+pub(crate) type GroupWork = Box<dyn FnOnce(&WriteTransaction) -> Result + Send>;
+// A panic in the work is caught, and resumed on the caller's thread
+type GroupResult = thread::Result<Result>;
+
+// Work, the durability requested for it, and the sender for its result
+type QueuedWork = (GroupWork, Durability, Sender<GroupResult>);
+
+struct QueueState {
+    pending: Vec<QueuedWork>,
+    leader_active: bool,
+}
+
+// Queue of work waiting to be committed by Database::group_commit(). Whichever caller finds no
+// leader becomes the leader, and commits all the work queued up to that point in a single write
+// transaction. Work queued while it commits is picked up by the next leader
+pub(crate) struct GroupCommitQueue {
+    state: Mutex<QueueState>,
+    // Signalled when a leader finishes committing a batch
+    leader_done: Condvar,
+}
+
+// Releases leadership, even if the leader panics, so that the waiting callers can elect a new one
+struct LeaderGuard<'a> {
+    queue: &'a GroupCommitQueue,
+}
+
+impl<'a> Drop for LeaderGuard<'a> {
+    fn drop(&mut self) {
+        self.queue.state.lock().unwrap().leader_active = false;
+        self.queue.leader_done.notify_all();
+    }
+}
+
+impl GroupCommitQueue {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                pending: vec![],
+                leader_active: false,
+            }),
+            leader_done: Condvar::new(),
+        }
+    }
+
+    // Queues the work, and blocks until it has been committed, or has failed
+    pub(crate) fn run(&self, db: &Database, work: GroupWork, durability: Durability) -> Result {
+        let (sender, receiver) = channel();
+        let mut state = self.state.lock().unwrap();
+        state.pending.push((work, durability, sender));
+        loop {
+            // Release the lock before returning, so that it's not poisoned by resuming a panic
+            match receiver.try_recv() {
+                Ok(Ok(result)) => {
+                    drop(state);
+                    return result;
+                }
+                Ok(Err(payload)) => {
+                    drop(state);
+                    panic::resume_unwind(payload);
+                }
+                // The leader panicked, while the work was in its batch
+                Err(TryRecvError::Disconnected) => {
+                    drop(state);
+                    panic!("Group commit leader panicked");
+                }
+                Err(TryRecvError::Empty) => {}
+            }
+            if state.leader_active {
+                state = self.leader_done.wait(state).unwrap();
+            } else {
+                state.leader_active = true;
+                let batch = mem::take(&mut state.pending);
+                drop(state);
+                let guard = LeaderGuard { queue: self };
+                commit_batch(db, batch);
+                drop(guard);
+                state = self.state.lock().unwrap();
+            }
+        }
+    }
+}
+
+// Returns the durability which satisfies both requests
+fn strongest(x: Durability, y: Durability) -> Durability {
+    match (x, y) {
+        (Durability::Immediate, _) | (_, Durability::Immediate) => Durability::Immediate,
+        (Durability::Eventual, _) | (_, Durability::Eventual) => Durability::Eventual,
+        (Durability::None, Durability::None) => Durability::None,
+    }
+}
+
+// Applies each work in the batch, and sends its result once the batch has committed
+fn commit_batch(db: &Database, batch: Vec<QueuedWork>) {
+    // The batch is committed with the strongest durability that any of its work requested
+    let durability = batch
+        .iter()
+        .fold(Durability::None, |acc, (_, durability, _)| {
+            strongest(acc, *durability)
+        });
+    // Senders for the work which succeeded, or was stopped by an error in the batch, and is
+    // waiting for the commit
+    let mut waiting = vec![];
+    let mut batch = batch.into_iter();
+    let result = apply_batch(db, durability, &mut batch, &mut waiting);
+    // If applying the batch failed, none of the remaining work was attempted
+    let senders = waiting
+        .into_iter()
+        .chain(batch.map(|(_, _, sender)| sender));
+    for sender in senders {
+        let result = match result {
+            Ok(()) => Ok(()),
+            Err(ref err) => Err(err.duplicate()),
+        };
+        // The caller can't have stopped waiting, unless it panicked
+        let _ = sender.send(Ok(result));
+    }
+}
+
+fn apply_batch(
+    db: &Database,
+    durability: Durability,
+    batch: &mut impl Iterator<Item = QueuedWork>,
+    waiting: &mut Vec<Sender<GroupResult>>,
+) -> Result {
+    let mut txn = db.begin_write()?;
+    txn.set_durability(durability);
+    match apply_work(&mut txn, batch, waiting) {
+        Ok(()) => txn.commit(),
+        Err(err) => {
+            txn.abort()?;
+            Err(err)
+        }
+    }
+}
+
+fn apply_work(
+    txn: &mut WriteTransaction,
+    batch: &mut impl Iterator<Item = QueuedWork>,
+    waiting: &mut Vec<Sender<GroupResult>>,
+) -> Result {
+    for (work, _, sender) in batch {
+        // The savepoint freezes the pages written by the earlier work, so release it as soon as
+        // this work has succeeded, to let the next work modify them in place
+        let savepoint = match txn.savepoint() {
+            Ok(savepoint) => savepoint,
+            Err(err) => {
+                // The work fails with the rest of the batch, and its caller receives the error
+                waiting.push(sender);
+                return Err(err);
+            }
+        };
+        let result = panic::catch_unwind(AssertUnwindSafe(|| work(txn)));
+        if let Ok(Ok(())) = result {
+            waiting.push(sender);
+            txn.release_savepoint(savepoint)?;
+        } else {
+            // Roll back the failed work, so that the rest of the batch can still be committed
+            let restored = txn
+                .restore_savepoint(&savepoint)
+                .and_then(|()| txn.release_savepoint(savepoint));
+            let _ = sender.send(result);
+            restored?;
+        }
+    }
+
+    Ok(())
+}
@@ -3,12 +3,31 @@
 pub use db::{Database, DatabaseBuilder, MultimapTableDefinition, TableDefinition, WriteStrategy};
 pub use error::Error;
 pub use multimap_table::{
-    MultimapRangeIter, MultimapTable, MultimapValueIter, ReadOnlyMultimapTable,
+    MultimapKeyIter, MultimapRangeIter, MultimapTable, MultimapValueIter, ReadOnlyMultimapTable,
     ReadableMultimapTable,
 };
-pub use table::{RangeIter, ReadOnlyTable, ReadableTable, Table};
-pub use transactions::{DatabaseStats, Durability, ReadTransaction, WriteTransaction};
-pub use tree_store::AccessGuard;
+pub use table::{
+    Cursor, CursorMut, Drain, Entry, KeyIter, OccupiedEntry, RangeIter, ReadOnlyTable,
+    ReadOnlyUntypedTable, ReadableTable, Table, UntypedRangeIter, VacantEntry, ValueIter,
+};
+pub use transactions::{
+    CommitEvent, DatabaseStats, Durability, ReadTransaction, Savepoint, WriteTransaction,
+};
+pub use tree_store::{
+    AccessGuard, AccessGuardMut, BlobReader, BlobWriter, Change, ChangeIter, ChangeKind,
+};
+pub use types::{
+    AsBytesWithLifetime, OwnedAsBytesLifetime, OwnedLifetime, RedbKey, RedbValue,
+    RefAsBytesLifetime, RefLifetime, WithLifetime,
+};
+pub use watch::Watch;
+pub use write_batch::WriteBatch;
+
+// Used by the code generated by the redb-derive crate
+#[doc(hidden)]
+pub mod derive_helpers {
+    pub use crate::types::{push_element, split_element};
+}
 
 type Result<T = (), E = Error> = std::result::Result<T, E>;
 
@@ -17,6 +36,7 @@ pub use crate::python::redb;
 
 mod db;
 mod error;
+mod group_commit;
 mod multimap_table;
 #[cfg(feature = "python")]
 mod python;
@@ -24,3 +44,5 @@ mod table;
 mod transactions;
 mod tree_store;
 mod types;
+mod watch;
+mod write_batch;
@@ -1,16 +1,18 @@
 use crate::tree_store::{
-    Btree, BtreeMut, BtreeRangeIter, Checksum, PageNumber, TransactionalMemory,
+    Btree, BtreeMut, BtreeRangeIter, ChangeKind, ChangelogWriter, Checksum,
+    InternalTableDefinition, PageNumber, TableRoots, TransactionalMemory,
 };
 use crate::types::{
     AsBytesWithLifetime, RedbKey, RedbValue, RefAsBytesLifetime, RefLifetime, WithLifetime,
 };
-use crate::{Result, WriteTransaction};
+use crate::{AccessGuard, Drain, Result, WriteTransaction};
+use std::borrow::Borrow;
 use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::collections::Bound;
 use std::convert::TryInto;
 use std::marker::PhantomData;
-use std::ops::RangeBounds;
+use std::ops::{RangeBounds, RangeFull, RangeInclusive};
 use std::rc::Rc;
 
 #[derive(Eq, PartialEq)]
@@ -180,13 +182,26 @@ impl<'a, K: RedbKey + ?Sized + 'a, V: RedbKey + ?Sized + 'a> MultimapKVPairAcces
 }
 
 fn make_serialized_key_with_op<K: RedbKey + ?Sized>(key: &K, op: MultimapKeyCompareOp) -> Vec<u8> {
+    make_serialized_key_bytes_with_op(key.as_bytes().as_ref(), op)
+}
+
+fn make_serialized_key_bytes_with_op(key: &[u8], op: MultimapKeyCompareOp) -> Vec<u8> {
     let mut result = vec![op.serialize()];
-    result.extend_from_slice(&(key.as_bytes().as_ref().len() as u32).to_le_bytes());
-    result.extend_from_slice(key.as_bytes().as_ref());
+    result.extend_from_slice(&(key.len() as u32).to_le_bytes());
+    result.extend_from_slice(key);
 
     result
 }
 
+// Returns the range of stored key-value pairs which have the given key
+fn make_key_query_range<K: RedbKey + ?Sized, V: RedbKey + ?Sized>(
+    key: &[u8],
+) -> RangeInclusive<MultimapKVPair<K, V>> {
+    let lower = make_serialized_key_bytes_with_op(key, MultimapKeyCompareOp::KeyMinusEpsilon);
+    let upper = make_serialized_key_bytes_with_op(key, MultimapKeyCompareOp::KeyPlusEpsilon);
+    MultimapKVPair::new(lower)..=MultimapKVPair::new(upper)
+}
+
 // Takes a key range and a lower & upper query bound to be used with an inclusive lower & upper bound
 // Returns None if the bound is Unbounded
 fn make_inclusive_query_range<'a, K: RedbKey + ?Sized + 'a, T: RangeBounds<&'a K>>(
@@ -219,6 +234,20 @@ fn make_inclusive_query_range<'a, K: RedbKey + ?Sized + 'a, T: RangeBounds<&'a K
     (start, end)
 }
 
+// Splits a stored key-value pair into its key and value
+fn split_pair<'a, K: RedbKey + ?Sized + 'a, V: RedbKey + ?Sized + 'a>(
+    (pair, _): (&'a [u8], &'a [u8]),
+) -> (
+    <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+    <<V as RedbValue>::View as WithLifetime<'a>>::Out,
+) {
+    let pair = MultimapKVPairAccessor::<K, V>::new(pair);
+    (
+        K::from_bytes(pair.key_bytes()),
+        V::from_bytes(pair.value_bytes()),
+    )
+}
+
 fn make_bound<'a, K: RedbKey + ?Sized + 'a, V: RedbKey + ?Sized + 'a>(
     included_or_unbounded: Option<MultimapKVPair<K, V>>,
 ) -> Bound<MultimapKVPair<K, V>> {
@@ -229,7 +258,7 @@ fn make_bound<'a, K: RedbKey + ?Sized + 'a, V: RedbKey + ?Sized + 'a>(
     }
 }
 
-#[doc(hidden)]
+/// A double-ended iterator over values in a multimap table
 pub struct MultimapValueIter<'a, K: RedbKey + ?Sized + 'a, V: RedbKey + ?Sized + 'a> {
     inner: BtreeRangeIter<'a, MultimapKVPair<K, V>, [u8]>,
 }
@@ -238,28 +267,60 @@ impl<'a, K: RedbKey + ?Sized + 'a, V: RedbKey + ?Sized + 'a> MultimapValueIter<'
     fn new(inner: BtreeRangeIter<'a, MultimapKVPair<K, V>, [u8]>) -> Self {
         Self { inner }
     }
+}
 
-    // TODO: implement Iter when GATs are stable
-    #[allow(clippy::should_implement_trait)]
-    pub fn next(&mut self) -> Option<<<V as RedbValue>::View as WithLifetime>::Out> {
-        if let Some(entry) = self.inner.next() {
-            let pair = MultimapKVPairAccessor::<K, V> {
-                data: entry.key(),
-                _key_type: Default::default(),
-                _value_type: Default::default(),
-            };
-            Some(V::from_bytes(pair.value_bytes()))
-        } else {
-            None
-        }
+impl<'a, K: RedbKey + ?Sized + 'a, V: RedbKey + ?Sized + 'a> Iterator
+    for MultimapValueIter<'a, K, V>
+{
+    type Item = <<V as RedbValue>::View as WithLifetime<'a>>::Out;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|entry| split_pair::<K, V>((entry.key(), entry.value())).1)
+    }
+}
+
+impl<'a, K: RedbKey + ?Sized + 'a, V: RedbKey + ?Sized + 'a> DoubleEndedIterator
+    for MultimapValueIter<'a, K, V>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|entry| split_pair::<K, V>((entry.key(), entry.value())).1)
+    }
+}
+
+/// A double-ended iterator over the keys in a range of a multimap table
+///
+/// Keys are returned once for each of their values
+pub struct MultimapKeyIter<'a, K: RedbKey + ?Sized + 'a, V: RedbKey + ?Sized + 'a> {
+    inner: BtreeRangeIter<'a, MultimapKVPair<K, V>, [u8]>,
+}
+
+impl<'a, K: RedbKey + ?Sized + 'a, V: RedbKey + ?Sized + 'a> Iterator
+    for MultimapKeyIter<'a, K, V>
+{
+    type Item = <<K as RedbValue>::View as WithLifetime<'a>>::Out;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|entry| split_pair::<K, V>((entry.key(), entry.value())).0)
     }
+}
 
-    pub fn rev(self) -> Self {
-        Self::new(self.inner.reverse())
+impl<'a, K: RedbKey + ?Sized + 'a, V: RedbKey + ?Sized + 'a> DoubleEndedIterator
+    for MultimapKeyIter<'a, K, V>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|entry| split_pair::<K, V>((entry.key(), entry.value())).0)
     }
 }
 
-#[doc(hidden)]
+/// A double-ended iterator over a range of key-value pairs in a multimap table
 pub struct MultimapRangeIter<'a, K: RedbKey + ?Sized + 'a, V: RedbKey + ?Sized + 'a> {
     inner: BtreeRangeIter<'a, MultimapKVPair<K, V>, [u8]>,
 }
@@ -269,32 +330,39 @@ impl<'a, K: RedbKey + ?Sized + 'a, V: RedbKey + ?Sized + 'a> MultimapRangeIter<'
         Self { inner }
     }
 
-    // TODO: Simplify this when GATs are stable
-    #[allow(clippy::type_complexity)]
-    // TODO: implement Iter when GATs are stable
-    #[allow(clippy::should_implement_trait)]
-    pub fn next(
-        &mut self,
-    ) -> Option<(
-        <<K as RedbValue>::View as WithLifetime>::Out,
-        <<V as RedbValue>::View as WithLifetime>::Out,
-    )> {
-        if let Some(entry) = self.inner.next() {
-            let pair = MultimapKVPairAccessor::<K, V> {
-                data: entry.key(),
-                _key_type: Default::default(),
-                _value_type: Default::default(),
-            };
-            let key = K::from_bytes(pair.key_bytes());
-            let value = V::from_bytes(pair.value_bytes());
-            Some((key, value))
-        } else {
-            None
-        }
+    /// Converts this into an iterator over only the keys in the range
+    pub fn keys(self) -> MultimapKeyIter<'a, K, V> {
+        MultimapKeyIter { inner: self.inner }
+    }
+
+    /// Converts this into an iterator over only the values in the range
+    pub fn values(self) -> MultimapValueIter<'a, K, V> {
+        MultimapValueIter::new(self.inner)
+    }
+}
+
+impl<'a, K: RedbKey + ?Sized + 'a, V: RedbKey + ?Sized + 'a> Iterator
+    for MultimapRangeIter<'a, K, V>
+{
+    type Item = (
+        <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+        <<V as RedbValue>::View as WithLifetime<'a>>::Out,
+    );
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|entry| split_pair::<K, V>((entry.key(), entry.value())))
     }
+}
 
-    pub fn rev(self) -> Self {
-        Self::new(self.inner.reverse())
+impl<'a, K: RedbKey + ?Sized + 'a, V: RedbKey + ?Sized + 'a> DoubleEndedIterator
+    for MultimapRangeIter<'a, K, V>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|entry| split_pair::<K, V>((entry.key(), entry.value())))
     }
 }
 
@@ -305,13 +373,14 @@ pub struct MultimapTable<'db, 'txn, K: RedbKey + ?Sized, V: RedbKey + ?Sized> {
     name: String,
     transaction: &'txn WriteTransaction<'db>,
     tree: BtreeMut<'txn, MultimapKVPair<K, V>, [u8]>,
+    changelog: ChangelogWriter<'txn>,
     mem: &'db TransactionalMemory,
 }
 
 impl<'db, 'txn, K: RedbKey + ?Sized, V: RedbKey + ?Sized> MultimapTable<'db, 'txn, K, V> {
     pub(crate) fn new(
         name: &str,
-        table_root: Option<(PageNumber, Checksum)>,
+        definition: &InternalTableDefinition,
         freed_pages: Rc<RefCell<Vec<PageNumber>>>,
         mem: &'db TransactionalMemory,
         transaction: &'txn WriteTransaction<'db>,
@@ -319,11 +388,33 @@ impl<'db, 'txn, K: RedbKey + ?Sized, V: RedbKey + ?Sized> MultimapTable<'db, 'tx
         MultimapTable {
             name: name.to_string(),
             transaction,
-            tree: BtreeMut::new(table_root, mem, freed_pages),
+            tree: BtreeMut::new(definition.get_root(), mem, freed_pages.clone()),
+            changelog: ChangelogWriter::new(
+                definition.get_changelog_root(),
+                definition.get_changelog_mode(),
+                transaction.transaction_id(),
+                mem,
+                freed_pages,
+            ),
             mem,
         }
     }
 
+    // Returns the key of a stored key-value pair, along with all of the values for that key
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    fn key_and_values<'a>(
+        &'a self,
+        pair: &'a [u8],
+    ) -> Result<(
+        <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+        MultimapValueIter<'a, K, V>,
+    )> {
+        let key = MultimapKVPairAccessor::<K, V>::new(pair).key_bytes();
+        let values = self.tree.range(make_key_query_range::<K, V>(key))?;
+        Ok((K::from_bytes(key), MultimapValueIter::new(values)))
+    }
+
     #[allow(dead_code)]
     pub(crate) fn print_debug(&self, include_values: bool) {
         self.tree.print_debug(include_values);
@@ -337,7 +428,16 @@ impl<'db, 'txn, K: RedbKey + ?Sized, V: RedbKey + ?Sized> MultimapTable<'db, 'tx
         // Safety: No other references to this table can exist.
         // Tables can only be opened mutably in one location (see Error::TableAlreadyOpen),
         // and we borrow &mut self.
-        unsafe { self.tree.insert(&kv, b"").map(|x| x.is_some()) }
+        let existed = unsafe { self.tree.insert(&kv, b"")?.is_some() };
+        if !existed {
+            self.changelog.record(
+                ChangeKind::Insert,
+                key.as_bytes().as_ref(),
+                None,
+                Some(value.as_bytes().as_ref()),
+            )?;
+        }
+        Ok(existed)
     }
 
     /// Removes the given key-value pair
@@ -348,7 +448,16 @@ impl<'db, 'txn, K: RedbKey + ?Sized, V: RedbKey + ?Sized> MultimapTable<'db, 'tx
         // Safety: No other references to this table can exist.
         // Tables can only be opened mutably in one location (see Error::TableAlreadyOpen),
         // and we borrow &mut self.
-        unsafe { self.tree.remove(&kv).map(|x| x.is_some()) }
+        let existed = unsafe { self.tree.remove(&kv)?.is_some() };
+        if existed {
+            self.changelog.record(
+                ChangeKind::Remove,
+                key.as_bytes().as_ref(),
+                Some(value.as_bytes().as_ref()),
+                None,
+            )?;
+        }
+        Ok(existed)
     }
 
     /// Removes all values for the given key
@@ -372,10 +481,134 @@ impl<'db, 'txn, K: RedbKey + ?Sized, V: RedbKey + ?Sized> MultimapTable<'db, 'tx
         let upper_bytes = make_serialized_key_with_op(key, MultimapKeyCompareOp::KeyPlusEpsilon);
         let lower = MultimapKVPair::<K, V>::new(lower_bytes);
         let upper = MultimapKVPair::<K, V>::new(upper_bytes);
+        if self.changelog.enabled() {
+            for entry in original_tree
+                .range::<RangeInclusive<&MultimapKVPair<K, V>>, &MultimapKVPair<K, V>>(
+                    &lower..=&upper,
+                )?
+            {
+                let pair = MultimapKVPairAccessor::<K, V>::new(entry.key());
+                self.changelog.record(
+                    ChangeKind::Remove,
+                    pair.key_bytes(),
+                    Some(pair.value_bytes()),
+                    None,
+                )?;
+            }
+        }
         original_tree
             .range(lower..=upper)
             .map(MultimapValueIter::new)
     }
+
+    /// Removes all key-value pairs whose key is in the given range
+    ///
+    /// Returns an iterator over the removed key-value pairs
+    pub fn drain<'a, T: RangeBounds<&'a K> + 'a>(&mut self, range: T) -> Result<Drain<K, V>>
+    where
+        K: 'a,
+    {
+        let (start_bytes, end_bytes) = make_inclusive_query_range(range);
+        let start_kv = start_bytes.map(MultimapKVPair::<K, V>::new);
+        let end_kv = end_bytes.map(MultimapKVPair::<K, V>::new);
+        let start = make_bound(start_kv);
+        let end = make_bound(end_kv);
+
+        self.drain_filter_range((start, end), |_, _| true)
+    }
+
+    /// Removes all key-value pairs for which the predicate returns `true`
+    ///
+    /// Returns an iterator over the removed key-value pairs
+    pub fn drain_filter<
+        F: for<'f> FnMut(
+            <<K as RedbValue>::View as WithLifetime<'f>>::Out,
+            <<V as RedbValue>::View as WithLifetime<'f>>::Out,
+        ) -> bool,
+    >(
+        &mut self,
+        predicate: F,
+    ) -> Result<Drain<K, V>> {
+        self.drain_filter_range::<RangeFull, MultimapKVPair<K, V>, F>(.., predicate)
+    }
+
+    /// Retains only the key-value pairs for which the predicate returns `true`
+    pub fn retain<
+        F: for<'f> FnMut(
+            <<K as RedbValue>::View as WithLifetime<'f>>::Out,
+            <<V as RedbValue>::View as WithLifetime<'f>>::Out,
+        ) -> bool,
+    >(
+        &mut self,
+        mut predicate: F,
+    ) -> Result {
+        if self.changelog.enabled() {
+            // The removed pairs have to be collected, so that they can be recorded
+            return self
+                .drain_filter_range::<RangeFull, MultimapKVPair<K, V>, _>(.., |key, value| {
+                    !predicate(key, value)
+                })
+                .map(|_| ());
+        }
+        // Safety: No other references to this table can exist.
+        // Tables can only be opened mutably in one location (see Error::TableAlreadyOpen),
+        // and we borrow &mut self.
+        unsafe {
+            self.tree
+                .delete_filter::<RangeFull, MultimapKVPair<K, V>, _>(.., |pair, _| {
+                    let pair = MultimapKVPairAccessor::<K, V>::new(pair);
+                    !predicate(
+                        K::from_bytes(pair.key_bytes()),
+                        V::from_bytes(pair.value_bytes()),
+                    )
+                })
+        }
+    }
+
+    fn drain_filter_range<
+        T: RangeBounds<KR>,
+        KR: Borrow<MultimapKVPair<K, V>> + ?Sized,
+        F: for<'f> FnMut(
+            <<K as RedbValue>::View as WithLifetime<'f>>::Out,
+            <<V as RedbValue>::View as WithLifetime<'f>>::Out,
+        ) -> bool,
+    >(
+        &mut self,
+        range: T,
+        mut predicate: F,
+    ) -> Result<Drain<K, V>> {
+        let mem = self.mem;
+        let mut removed = vec![];
+        // Safety: No other references to this table can exist.
+        // Tables can only be opened mutably in one location (see Error::TableAlreadyOpen),
+        // and we borrow &mut self.
+        unsafe {
+            self.tree.delete_filter(range, |pair, _| {
+                let pair = MultimapKVPairAccessor::<K, V>::new(pair);
+                if predicate(
+                    K::from_bytes(pair.key_bytes()),
+                    V::from_bytes(pair.value_bytes()),
+                ) {
+                    removed.push((
+                        AccessGuard::with_owned_value(pair.key_bytes().to_vec(), mem),
+                        AccessGuard::with_owned_value(pair.value_bytes().to_vec(), mem),
+                    ));
+                    true
+                } else {
+                    false
+                }
+            })?;
+        }
+        for (key, value) in removed.iter() {
+            self.changelog.record(
+                ChangeKind::Remove,
+                key.value_bytes(),
+                Some(value.value_bytes()),
+                None,
+            )?;
+        }
+        Ok(Drain::new(removed))
+    }
 }
 
 impl<'db, 'txn, K: RedbKey + ?Sized, V: RedbKey + ?Sized> ReadableMultimapTable<K, V>
@@ -404,6 +637,98 @@ impl<'db, 'txn, K: RedbKey + ?Sized, V: RedbKey + ?Sized> ReadableMultimapTable<
         self.tree.range((start, end)).map(MultimapRangeIter::new)
     }
 
+    /// Returns the first key-value pair in the table, if it is not empty
+    fn first(
+        &self,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        Ok(self.tree.first()?.map(split_pair::<K, V>))
+    }
+
+    /// Returns the last key-value pair in the table, if it is not empty
+    fn last(
+        &self,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        Ok(self.tree.last()?.map(split_pair::<K, V>))
+    }
+
+    /// Returns the largest key less than or equal to `key`, and an iterator over its values
+    fn floor<'a>(
+        &'a self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+            MultimapValueIter<'a, K, V>,
+        )>,
+    > {
+        let query = make_serialized_key_with_op(key, MultimapKeyCompareOp::KeyPlusEpsilon);
+        match self.tree.floor(&MultimapKVPair::new(query))? {
+            Some((pair, _)) => self.key_and_values(pair).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the smallest key greater than or equal to `key`, and an iterator over its values
+    fn ceiling<'a>(
+        &'a self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+            MultimapValueIter<'a, K, V>,
+        )>,
+    > {
+        let query = make_serialized_key_with_op(key, MultimapKeyCompareOp::KeyMinusEpsilon);
+        match self.tree.ceiling(&MultimapKVPair::new(query))? {
+            Some((pair, _)) => self.key_and_values(pair).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the largest key strictly less than `key`, and an iterator over its values
+    fn predecessor<'a>(
+        &'a self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+            MultimapValueIter<'a, K, V>,
+        )>,
+    > {
+        let query = make_serialized_key_with_op(key, MultimapKeyCompareOp::KeyMinusEpsilon);
+        match self.tree.floor(&MultimapKVPair::new(query))? {
+            Some((pair, _)) => self.key_and_values(pair).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the smallest key strictly greater than `key`, and an iterator over its values
+    fn successor<'a>(
+        &'a self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+            MultimapValueIter<'a, K, V>,
+        )>,
+    > {
+        let query = make_serialized_key_with_op(key, MultimapKeyCompareOp::KeyPlusEpsilon);
+        match self.tree.ceiling(&MultimapKVPair::new(query))? {
+            Some((pair, _)) => self.key_and_values(pair).map(Some),
+            None => Ok(None),
+        }
+    }
+
     /// Returns the number of key-value pairs in the table
     fn len(&self) -> Result<usize> {
         self.tree.len()
@@ -417,7 +742,12 @@ impl<'db, 'txn, K: RedbKey + ?Sized, V: RedbKey + ?Sized> ReadableMultimapTable<
 
 impl<'db, 'txn, K: RedbKey + ?Sized, V: RedbKey + ?Sized> Drop for MultimapTable<'db, 'txn, K, V> {
     fn drop(&mut self) {
-        self.transaction.close_table(&self.name, &mut self.tree);
+        let roots = TableRoots {
+            table_root: self.tree.get_root(),
+            blob_root: None,
+            changelog_root: self.changelog.get_root(),
+        };
+        self.transaction.close_table(&self.name, roots);
     }
 }
 
@@ -429,6 +759,76 @@ pub trait ReadableMultimapTable<K: RedbKey + ?Sized, V: RedbKey + ?Sized> {
         range: T,
     ) -> Result<MultimapRangeIter<'a, K, V>>;
 
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    fn first(
+        &self,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    >;
+
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    fn last(
+        &self,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    >;
+
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    fn floor<'a>(
+        &'a self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+            MultimapValueIter<'a, K, V>,
+        )>,
+    >;
+
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    fn ceiling<'a>(
+        &'a self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+            MultimapValueIter<'a, K, V>,
+        )>,
+    >;
+
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    fn predecessor<'a>(
+        &'a self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+            MultimapValueIter<'a, K, V>,
+        )>,
+    >;
+
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    fn successor<'a>(
+        &'a self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+            MultimapValueIter<'a, K, V>,
+        )>,
+    >;
+
     fn len(&self) -> Result<usize>;
 
     fn is_empty(&self) -> Result<bool>;
@@ -448,6 +848,21 @@ impl<'txn, K: RedbKey + ?Sized, V: RedbKey + ?Sized> ReadOnlyMultimapTable<'txn,
             tree: Btree::new(root_page, mem),
         }
     }
+
+    // Returns the key of a stored key-value pair, along with all of the values for that key
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    fn key_and_values<'a>(
+        &'a self,
+        pair: &'a [u8],
+    ) -> Result<(
+        <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+        MultimapValueIter<'a, K, V>,
+    )> {
+        let key = MultimapKVPairAccessor::<K, V>::new(pair).key_bytes();
+        let values = self.tree.range(make_key_query_range::<K, V>(key))?;
+        Ok((K::from_bytes(key), MultimapValueIter::new(values)))
+    }
 }
 
 impl<'txn, K: RedbKey + ?Sized, V: RedbKey + ?Sized> ReadableMultimapTable<K, V>
@@ -474,6 +889,92 @@ impl<'txn, K: RedbKey + ?Sized, V: RedbKey + ?Sized> ReadableMultimapTable<K, V>
         self.tree.range((start, end)).map(MultimapRangeIter::new)
     }
 
+    fn first(
+        &self,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        Ok(self.tree.first()?.map(split_pair::<K, V>))
+    }
+
+    fn last(
+        &self,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        Ok(self.tree.last()?.map(split_pair::<K, V>))
+    }
+
+    fn floor<'a>(
+        &'a self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+            MultimapValueIter<'a, K, V>,
+        )>,
+    > {
+        let query = make_serialized_key_with_op(key, MultimapKeyCompareOp::KeyPlusEpsilon);
+        match self.tree.floor(&MultimapKVPair::new(query))? {
+            Some((pair, _)) => self.key_and_values(pair).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn ceiling<'a>(
+        &'a self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+            MultimapValueIter<'a, K, V>,
+        )>,
+    > {
+        let query = make_serialized_key_with_op(key, MultimapKeyCompareOp::KeyMinusEpsilon);
+        match self.tree.ceiling(&MultimapKVPair::new(query))? {
+            Some((pair, _)) => self.key_and_values(pair).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn predecessor<'a>(
+        &'a self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+            MultimapValueIter<'a, K, V>,
+        )>,
+    > {
+        let query = make_serialized_key_with_op(key, MultimapKeyCompareOp::KeyMinusEpsilon);
+        match self.tree.floor(&MultimapKVPair::new(query))? {
+            Some((pair, _)) => self.key_and_values(pair).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn successor<'a>(
+        &'a self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+            MultimapValueIter<'a, K, V>,
+        )>,
+    > {
+        let query = make_serialized_key_with_op(key, MultimapKeyCompareOp::KeyPlusEpsilon);
+        match self.tree.ceiling(&MultimapKVPair::new(query))? {
+            Some((pair, _)) => self.key_and_values(pair).map(Some),
+            None => Ok(None),
+        }
+    }
+
     fn len(&self) -> Result<usize> {
         self.tree.len()
     }
@@ -1,33 +1,52 @@
 use crate::tree_store::{
-    AccessGuardMut, Btree, BtreeMut, BtreeRangeIter, Checksum, PageNumber, TransactionalMemory,
+    AccessGuardMut, BlobReader, BlobWriter, Btree, BtreeCursor, BtreeMut, BtreeRangeIter,
+    ChangeKind, ChangelogWriter, Checksum, EntryAccessor, InternalTableDefinition, PageNumber,
+    RawBtree, TableRoots, TransactionalMemory,
 };
 use crate::types::{RedbKey, RedbValue, WithLifetime};
 use crate::Result;
 use crate::{AccessGuard, WriteTransaction};
 use std::borrow::Borrow;
 use std::cell::RefCell;
-use std::ops::RangeBounds;
+use std::mem;
+use std::ops::{RangeBounds, RangeFull};
 use std::rc::Rc;
+use std::vec;
 
 /// A table containing key-value mappings
 pub struct Table<'db, 'txn, K: RedbKey + ?Sized, V: RedbValue + ?Sized> {
     name: String,
     transaction: &'txn WriteTransaction<'db>,
     tree: BtreeMut<'txn, K, V>,
+    // Maps keys to the headers of their blobs
+    blobs: BtreeMut<'txn, K, [u8]>,
+    changelog: ChangelogWriter<'txn>,
 }
 
 impl<'db, 'txn, K: RedbKey + ?Sized, V: RedbValue + ?Sized> Table<'db, 'txn, K, V> {
     pub(crate) fn new(
         name: &str,
-        table_root: Option<(PageNumber, Checksum)>,
+        definition: &InternalTableDefinition,
+        max_inline_value_size: Option<usize>,
         freed_pages: Rc<RefCell<Vec<PageNumber>>>,
         mem: &'db TransactionalMemory,
         transaction: &'txn WriteTransaction<'db>,
     ) -> Table<'db, 'txn, K, V> {
+        let mut tree = BtreeMut::new(definition.get_root(), mem, freed_pages.clone());
+        tree.set_max_inline_value_size(max_inline_value_size);
+        tree.set_out_of_line_values(definition.has_out_of_line_values());
         Table {
             name: name.to_string(),
             transaction,
-            tree: BtreeMut::new(table_root, mem, freed_pages),
+            tree,
+            blobs: BtreeMut::new(definition.get_blob_root(), mem, freed_pages.clone()),
+            changelog: ChangelogWriter::new(
+                definition.get_changelog_root(),
+                definition.get_changelog_mode(),
+                transaction.transaction_id(),
+                mem,
+                freed_pages,
+            ),
         }
     }
 
@@ -40,29 +59,344 @@ impl<'db, 'txn, K: RedbKey + ?Sized, V: RedbValue + ?Sized> Table<'db, 'txn, K,
     ///
     /// Returns the old value, if the key was present in the table
     pub fn insert(&mut self, key: &K, value: &V) -> Result<Option<AccessGuard<V>>> {
+        self.insert_serialized(key.as_bytes().as_ref(), value.as_bytes().as_ref())
+    }
+
+    // Same as insert(), but with the key and value already serialized
+    pub(crate) fn insert_serialized(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<Option<AccessGuard<V>>> {
         // Safety: No other references to this table can exist.
         // Tables can only be opened mutably in one location (see Error::TableAlreadyOpen),
         // and we borrow &mut self.
-        unsafe { self.tree.insert(key, value) }
+        let old = unsafe { self.tree.insert_bytes(key, value)? };
+        if self.changelog.enabled() {
+            let kind = if old.is_some() {
+                ChangeKind::Update
+            } else {
+                ChangeKind::Insert
+            };
+            self.changelog.record(
+                kind,
+                key,
+                old.as_ref().map(AccessGuard::value_bytes),
+                Some(value),
+            )?;
+        }
+        Ok(old)
     }
 
     /// Reserve space to insert a key-value pair
     /// The returned reference will have length equal to value_length
+    ///
+    /// If the table has a changelog, the insertion is recorded in it without its values: the
+    /// value written through the returned reference is only known once the reference is dropped.
+    /// [`Change::old_value`](crate::Change::old_value) and
+    /// [`Change::new_value`](crate::Change::new_value) of the recorded change are always `None`
     pub fn insert_reserve(&mut self, key: &K, value_length: usize) -> Result<AccessGuardMut> {
+        // Looking up the key is only needed to tell an insertion from an update in the changelog
+        if self.changelog.enabled() {
+            let kind = if self.tree.get(key)?.is_some() {
+                ChangeKind::Update
+            } else {
+                ChangeKind::Insert
+            };
+            self.changelog
+                .record(kind, key.as_bytes().as_ref(), None, None)?;
+        }
         // Safety: No other references to this table can exist.
         // Tables can only be opened mutably in one location (see Error::TableAlreadyOpen),
         // and we borrow &mut self.
         unsafe { self.tree.insert_reserve(key, value_length) }
     }
 
+    /// Insert a blob with the given key, replacing any existing blob with that key
+    ///
+    /// Blobs are stored separately from the other entries in the table, and are not limited in
+    /// size by the database's region size. A key may have both a value and a blob, and they are
+    /// independent of each other
+    ///
+    /// The contents of the blob are written through the returned [`BlobWriter`]
+    pub fn insert_blob<'a>(&'a mut self, key: &'a K) -> Result<BlobWriter<'a, 'txn, K>> {
+        // Safety: No other references to this table can exist.
+        // Tables can only be opened mutably in one location (see Error::TableAlreadyOpen),
+        // and we borrow &mut self.
+        unsafe {
+            BlobWriter::remove(&mut self.blobs, key)?;
+        }
+        Ok(BlobWriter::new(&mut self.blobs, key))
+    }
+
+    /// Opens the existing blob with the given key for writing, starting at its beginning
+    ///
+    /// Use [`std::io::Seek`] on the returned writer to overwrite a range within the blob, or to
+    /// append to it
+    pub fn open_blob<'a>(&'a mut self, key: &'a K) -> Result<Option<BlobWriter<'a, 'txn, K>>> {
+        BlobWriter::open(&mut self.blobs, key)
+    }
+
+    /// Removes the blob with the given key
+    ///
+    /// Returns `true` if the blob existed
+    pub fn remove_blob(&mut self, key: &K) -> Result<bool> {
+        // Safety: No other references to this table can exist.
+        // Tables can only be opened mutably in one location (see Error::TableAlreadyOpen),
+        // and we borrow &mut self.
+        unsafe { BlobWriter::remove(&mut self.blobs, key) }
+    }
+
+    /// Insert mapping of the given key to the given value, if the key is not present in the table
+    ///
+    /// Returns `true` if the value was inserted
+    pub fn insert_if_absent(&mut self, key: &K, value: &V) -> Result<bool> {
+        // Safety: No other references to this table can exist.
+        // Tables can only be opened mutably in one location (see Error::TableAlreadyOpen),
+        // and we borrow &mut self.
+        let inserted = unsafe { self.tree.insert_if_absent(key, value)? };
+        if inserted && self.changelog.enabled() {
+            self.changelog.record(
+                ChangeKind::Insert,
+                key.as_bytes().as_ref(),
+                None,
+                Some(value.as_bytes().as_ref()),
+            )?;
+        }
+        Ok(inserted)
+    }
+
+    /// Replaces the value of the given key with `new`, if its current value is `expected`.
+    /// If `expected` is `None`, the key must not be present in the table
+    ///
+    /// Values are compared by their serialized bytes. Returns `true` if the value was replaced
+    pub fn compare_and_swap(&mut self, key: &K, expected: Option<&V>, new: &V) -> Result<bool> {
+        // Safety: No other references to this table can exist.
+        // Tables can only be opened mutably in one location (see Error::TableAlreadyOpen),
+        // and we borrow &mut self.
+        let swapped = unsafe { self.tree.compare_and_swap(key, expected, new)? };
+        if swapped && self.changelog.enabled() {
+            let kind = if expected.is_some() {
+                ChangeKind::Update
+            } else {
+                ChangeKind::Insert
+            };
+            let expected = expected.map(|x| x.as_bytes());
+            self.changelog.record(
+                kind,
+                key.as_bytes().as_ref(),
+                expected.as_ref().map(|x| x.as_ref()),
+                Some(new.as_bytes().as_ref()),
+            )?;
+        }
+        Ok(swapped)
+    }
+
+    /// Returns the entry for the given key, which can be used to inspect and modify it in place
+    ///
+    /// The key is looked up once, and the value of an occupied entry is copied into it
+    pub fn entry<'a>(&'a mut self, key: &'a K) -> Result<Entry<'a, 'db, 'txn, K, V>> {
+        let entry = if let Some(value) = self.tree.get_owned(key)? {
+            Entry::Occupied(OccupiedEntry {
+                table: self,
+                key,
+                value,
+            })
+        } else {
+            Entry::Vacant(VacantEntry { table: self, key })
+        };
+        Ok(entry)
+    }
+
+    // Inserts the value, if the key is not present. Returns the value stored under the key, and
+    // whether it was inserted
+    fn get_or_insert(&mut self, key: &K, value: &V) -> Result<(AccessGuard<V>, bool)> {
+        // Safety: No other references to this table can exist.
+        // Tables can only be opened mutably in one location (see Error::TableAlreadyOpen),
+        // and we borrow &mut self.
+        let (stored, inserted) = unsafe { self.tree.get_or_insert(key, value)? };
+        if inserted && self.changelog.enabled() {
+            self.changelog.record(
+                ChangeKind::Insert,
+                key.as_bytes().as_ref(),
+                None,
+                Some(value.as_bytes().as_ref()),
+            )?;
+        }
+        Ok((stored, inserted))
+    }
+
     /// Removes the given key
     ///
     /// Returns the old value, if the key was present in the table
     pub fn remove(&mut self, key: &K) -> Result<Option<AccessGuard<V>>> {
+        self.remove_serialized(key.as_bytes().as_ref())
+    }
+
+    // Same as remove(), but with the key already serialized
+    pub(crate) fn remove_serialized(&mut self, key: &[u8]) -> Result<Option<AccessGuard<V>>> {
+        // Safety: No other references to this table can exist.
+        // Tables can only be opened mutably in one location (see Error::TableAlreadyOpen),
+        // and we borrow &mut self.
+        let removed = unsafe { self.tree.remove_bytes(key)? };
+        if let Some(ref value) = removed {
+            self.changelog
+                .record(ChangeKind::Remove, key, Some(value.value_bytes()), None)?;
+        }
+        Ok(removed)
+    }
+
+    /// Removes the first entry in the table
+    ///
+    /// Returns the removed key and value, if the table was not empty
+    pub fn pop_first(&mut self) -> Result<Option<(AccessGuard<K>, AccessGuard<V>)>> {
+        // Safety: No other references to this table can exist.
+        // Tables can only be opened mutably in one location (see Error::TableAlreadyOpen),
+        // and we borrow &mut self.
+        let removed = unsafe { self.tree.pop_first()? };
+        self.record_removed_entry(&removed)?;
+        Ok(removed)
+    }
+
+    /// Removes the last entry in the table
+    ///
+    /// Returns the removed key and value, if the table was not empty
+    pub fn pop_last(&mut self) -> Result<Option<(AccessGuard<K>, AccessGuard<V>)>> {
+        // Safety: No other references to this table can exist.
+        // Tables can only be opened mutably in one location (see Error::TableAlreadyOpen),
+        // and we borrow &mut self.
+        let removed = unsafe { self.tree.pop_last()? };
+        self.record_removed_entry(&removed)?;
+        Ok(removed)
+    }
+
+    /// Removes all entries in the given range
+    ///
+    /// Returns an iterator over the removed entries
+    pub fn drain<T: RangeBounds<KR>, KR: Borrow<K>>(&mut self, range: T) -> Result<Drain<K, V>> {
+        self.drain_filter_range(range, |_, _| true)
+    }
+
+    /// Removes all entries in the given range
+    ///
+    /// Unlike [`Table::drain`], the removed entries are not returned, and subtrees which are entirely
+    /// within the range are freed without reading their leaves
+    pub fn delete_range<T: RangeBounds<KR>, KR: Borrow<K>>(&mut self, range: T) -> Result {
+        if self.changelog.enabled() {
+            // The removed entries have to be read, so that they can be recorded
+            return self.drain_filter_range(range, |_, _| true).map(|_| ());
+        }
+        // Safety: No other references to this table can exist.
+        // Tables can only be opened mutably in one location (see Error::TableAlreadyOpen),
+        // and we borrow &mut self.
+        unsafe { self.tree.delete_range(range) }
+    }
+
+    /// Removes all entries from the table
+    pub fn clear(&mut self) -> Result {
+        self.delete_range::<RangeFull, &K>(..)
+    }
+
+    /// Returns a cursor over the table, which can also modify the entry that it is positioned on
+    ///
+    /// The cursor starts out unpositioned
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, 'db, 'txn, K, V> {
+        CursorMut::new(self)
+    }
+
+    /// Removes all entries for which the predicate returns `true`
+    ///
+    /// Returns an iterator over the removed entries
+    pub fn drain_filter<
+        F: for<'f> FnMut(
+            <<K as RedbValue>::View as WithLifetime<'f>>::Out,
+            <<V as RedbValue>::View as WithLifetime<'f>>::Out,
+        ) -> bool,
+    >(
+        &mut self,
+        predicate: F,
+    ) -> Result<Drain<K, V>> {
+        self.drain_filter_range::<RangeFull, K, F>(.., predicate)
+    }
+
+    /// Retains only the entries for which the predicate returns `true`
+    pub fn retain<
+        F: for<'f> FnMut(
+            <<K as RedbValue>::View as WithLifetime<'f>>::Out,
+            <<V as RedbValue>::View as WithLifetime<'f>>::Out,
+        ) -> bool,
+    >(
+        &mut self,
+        mut predicate: F,
+    ) -> Result {
+        if self.changelog.enabled() {
+            // The removed entries have to be collected, so that they can be recorded
+            return self
+                .drain_filter_range::<RangeFull, K, _>(.., |key, value| !predicate(key, value))
+                .map(|_| ());
+        }
         // Safety: No other references to this table can exist.
         // Tables can only be opened mutably in one location (see Error::TableAlreadyOpen),
         // and we borrow &mut self.
-        unsafe { self.tree.remove(key) }
+        unsafe {
+            self.tree
+                .delete_filter::<RangeFull, K, _>(.., |key, value| {
+                    !predicate(K::from_bytes(key), V::from_bytes(value))
+                })
+        }
+    }
+
+    fn drain_filter_range<
+        T: RangeBounds<KR>,
+        KR: Borrow<K> + ?Sized,
+        F: for<'f> FnMut(
+            <<K as RedbValue>::View as WithLifetime<'f>>::Out,
+            <<V as RedbValue>::View as WithLifetime<'f>>::Out,
+        ) -> bool,
+    >(
+        &mut self,
+        range: T,
+        mut predicate: F,
+    ) -> Result<Drain<K, V>> {
+        let mem = self.tree.mem();
+        let mut removed = vec![];
+        // Safety: No other references to this table can exist.
+        // Tables can only be opened mutably in one location (see Error::TableAlreadyOpen),
+        // and we borrow &mut self.
+        unsafe {
+            self.tree.delete_filter(range, |key, value| {
+                if predicate(K::from_bytes(key), V::from_bytes(value)) {
+                    removed.push((
+                        AccessGuard::with_owned_value(key.to_vec(), mem),
+                        AccessGuard::with_owned_value(value.to_vec(), mem),
+                    ));
+                    true
+                } else {
+                    false
+                }
+            })?;
+        }
+        for (key, value) in removed.iter() {
+            self.changelog.record(
+                ChangeKind::Remove,
+                key.value_bytes(),
+                Some(value.value_bytes()),
+                None,
+            )?;
+        }
+        Ok(Drain::new(removed))
+    }
+
+    fn record_removed_entry(&mut self, entry: &Option<(AccessGuard<K>, AccessGuard<V>)>) -> Result {
+        if let Some((key, value)) = entry {
+            self.changelog.record(
+                ChangeKind::Remove,
+                key.value_bytes(),
+                Some(value.value_bytes()),
+                None,
+            )?;
+        }
+        Ok(())
     }
 }
 
@@ -80,18 +414,125 @@ impl<'db, 'txn, K: RedbKey + ?Sized, V: RedbValue + ?Sized> ReadableTable<K, V>
         self.tree.range(range).map(RangeIter::new)
     }
 
+    fn cursor(&self) -> Result<Cursor<K, V>> {
+        Ok(Cursor::new(self.tree.cursor()))
+    }
+
+    fn first(
+        &self,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.tree.first()
+    }
+
+    fn last(
+        &self,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.tree.last()
+    }
+
+    fn floor(
+        &self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.tree.floor(key)
+    }
+
+    fn ceiling(
+        &self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.tree.ceiling(key)
+    }
+
+    fn predecessor(
+        &self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.tree.predecessor(key)
+    }
+
+    fn successor(
+        &self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.tree.successor(key)
+    }
+
     fn len(&self) -> Result<usize> {
         self.tree.len()
     }
 
+    fn range_len<'a, T: RangeBounds<KR>, KR: Borrow<K> + 'a>(&'a self, range: T) -> Result<usize> {
+        self.tree.range_len(range)
+    }
+
+    fn rank(&self, key: &K) -> Result<usize> {
+        self.tree.rank(key)
+    }
+
+    fn get_by_index(
+        &self,
+        index: usize,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.tree.get_by_index(index)
+    }
+
     fn is_empty(&self) -> Result<bool> {
         self.len().map(|x| x == 0)
     }
+
+    fn get_blob(&self, key: &K) -> Result<Option<BlobReader>> {
+        let mem = self.blobs.mem();
+        Ok(self
+            .blobs
+            .get(key)?
+            .map(|header| BlobReader::new(header, mem)))
+    }
 }
 
 impl<'db, 'txn, K: RedbKey + ?Sized, V: RedbValue + ?Sized> Drop for Table<'db, 'txn, K, V> {
     fn drop(&mut self) {
-        self.transaction.close_table(&self.name, &mut self.tree);
+        let roots = TableRoots {
+            table_root: self.tree.get_root(),
+            blob_root: self.blobs.get_root(),
+            changelog_root: self.changelog.get_root(),
+        };
+        self.transaction.close_table(&self.name, roots);
     }
 }
 
@@ -135,25 +576,135 @@ pub trait ReadableTable<K: RedbKey + ?Sized, V: RedbValue + ?Sized> {
         range: T,
     ) -> Result<RangeIter<K, V>>;
 
+    /// Returns a cursor over the table, which can be moved in either direction
+    ///
+    /// The cursor starts out unpositioned
+    fn cursor(&self) -> Result<Cursor<K, V>>;
+
+    /// Returns the entry with the smallest key, if the table is not empty
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    fn first(
+        &self,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    >;
+
+    /// Returns the entry with the largest key, if the table is not empty
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    fn last(
+        &self,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    >;
+
+    /// Returns the entry with the largest key less than or equal to `key`
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    fn floor(
+        &self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    >;
+
+    /// Returns the entry with the smallest key greater than or equal to `key`
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    fn ceiling(
+        &self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    >;
+
+    /// Returns the entry with the largest key strictly less than `key`
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    fn predecessor(
+        &self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    >;
+
+    /// Returns the entry with the smallest key strictly greater than `key`
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    fn successor(
+        &self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    >;
+
     /// Returns the number of entries in the table
     fn len(&self) -> Result<usize>;
 
+    /// Returns the number of entries whose keys fall within the given range
+    fn range_len<'a, T: RangeBounds<KR>, KR: Borrow<K> + 'a>(&'a self, range: T) -> Result<usize>;
+
+    /// Returns the number of entries with keys strictly less than `key`
+    ///
+    /// If `key` is present, this is its position in the table
+    fn rank(&self, key: &K) -> Result<usize>;
+
+    /// Returns the entry at the given position, counting from the smallest key
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    fn get_by_index(
+        &self,
+        index: usize,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    >;
+
     /// Returns `true` if the table is empty
     fn is_empty(&self) -> Result<bool>;
+
+    /// Returns a reader over the blob with the given key
+    ///
+    /// See [`Table::insert_blob`]
+    fn get_blob(&self, key: &K) -> Result<Option<BlobReader>>;
 }
 
 /// A read-only table
 pub struct ReadOnlyTable<'txn, K: RedbKey + ?Sized, V: RedbValue + ?Sized> {
     tree: Btree<'txn, K, V>,
+    blobs: Btree<'txn, K, [u8]>,
 }
 
 impl<'txn, K: RedbKey + ?Sized, V: RedbValue + ?Sized> ReadOnlyTable<'txn, K, V> {
     pub(crate) fn new(
         root_page: Option<(PageNumber, Checksum)>,
+        blob_root: Option<(PageNumber, Checksum)>,
         mem: &'txn TransactionalMemory,
     ) -> ReadOnlyTable<'txn, K, V> {
         ReadOnlyTable {
             tree: Btree::new(root_page, mem),
+            blobs: Btree::new(blob_root, mem),
         }
     }
 }
@@ -172,16 +723,203 @@ impl<'txn, K: RedbKey + ?Sized, V: RedbValue + ?Sized> ReadableTable<K, V>
         self.tree.range(range).map(RangeIter::new)
     }
 
+    fn cursor(&self) -> Result<Cursor<K, V>> {
+        Ok(Cursor::new(self.tree.cursor()))
+    }
+
+    fn first(
+        &self,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.tree.first()
+    }
+
+    fn last(
+        &self,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.tree.last()
+    }
+
+    fn floor(
+        &self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.tree.floor(key)
+    }
+
+    fn ceiling(
+        &self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.tree.ceiling(key)
+    }
+
+    fn predecessor(
+        &self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.tree.predecessor(key)
+    }
+
+    fn successor(
+        &self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.tree.successor(key)
+    }
+
     fn len(&self) -> Result<usize> {
         self.tree.len()
     }
 
+    fn range_len<'a, T: RangeBounds<KR>, KR: Borrow<K> + 'a>(&'a self, range: T) -> Result<usize> {
+        self.tree.range_len(range)
+    }
+
+    fn rank(&self, key: &K) -> Result<usize> {
+        self.tree.rank(key)
+    }
+
+    fn get_by_index(
+        &self,
+        index: usize,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.tree.get_by_index(index)
+    }
+
     fn is_empty(&self) -> Result<bool> {
         self.len().map(|x| x == 0)
     }
+
+    fn get_blob(&self, key: &K) -> Result<Option<BlobReader>> {
+        let mem = self.blobs.mem();
+        Ok(self
+            .blobs
+            .get(key)?
+            .map(|header| BlobReader::new(header, mem)))
+    }
+}
+
+/// A read-only table, whose key and value types are not known at compile time
+///
+/// Keys and values are returned as their serialized bytes. Since keys cannot be compared without
+/// their type, only operations which use the structure of the table are supported
+pub struct ReadOnlyUntypedTable<'txn> {
+    tree: RawBtree<'txn>,
+    key_type: String,
+    value_type: String,
+}
+
+impl<'txn> ReadOnlyUntypedTable<'txn> {
+    pub(crate) fn new(
+        root_page: Option<(PageNumber, Checksum)>,
+        fixed_key_size: Option<usize>,
+        fixed_value_size: Option<usize>,
+        key_type: String,
+        value_type: String,
+        mem: &'txn TransactionalMemory,
+    ) -> ReadOnlyUntypedTable<'txn> {
+        ReadOnlyUntypedTable {
+            tree: RawBtree::new(root_page, fixed_key_size, fixed_value_size, mem),
+            key_type,
+            value_type,
+        }
+    }
+
+    /// The name of the type of the keys, as returned by [`RedbValue::redb_type_name`]
+    pub fn key_type(&self) -> &str {
+        &self.key_type
+    }
+
+    /// The name of the type of the values, as returned by [`RedbValue::redb_type_name`]
+    pub fn value_type(&self) -> &str {
+        &self.value_type
+    }
+
+    /// The width of the keys, if they are fixed width
+    pub fn fixed_key_size(&self) -> Option<usize> {
+        self.tree.fixed_key_size()
+    }
+
+    /// The width of the values, if they are fixed width
+    pub fn fixed_value_size(&self) -> Option<usize> {
+        self.tree.fixed_value_size()
+    }
+
+    /// Returns an iterator over the serialized entries of the table, in the order they are stored
+    pub fn iter(&self) -> Result<UntypedRangeIter> {
+        Ok(UntypedRangeIter {
+            inner: self.tree.iter(),
+        })
+    }
+
+    /// Returns the number of entries in the table
+    pub fn len(&self) -> Result<usize> {
+        self.tree.len()
+    }
+
+    /// Returns `true` if the table is empty
+    pub fn is_empty(&self) -> Result<bool> {
+        self.len().map(|x| x == 0)
+    }
+}
+
+/// A double-ended iterator over the serialized entries of a [`ReadOnlyUntypedTable`]
+pub struct UntypedRangeIter<'a> {
+    inner: BtreeRangeIter<'a, [u8], [u8]>,
+}
+
+impl<'a> Iterator for UntypedRangeIter<'a> {
+    type Item = (&'a [u8], &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| (entry.key(), entry.value()))
+    }
 }
 
-#[doc(hidden)]
+impl<'a> DoubleEndedIterator for UntypedRangeIter<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|entry| (entry.key(), entry.value()))
+    }
+}
+
+/// A double-ended iterator over a range of entries in a table
 pub struct RangeIter<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> {
     inner: BtreeRangeIter<'a, K, V>,
 }
@@ -191,30 +929,461 @@ impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> RangeIter<'a, K,
         Self { inner }
     }
 
-    // TODO: Simplify this when GATs are stable
-    #[allow(clippy::type_complexity)]
-    // TODO: implement Iter when GATs are stable
+    /// Converts this into an iterator over only the keys in the range
+    pub fn keys(self) -> KeyIter<'a, K, V> {
+        KeyIter { inner: self.inner }
+    }
+
+    /// Converts this into an iterator over only the values in the range
+    pub fn values(self) -> ValueIter<'a, K, V> {
+        ValueIter { inner: self.inner }
+    }
+}
+
+impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> Iterator for RangeIter<'a, K, V> {
+    type Item = (
+        <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+        <<V as RedbValue>::View as WithLifetime<'a>>::Out,
+    );
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|entry| (K::from_bytes(entry.key()), V::from_bytes(entry.value())))
+    }
+}
+
+impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> DoubleEndedIterator
+    for RangeIter<'a, K, V>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|entry| (K::from_bytes(entry.key()), V::from_bytes(entry.value())))
+    }
+}
+
+/// A double-ended iterator over the keys in a range of a table
+pub struct KeyIter<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> {
+    inner: BtreeRangeIter<'a, K, V>,
+}
+
+impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> Iterator for KeyIter<'a, K, V> {
+    type Item = <<K as RedbValue>::View as WithLifetime<'a>>::Out;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| K::from_bytes(entry.key()))
+    }
+}
+
+impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> DoubleEndedIterator
+    for KeyIter<'a, K, V>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|entry| K::from_bytes(entry.key()))
+    }
+}
+
+/// A double-ended iterator over the values in a range of a table
+pub struct ValueIter<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> {
+    inner: BtreeRangeIter<'a, K, V>,
+}
+
+impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> Iterator for ValueIter<'a, K, V> {
+    type Item = <<V as RedbValue>::View as WithLifetime<'a>>::Out;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| V::from_bytes(entry.value()))
+    }
+}
+
+impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> DoubleEndedIterator
+    for ValueIter<'a, K, V>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|entry| V::from_bytes(entry.value()))
+    }
+}
+
+// TODO: Simplify this when GATs are stable
+#[allow(clippy::type_complexity)]
+fn decode_entry<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a>(
+    entry: EntryAccessor<'a>,
+) -> (
+    <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+    <<V as RedbValue>::View as WithLifetime<'a>>::Out,
+) {
+    (K::from_bytes(entry.key()), V::from_bytes(entry.value()))
+}
+
+/// A cursor over the entries of a table
+///
+/// The cursor is either positioned on an entry, or unpositioned. Moving past either end of the
+/// table leaves it unpositioned, and moving from there wraps around to the first, or last, entry
+pub struct Cursor<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> {
+    inner: BtreeCursor<'a, K, V>,
+}
+
+// TODO: Simplify this when GATs are stable
+#[allow(clippy::type_complexity)]
+impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> Cursor<'a, K, V> {
+    fn new(inner: BtreeCursor<'a, K, V>) -> Self {
+        Self { inner }
+    }
+
+    /// Returns the entry that the cursor is positioned on
+    pub fn current(
+        &self,
+    ) -> Option<(
+        <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+        <<V as RedbValue>::View as WithLifetime<'a>>::Out,
+    )> {
+        self.inner.current().map(decode_entry::<K, V>)
+    }
+
+    /// Moves to the first entry with a key greater than or equal to `key`, and returns it
+    pub fn seek(
+        &mut self,
+        key: &K,
+    ) -> Option<(
+        <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+        <<V as RedbValue>::View as WithLifetime<'a>>::Out,
+    )> {
+        self.inner
+            .seek(key.as_bytes().as_ref())
+            .map(decode_entry::<K, V>)
+    }
+
+    /// Moves to the first entry in the table, and returns it
+    pub fn seek_first(
+        &mut self,
+    ) -> Option<(
+        <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+        <<V as RedbValue>::View as WithLifetime<'a>>::Out,
+    )> {
+        self.inner.seek_first().map(decode_entry::<K, V>)
+    }
+
+    /// Moves to the last entry in the table, and returns it
+    pub fn seek_last(
+        &mut self,
+    ) -> Option<(
+        <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+        <<V as RedbValue>::View as WithLifetime<'a>>::Out,
+    )> {
+        self.inner.seek_last().map(decode_entry::<K, V>)
+    }
+
+    /// Moves to the next entry, and returns it
     #[allow(clippy::should_implement_trait)]
     pub fn next(
         &mut self,
+    ) -> Option<(
+        <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+        <<V as RedbValue>::View as WithLifetime<'a>>::Out,
+    )> {
+        self.inner.next().map(decode_entry::<K, V>)
+    }
+
+    /// Moves to the previous entry, and returns it
+    pub fn prev(
+        &mut self,
+    ) -> Option<(
+        <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+        <<V as RedbValue>::View as WithLifetime<'a>>::Out,
+    )> {
+        self.inner.prev().map(decode_entry::<K, V>)
+    }
+}
+
+/// A cursor over the entries of a table, which can modify the entry that it is positioned on
+///
+/// Movement behaves the same as [`Cursor`]. Entries returned by the cursor borrow it, so they
+/// must be dropped before it is moved or used to modify the table
+pub struct CursorMut<'c, 'db, 'txn, K: RedbKey + ?Sized, V: RedbValue + ?Sized> {
+    table: &'c mut Table<'db, 'txn, K, V>,
+    inner: BtreeCursor<'txn, K, V>,
+}
+
+// TODO: Simplify this when GATs are stable
+#[allow(clippy::type_complexity)]
+impl<'c, 'db, 'txn, K: RedbKey + ?Sized, V: RedbValue + ?Sized> CursorMut<'c, 'db, 'txn, K, V> {
+    fn new(table: &'c mut Table<'db, 'txn, K, V>) -> Self {
+        let inner = table.tree.cursor();
+        Self { table, inner }
+    }
+
+    /// Returns the entry that the cursor is positioned on
+    pub fn current(
+        &self,
     ) -> Option<(
         <<K as RedbValue>::View as WithLifetime>::Out,
         <<V as RedbValue>::View as WithLifetime>::Out,
     )> {
-        if let Some(entry) = self.inner.next() {
-            let key = K::from_bytes(entry.key());
-            let value = V::from_bytes(entry.value());
-            Some((key, value))
-        } else {
-            None
+        self.inner.current().map(decode_entry::<K, V>)
+    }
+
+    /// Moves to the first entry with a key greater than or equal to `key`, and returns it
+    pub fn seek(
+        &mut self,
+        key: &K,
+    ) -> Option<(
+        <<K as RedbValue>::View as WithLifetime>::Out,
+        <<V as RedbValue>::View as WithLifetime>::Out,
+    )> {
+        self.inner
+            .seek(key.as_bytes().as_ref())
+            .map(decode_entry::<K, V>)
+    }
+
+    /// Moves to the first entry in the table, and returns it
+    pub fn seek_first(
+        &mut self,
+    ) -> Option<(
+        <<K as RedbValue>::View as WithLifetime>::Out,
+        <<V as RedbValue>::View as WithLifetime>::Out,
+    )> {
+        self.inner.seek_first().map(decode_entry::<K, V>)
+    }
+
+    /// Moves to the last entry in the table, and returns it
+    pub fn seek_last(
+        &mut self,
+    ) -> Option<(
+        <<K as RedbValue>::View as WithLifetime>::Out,
+        <<V as RedbValue>::View as WithLifetime>::Out,
+    )> {
+        self.inner.seek_last().map(decode_entry::<K, V>)
+    }
+
+    /// Moves to the next entry, and returns it
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(
+        &mut self,
+    ) -> Option<(
+        <<K as RedbValue>::View as WithLifetime>::Out,
+        <<V as RedbValue>::View as WithLifetime>::Out,
+    )> {
+        self.inner.next().map(decode_entry::<K, V>)
+    }
+
+    /// Moves to the previous entry, and returns it
+    pub fn prev(
+        &mut self,
+    ) -> Option<(
+        <<K as RedbValue>::View as WithLifetime>::Out,
+        <<V as RedbValue>::View as WithLifetime>::Out,
+    )> {
+        self.inner.prev().map(decode_entry::<K, V>)
+    }
+
+    /// Removes the entry that the cursor is positioned on, and moves to the entry after it
+    ///
+    /// Returns the removed value, or `None` if the cursor was not positioned on an entry
+    pub fn delete_current(&mut self) -> Result<Option<AccessGuard<V>>> {
+        let key = match self.inner.current() {
+            Some(entry) => entry.key().to_vec(),
+            None => return Ok(None),
+        };
+        let path = self.inner.path_indices();
+        // The pages under the cursor may be freed by the modification, so drop them first
+        self.inner.reset();
+        // Safety: No other references to this table can exist.
+        // Tables can only be opened mutably in one location (see Error::TableAlreadyOpen),
+        // and all entries returned by this cursor borrow it.
+        let removed = unsafe { self.table.tree.remove_bytes_at(&key, path.clone())? };
+        // The removal may be deferred until the guard is dropped, which has to happen before the
+        // tree is read again
+        let removed = removed.map(AccessGuard::into_owned);
+        if let Some(ref value) = removed {
+            self.table.changelog.record(
+                ChangeKind::Remove,
+                &key,
+                Some(value.value_bytes()),
+                None,
+            )?;
         }
+        self.inner = self.table.tree.cursor();
+        self.inner.seek_modified(&path, &key, true);
+        Ok(removed)
     }
 
-    pub fn rev(self) -> Self {
-        Self::new(self.inner.reverse())
+    /// Replaces the value of the entry that the cursor is positioned on. The cursor stays on the
+    /// same entry
+    ///
+    /// Returns the old value, or `None` if the cursor was not positioned on an entry
+    pub fn update_current(&mut self, value: &V) -> Result<Option<AccessGuard<V>>> {
+        let key = match self.inner.current() {
+            Some(entry) => entry.key().to_vec(),
+            None => return Ok(None),
+        };
+        let path = self.inner.path_indices();
+        // The pages under the cursor may be freed by the modification, so drop them first
+        self.inner.reset();
+        // Safety: No other references to this table can exist.
+        // Tables can only be opened mutably in one location (see Error::TableAlreadyOpen),
+        // and all entries returned by this cursor borrow it.
+        let old = unsafe {
+            self.table
+                .tree
+                .insert_bytes_at(&key, value.as_bytes().as_ref(), path.clone())?
+        };
+        if self.table.changelog.enabled() {
+            self.table.changelog.record(
+                ChangeKind::Update,
+                &key,
+                old.as_ref().map(AccessGuard::value_bytes),
+                Some(value.as_bytes().as_ref()),
+            )?;
+        }
+        self.inner = self.table.tree.cursor();
+        self.inner.seek_modified(&path, &key, false);
+        Ok(old)
     }
 }
 
+/// A view into a single entry of a [`Table`], which may be either occupied or vacant
+pub enum Entry<'a, 'db, 'txn, K: RedbKey + ?Sized, V: RedbValue + ?Sized> {
+    Occupied(OccupiedEntry<'a, 'db, 'txn, K, V>),
+    Vacant(VacantEntry<'a, 'db, 'txn, K, V>),
+}
+
+impl<'a, 'db, 'txn, K: RedbKey + ?Sized, V: RedbValue + ?Sized> Entry<'a, 'db, 'txn, K, V> {
+    /// Returns the key of this entry
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Inserts `default` if the entry is vacant
+    ///
+    /// Returns the value of the entry
+    pub fn or_insert(self, default: &V) -> Result<AccessGuard<'a, V>> {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.value),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Calls `f` with the entry, if it is occupied
+    pub fn and_modify<F: FnOnce(&mut OccupiedEntry<'a, 'db, 'txn, K, V>) -> Result>(
+        self,
+        f: F,
+    ) -> Result<Self> {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(&mut entry)?;
+                Ok(Entry::Occupied(entry))
+            }
+            Entry::Vacant(entry) => Ok(Entry::Vacant(entry)),
+        }
+    }
+}
+
+/// An entry of a [`Table`] whose key is present
+pub struct OccupiedEntry<'a, 'db, 'txn, K: RedbKey + ?Sized, V: RedbValue + ?Sized> {
+    table: &'a mut Table<'db, 'txn, K, V>,
+    key: &'a K,
+    // A copy of the current value, so that reading it doesn't search the table again
+    value: AccessGuard<'a, V>,
+}
+
+impl<'a, 'db, 'txn, K: RedbKey + ?Sized, V: RedbValue + ?Sized> OccupiedEntry<'a, 'db, 'txn, K, V> {
+    /// Returns the key of this entry
+    pub fn key(&self) -> &K {
+        self.key
+    }
+
+    /// Returns the value of this entry
+    pub fn get(&self) -> Result<<<V as RedbValue>::View as WithLifetime>::Out> {
+        Ok(self.value.to_value())
+    }
+
+    /// Replaces the value of this entry
+    ///
+    /// Returns the old value
+    pub fn insert(&mut self, value: &V) -> Result<AccessGuard<V>> {
+        self.table.insert(self.key, value)?;
+        let value = value.as_bytes().as_ref().to_vec();
+        let value = AccessGuard::with_owned_value(value, self.table.tree.mem());
+        Ok(mem::replace(&mut self.value, value))
+    }
+
+    /// Removes this entry from the table
+    ///
+    /// Returns its value
+    pub fn remove(self) -> Result<AccessGuard<'a, V>> {
+        Ok(self.table.remove(self.key)?.unwrap())
+    }
+}
+
+/// An entry of a [`Table`] whose key is not present
+pub struct VacantEntry<'a, 'db, 'txn, K: RedbKey + ?Sized, V: RedbValue + ?Sized> {
+    table: &'a mut Table<'db, 'txn, K, V>,
+    key: &'a K,
+}
+
+impl<'a, 'db, 'txn, K: RedbKey + ?Sized, V: RedbValue + ?Sized> VacantEntry<'a, 'db, 'txn, K, V> {
+    /// Returns the key of this entry
+    pub fn key(&self) -> &K {
+        self.key
+    }
+
+    /// Inserts the given value for this entry's key
+    ///
+    /// Returns the inserted value
+    pub fn insert(self, value: &V) -> Result<AccessGuard<'a, V>> {
+        // The entry borrows the table mutably, so the key can't have been inserted since it was
+        // looked up, and the value is always inserted
+        let (inserted, _) = self.table.get_or_insert(self.key, value)?;
+        Ok(inserted)
+    }
+}
+
+/// An iterator over the entries removed from a table
+pub struct Drain<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> {
+    inner: vec::IntoIter<(AccessGuard<'a, K>, AccessGuard<'a, V>)>,
+}
+
+impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> Drain<'a, K, V> {
+    pub(crate) fn new(removed: Vec<(AccessGuard<'a, K>, AccessGuard<'a, V>)>) -> Self {
+        Self {
+            inner: removed.into_iter(),
+        }
+    }
+}
+
+impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> Iterator for Drain<'a, K, V> {
+    type Item = (AccessGuard<'a, K>, AccessGuard<'a, V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> DoubleEndedIterator
+    for Drain<'a, K, V>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back()
+    }
+}
+
+impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> ExactSizeIterator
+    for Drain<'a, K, V>
+{
+}
+
 #[cfg(test)]
 mod test {
     use crate::types::{
@@ -224,6 +1393,41 @@ mod test {
     use std::cmp::Ordering;
     use tempfile::NamedTempFile;
 
+    #[test]
+    fn cursor_mut_keeps_path() {
+        let definition: TableDefinition<u64, u64> = TableDefinition::new("x");
+        let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+        let db = unsafe { Database::create(tmpfile.path(), 16 * 1024 * 1024).unwrap() };
+        let write_txn = db.begin_write().unwrap();
+        {
+            let mut table = write_txn.open_table(definition).unwrap();
+            for i in 0..10000u64 {
+                table.insert(&i, &i).unwrap();
+            }
+            let mut cursor = table.cursor_mut();
+            cursor.seek(&5000).unwrap();
+            let path = cursor.inner.path_indices();
+            assert!(path.len() > 1);
+
+            // Neither modification changes the structure of the tree, so the path from before it
+            // leads to the new position, without searching for the key
+            let key = 5000u64.to_le_bytes();
+            assert_eq!(5000, cursor.update_current(&0).unwrap().unwrap().to_value());
+            assert_eq!(path, cursor.inner.path_indices());
+            let mut inner = cursor.table.tree.cursor();
+            assert!(inner.seek_modified(&path, &key, false));
+            assert_eq!(path, inner.path_indices());
+
+            assert_eq!(0, cursor.delete_current().unwrap().unwrap().to_value());
+            assert_eq!(5001, cursor.current().unwrap().0);
+            assert_eq!(path, cursor.inner.path_indices());
+            let mut inner = cursor.table.tree.cursor();
+            assert!(inner.seek_modified(&path, &key, true));
+            assert_eq!(path, inner.path_indices());
+        }
+        write_txn.abort().unwrap();
+    }
+
     #[test]
     fn custom_ordering() {
         #[derive(Debug)]
@@ -1,12 +1,12 @@
 use crate::db::TransactionId;
 use crate::tree_store::{
-    Btree, BtreeMut, FreedTableKey, InternalTableDefinition, PageNumber, TableTree, TableType,
-    TransactionalMemory,
+    Btree, BtreeMut, ChangeIter, ChangelogMode, Checksum, FreedTableKey, InternalTableDefinition,
+    PageNumber, SnapshotRecord, TableRoots, TableTree, TableType, TransactionalMemory,
 };
-use crate::types::{RedbKey, RedbValue};
+use crate::types::{RedbKey, RedbValue, WithLifetime};
 use crate::{
     Database, Error, MultimapTable, MultimapTableDefinition, ReadOnlyMultimapTable, ReadOnlyTable,
-    Result, Table, TableDefinition,
+    ReadOnlyUntypedTable, Result, Table, TableDefinition,
 };
 #[cfg(feature = "logging")]
 use log::info;
@@ -14,6 +14,7 @@ use std::cell::RefCell;
 use std::cmp::min;
 use std::collections::HashMap;
 use std::mem::size_of;
+use std::ops::RangeFull;
 use std::panic;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -74,6 +75,34 @@ impl DatabaseStats {
     }
 }
 
+/// A notification that a write transaction has committed
+///
+/// Delivered to the receivers returned by [`Database::subscribe`]
+#[derive(Clone, Debug)]
+pub struct CommitEvent {
+    pub(crate) transaction_id: u64,
+    pub(crate) durability: Durability,
+    pub(crate) changed_tables: Vec<String>,
+}
+
+impl CommitEvent {
+    /// Id of the committed transaction. Ids increase with each transaction
+    pub fn transaction_id(&self) -> u64 {
+        self.transaction_id
+    }
+
+    /// Durability level that the transaction was committed with
+    pub fn durability(&self) -> Durability {
+        self.durability
+    }
+
+    /// Names of the tables which were created, modified, or deleted by the transaction, in sorted
+    /// order
+    pub fn changed_tables(&self) -> &[String] {
+        &self.changed_tables
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub enum Durability {
     /// Commits with this durability level will not be persisted to disk unless followed by a
@@ -90,6 +119,19 @@ pub enum Durability {
     Immediate,
 }
 
+/// A point within a [`WriteTransaction`] that the transaction can be rolled back to
+///
+/// Created by [`WriteTransaction::savepoint`], and used by [`WriteTransaction::restore_savepoint`]
+/// and [`WriteTransaction::release_savepoint`]
+pub struct Savepoint {
+    id: u64,
+    transaction_id: TransactionId,
+    master_root: Option<(PageNumber, Checksum)>,
+    pending_table_updates: HashMap<String, TableRoots>,
+    snapshot_root: Option<(PageNumber, Checksum)>,
+    freed_pages: usize,
+}
+
 /// A read/write transaction
 ///
 /// Only a single [`WriteTransaction`] may exist at a time
@@ -102,8 +144,16 @@ pub struct WriteTransaction<'db> {
     // The table of freed pages by transaction. FreedTableKey -> binary.
     // The binary blob is a length-prefixed array of PageNumber
     freed_tree: BtreeMut<'db, FreedTableKey, [u8]>,
+    // The table of named snapshots. Snapshot name -> SnapshotRecord
+    snapshot_tree: RefCell<BtreeMut<'db, str, SnapshotRecord>>,
+    // The most recently committed state, which this transaction started from
+    base_root: Option<(PageNumber, Checksum)>,
+    base_transaction_id: TransactionId,
     freed_pages: Rc<RefCell<Vec<PageNumber>>>,
     open_tables: RefCell<HashMap<String, &'static panic::Location<'static>>>,
+    // Ids of the savepoints which may still be restored, in the order they were created
+    live_savepoints: Vec<u64>,
+    next_savepoint_id: u64,
     completed: AtomicBool,
     durability: Durability,
 }
@@ -114,6 +164,8 @@ impl<'db> WriteTransaction<'db> {
     pub(crate) unsafe fn new(db: &'db Database, transaction_id: TransactionId) -> Result<Self> {
         let root_page = db.get_memory().get_data_root();
         let freed_root = db.get_memory().get_freed_root();
+        let snapshot_root = db.get_memory().get_snapshot_root();
+        let base_transaction_id = db.get_memory().get_last_committed_transaction_id()?;
         let freed_pages = Rc::new(RefCell::new(vec![]));
         Ok(Self {
             db,
@@ -125,8 +177,17 @@ impl<'db> WriteTransaction<'db> {
                 freed_pages.clone(),
             )),
             freed_tree: BtreeMut::new(freed_root, db.get_memory(), freed_pages.clone()),
+            snapshot_tree: RefCell::new(BtreeMut::new(
+                snapshot_root,
+                db.get_memory(),
+                freed_pages.clone(),
+            )),
+            base_root: root_page,
+            base_transaction_id,
             freed_pages,
             open_tables: RefCell::new(Default::default()),
+            live_savepoints: vec![],
+            next_savepoint_id: 0,
             completed: Default::default(),
             durability: Durability::Immediate,
         })
@@ -138,6 +199,161 @@ impl<'db> WriteTransaction<'db> {
         self.durability = durability;
     }
 
+    /// Creates a savepoint, capturing the current state of all tables in this transaction
+    ///
+    /// Use [`Self::restore_savepoint`] to roll back all writes made after this point, without
+    /// aborting the transaction.
+    ///
+    /// While the savepoint is live, pages written before it are copied when they are modified,
+    /// rather than being modified in place. Use [`Self::release_savepoint`] once it is no longer
+    /// needed
+    pub fn savepoint(&mut self) -> Result<Savepoint> {
+        let (master_root, pending_table_updates) = self.table_tree.borrow().get_state();
+        let id = self.next_savepoint_id;
+        self.next_savepoint_id += 1;
+        self.live_savepoints.push(id);
+        self.mem.savepoint();
+        #[cfg(feature = "logging")]
+        info!(
+            "Creating savepoint id={} in transaction id={}",
+            id, self.transaction_id
+        );
+
+        Ok(Savepoint {
+            id,
+            transaction_id: self.transaction_id,
+            master_root,
+            pending_table_updates,
+            snapshot_root: self.snapshot_tree.borrow().get_root(),
+            freed_pages: self.freed_pages.borrow().len(),
+        })
+    }
+
+    /// Restores the state of all tables to the given savepoint
+    ///
+    /// All writes made after the savepoint was created are rolled back. Savepoints created after
+    /// this one are invalidated, but this savepoint may be restored again.
+    ///
+    /// Returns [`Error::InvalidSavepoint`] if the savepoint was not created by this transaction, or
+    /// has been invalidated
+    pub fn restore_savepoint(&mut self, savepoint: &Savepoint) -> Result {
+        let position = self.live_savepoint_position(savepoint)?;
+        #[cfg(feature = "logging")]
+        info!(
+            "Restoring savepoint id={} in transaction id={}",
+            savepoint.id, self.transaction_id
+        );
+        self.live_savepoints.truncate(position + 1);
+
+        self.table_tree.borrow_mut().restore_state(
+            savepoint.master_root,
+            savepoint.pending_table_updates.clone(),
+        );
+        *self.snapshot_tree.borrow_mut() =
+            BtreeMut::new(savepoint.snapshot_root, self.mem, self.freed_pages.clone());
+        self.freed_pages
+            .borrow_mut()
+            .truncate(savepoint.freed_pages);
+        self.mem.restore_savepoint(position)?;
+
+        Ok(())
+    }
+
+    /// Releases the savepoint, without changing the state of any tables
+    ///
+    /// Pages written before the savepoint can be modified in place again, unless they are
+    /// referenced by a later savepoint. Savepoints created after this one remain valid.
+    ///
+    /// Returns [`Error::InvalidSavepoint`] if the savepoint was not created by this transaction, or
+    /// has been invalidated
+    pub fn release_savepoint(&mut self, savepoint: Savepoint) -> Result {
+        let position = self.live_savepoint_position(&savepoint)?;
+        #[cfg(feature = "logging")]
+        info!(
+            "Releasing savepoint id={} in transaction id={}",
+            savepoint.id, self.transaction_id
+        );
+        self.live_savepoints.remove(position);
+        self.mem.release_savepoint(position);
+
+        Ok(())
+    }
+
+    fn live_savepoint_position(&self, savepoint: &Savepoint) -> Result<usize> {
+        if savepoint.transaction_id != self.transaction_id {
+            return Err(Error::InvalidSavepoint);
+        }
+        self.live_savepoints
+            .iter()
+            .position(|x| *x == savepoint.id)
+            .ok_or(Error::InvalidSavepoint)
+    }
+
+    /// Creates a persistent snapshot named `name`, of the state of the database when this
+    /// transaction began
+    ///
+    /// The snapshot becomes visible once this transaction commits, and remains readable via
+    /// [`Database::begin_read_snapshot`] until it is deleted, including across restarts. Pages
+    /// referenced by a snapshot are not reclaimed until it is deleted.
+    ///
+    /// Returns [`Error::SnapshotAlreadyExists`] if a snapshot with the same name exists
+    pub fn create_snapshot(&self, name: &str) -> Result {
+        #[cfg(feature = "logging")]
+        info!(
+            "Creating snapshot '{}' of transaction id={}",
+            name, self.base_transaction_id
+        );
+        let mut snapshot_tree = self.snapshot_tree.borrow_mut();
+        if snapshot_tree.get(name)?.is_some() {
+            return Err(Error::SnapshotAlreadyExists(name.to_string()));
+        }
+        let record = SnapshotRecord {
+            transaction_id: self.base_transaction_id,
+            data_root: self.base_root,
+        };
+        // Safety: References into the snapshot table are never returned to the user
+        unsafe { snapshot_tree.insert(name, &record)? };
+
+        Ok(())
+    }
+
+    /// Delete the given snapshot
+    ///
+    /// Returns a bool indicating whether the snapshot existed
+    pub fn delete_snapshot(&self, name: &str) -> Result<bool> {
+        #[cfg(feature = "logging")]
+        info!("Deleting snapshot '{}'", name);
+        // Pages referenced only by this snapshot are still in the freed table, and will be
+        // reclaimed by a commit after this one, once nothing else references them
+        // Safety: References into the snapshot table are never returned to the user
+        let found = unsafe { self.snapshot_tree.borrow_mut().remove(name)?.is_some() };
+        Ok(found)
+    }
+
+    /// List all the snapshots
+    // TODO: should return an iterator of &str, once GATs are available
+    pub fn list_snapshots(&self) -> Result<impl Iterator<Item = String> + '_> {
+        let snapshot_tree = self.snapshot_tree.borrow();
+        let iter = snapshot_tree.range::<RangeFull, &str>(..)?;
+        let mut names = vec![];
+        for entry in iter {
+            names.push(str::from_bytes(entry.key()).to_string());
+        }
+        Ok(names.into_iter())
+    }
+
+    // Returns the transaction id of the oldest state pinned by a snapshot
+    fn oldest_snapshot(&self) -> Result<Option<TransactionId>> {
+        let snapshot_tree = self.snapshot_tree.borrow();
+        let iter = snapshot_tree.range::<RangeFull, &str>(..)?;
+        let mut oldest: Option<TransactionId> = None;
+        for entry in iter {
+            let transaction_id = SnapshotRecord::from_bytes(entry.value()).transaction_id;
+            oldest = Some(oldest.map_or(transaction_id, |x| min(x, transaction_id)));
+        }
+        Ok(oldest)
+    }
+
     /// Open the given table
     ///
     /// The table will be created if it does not exist
@@ -157,14 +373,24 @@ impl<'db> WriteTransaction<'db> {
             .borrow_mut()
             .insert(definition.name().to_string(), panic::Location::caller());
 
-        let internal_table = self
+        let mut internal_table = self
             .table_tree
             .borrow_mut()
             .get_or_create_table::<K, V>(definition.name(), TableType::Normal)?;
+        if V::fixed_width().is_none()
+            && self.db.max_inline_value_size().is_some()
+            && !internal_table.has_out_of_line_values()
+        {
+            internal_table = self
+                .table_tree
+                .borrow_mut()
+                .enable_out_of_line_values(definition.name())?;
+        }
 
         Ok(Table::new(
             definition.name(),
-            internal_table.get_root(),
+            &internal_table,
+            self.db.max_inline_value_size(),
             self.freed_pages.clone(),
             self.mem,
             self,
@@ -197,22 +423,72 @@ impl<'db> WriteTransaction<'db> {
 
         Ok(MultimapTable::new(
             definition.name(),
-            internal_table.get_root(),
+            &internal_table,
             self.freed_pages.clone(),
             self.mem,
             self,
         ))
     }
 
-    pub(crate) fn close_table<K: RedbKey + ?Sized, V: RedbValue + ?Sized>(
-        &self,
-        name: &str,
-        table: &mut BtreeMut<K, V>,
-    ) {
+    /// Enables the changelog of the table named `name`, which may be a normal or multimap table
+    ///
+    /// Once enabled, every modification of the table is recorded, and can be read with
+    /// [`ReadTransaction::changes_since`]. If `include_values` is true, the old and new values are
+    /// recorded along with the key. Blobs are not recorded. Enabling the changelog of a table which
+    /// already has one only changes whether values are recorded.
+    ///
+    /// Returns [`Error::TableDoesNotExist`] if the table does not exist, and
+    /// [`Error::TableAlreadyOpen`] if it is open
+    pub fn enable_changelog(&self, name: &str, include_values: bool) -> Result {
+        let mode = if include_values {
+            ChangelogMode::KeysAndValues
+        } else {
+            ChangelogMode::Keys
+        };
+        self.set_changelog_mode(name, mode)
+    }
+
+    /// Disables the changelog of the table named `name`, and removes all the changes recorded in it
+    ///
+    /// Returns [`Error::TableDoesNotExist`] if the table does not exist, and
+    /// [`Error::TableAlreadyOpen`] if it is open
+    pub fn disable_changelog(&self, name: &str) -> Result {
+        self.set_changelog_mode(name, ChangelogMode::Disabled)
+    }
+
+    fn set_changelog_mode(&self, name: &str, mode: ChangelogMode) -> Result {
+        #[cfg(feature = "logging")]
+        info!("Setting changelog of table: {} to {:?}", name, mode);
+        if let Some(location) = self.open_tables.borrow().get(name) {
+            return Err(Error::TableAlreadyOpen(name.to_string(), location));
+        }
+        self.table_tree.borrow_mut().set_changelog_mode(name, mode)
+    }
+
+    /// Removes the changes made by transactions up to, and including, `transaction_id` from the
+    /// changelog of the table named `name`
+    ///
+    /// Returns [`Error::TableDoesNotExist`] if the table does not exist,
+    /// [`Error::ChangelogNotEnabled`] if it does not have a changelog, and
+    /// [`Error::TableAlreadyOpen`] if it is open
+    pub fn truncate_changelog(&self, name: &str, transaction_id: u64) -> Result {
+        if let Some(location) = self.open_tables.borrow().get(name) {
+            return Err(Error::TableAlreadyOpen(name.to_string(), location));
+        }
+        self.table_tree
+            .borrow_mut()
+            .truncate_changelog(name, transaction_id)
+    }
+
+    pub(crate) fn transaction_id(&self) -> TransactionId {
+        self.transaction_id
+    }
+
+    pub(crate) fn close_table(&self, name: &str, roots: TableRoots) {
         self.open_tables.borrow_mut().remove(name).unwrap();
         self.table_tree
             .borrow_mut()
-            .stage_update_table_root(name, table.get_root());
+            .stage_update_table_root(name, roots);
     }
 
     /// Delete the given table
@@ -243,6 +519,140 @@ impl<'db> WriteTransaction<'db> {
             .delete_table::<K, V>(definition.name(), TableType::Multimap)
     }
 
+    /// Migrates the table named `name` from type `Table<K1, V1>` to `Table<K2, V2>`
+    ///
+    /// Every entry is passed to `f`, and the entry it returns is inserted into a new table. If `f`
+    /// returns the same key for multiple entries, the last one is kept. Once all the entries have
+    /// been migrated, the new table replaces the old one, and the old one is freed.
+    ///
+    /// Tables with a changelog can't be migrated, because the recorded changes are of the old types.
+    /// Consume the changes and disable the changelog first, with [`Self::disable_changelog`].
+    ///
+    /// Returns [`Error::TableDoesNotExist`] if the table does not exist, and
+    /// [`Error::TableTypeMismatch`] if it is not of type `Table<K1, V1>`, contains blobs, or has a
+    /// changelog
+    pub fn migrate_table<K1, V1, K2, V2, F>(&self, name: &str, f: F) -> Result
+    where
+        K1: RedbKey + ?Sized,
+        V1: RedbValue + ?Sized,
+        K2: RedbKey,
+        V2: RedbValue,
+        F: for<'a> FnMut(
+            <<K1 as RedbValue>::View as WithLifetime<'a>>::Out,
+            <<V1 as RedbValue>::View as WithLifetime<'a>>::Out,
+        ) -> (K2, V2),
+    {
+        #[cfg(feature = "logging")]
+        info!(
+            "Migrating table: {} from Table<{}, {}> to Table<{}, {}>",
+            name,
+            K1::redb_type_name(),
+            V1::redb_type_name(),
+            K2::redb_type_name(),
+            V2::redb_type_name()
+        );
+        if let Some(location) = self.open_tables.borrow().get(name) {
+            return Err(Error::TableAlreadyOpen(name.to_string(), location));
+        }
+        // Hold the table open, so that it cannot be modified by f
+        self.open_tables
+            .borrow_mut()
+            .insert(name.to_string(), panic::Location::caller());
+        let result = self.migrate_table_inner::<K1, V1, K2, V2, F>(name, f);
+        self.open_tables.borrow_mut().remove(name).unwrap();
+
+        result
+    }
+
+    fn migrate_table_inner<K1, V1, K2, V2, F>(&self, name: &str, mut f: F) -> Result
+    where
+        K1: RedbKey + ?Sized,
+        V1: RedbValue + ?Sized,
+        K2: RedbKey,
+        V2: RedbValue,
+        F: for<'a> FnMut(
+            <<K1 as RedbValue>::View as WithLifetime<'a>>::Out,
+            <<V1 as RedbValue>::View as WithLifetime<'a>>::Out,
+        ) -> (K2, V2),
+    {
+        let definition = self
+            .table_tree
+            .borrow()
+            .get_table::<K1, V1>(name, TableType::Normal)?
+            .ok_or_else(|| Error::TableDoesNotExist(name.to_string()))?;
+        // Blobs are keyed independently of the entries, so there is no way to map their keys
+        if definition.get_blob_root().is_some() {
+            return Err(Error::TableTypeMismatch(format!(
+                "{} contains blobs, and cannot be migrated",
+                name
+            )));
+        }
+        // The recorded changes can't be converted to the new types, and dropping them would hide
+        // them from readers of the changelog
+        if definition.get_changelog_mode() != ChangelogMode::Disabled {
+            return Err(Error::TableTypeMismatch(format!(
+                "{} has a changelog, and cannot be migrated",
+                name
+            )));
+        }
+
+        let source: Btree<K1, V1> = Btree::new(definition.get_root(), self.mem);
+        let mut destination: BtreeMut<K2, V2> =
+            BtreeMut::new(None, self.mem, self.freed_pages.clone());
+        destination.set_max_inline_value_size(self.db.max_inline_value_size());
+        for entry in source.range::<RangeFull, &K1>(..)? {
+            let (key, value) = f(K1::from_bytes(entry.key()), V1::from_bytes(entry.value()));
+            // Safety: References into the new table are never returned to the user
+            unsafe { destination.insert(&key, &value)? };
+        }
+
+        self.table_tree
+            .borrow_mut()
+            .replace_table::<K1, V1, K2, V2>(
+                name,
+                TableRoots {
+                    table_root: destination.get_root(),
+                    blob_root: None,
+                    changelog_root: None,
+                },
+                V2::fixed_width().is_none() && self.db.max_inline_value_size().is_some(),
+            )
+    }
+
+    /// Changes the type of the table named `name` from `Table<K1, V1>` to `Table<K2, V2>`, without
+    /// rewriting its contents
+    ///
+    /// This is only correct if `K2` and `V2` have the same serialized format as `K1` and `V1`, and
+    /// `K2` orders keys the same as `K1`. For example, when a type has been renamed. Use
+    /// [`Self::migrate_table`] for all other changes.
+    ///
+    /// Returns [`Error::TableDoesNotExist`] if the table does not exist, and
+    /// [`Error::TableTypeMismatch`] if it is not of type `Table<K1, V1>`, or the fixed widths of the
+    /// types differ
+    pub fn retype_table<K1, V1, K2, V2>(&self, name: &str) -> Result
+    where
+        K1: RedbKey + ?Sized,
+        V1: RedbValue + ?Sized,
+        K2: RedbKey + ?Sized,
+        V2: RedbValue + ?Sized,
+    {
+        #[cfg(feature = "logging")]
+        info!(
+            "Retyping table: {} from Table<{}, {}> to Table<{}, {}>",
+            name,
+            K1::redb_type_name(),
+            V1::redb_type_name(),
+            K2::redb_type_name(),
+            V2::redb_type_name()
+        );
+        if let Some(location) = self.open_tables.borrow().get(name) {
+            return Err(Error::TableAlreadyOpen(name.to_string(), location));
+        }
+        self.table_tree
+            .borrow_mut()
+            .retype_table::<K1, V1, K2, V2>(name)
+    }
+
     /// List all the tables
     // TODO: should return an iterator of &str, once GATs are available
     pub fn list_tables(&self) -> Result<impl Iterator<Item = String> + '_> {
@@ -267,8 +677,29 @@ impl<'db> WriteTransaction<'db> {
     /// durable as consistent with the [`Durability`] level set by [`Self::set_durability`]
     pub fn commit(mut self) -> Result {
         self.table_tree.borrow_mut().flush_table_root_updates()?;
+        // Skip the comparison against the previous commit, if there is no one to notify
+        let changed_tables = if self.db.has_commit_subscribers() {
+            Some(self.table_tree.borrow().changed_tables(self.base_root)?)
+        } else {
+            None
+        };
+        // Always checked, even if there are no watchers yet, so that a watch which is registered
+        // while this transaction commits is woken
+        let notifications = self
+            .db
+            .check_watchers(self.base_root, &self.table_tree.borrow())?;
         match self.commit_inner() {
             Ok(_) => {
+                // Publish before another write transaction can begin, so that events are in
+                // commit order
+                if let Some(changed_tables) = changed_tables {
+                    self.db.publish_commit(CommitEvent {
+                        transaction_id: self.transaction_id,
+                        durability: self.durability,
+                        changed_tables,
+                    });
+                }
+                notifications.notify();
                 self.db.deallocate_write_transaction(self.transaction_id);
                 Ok(())
             }
@@ -307,6 +738,10 @@ impl<'db> WriteTransaction<'db> {
     ///
     /// All writes performed in this transaction will be rolled back
     pub fn abort(self) -> Result {
+        self.abort_inner()
+    }
+
+    fn abort_inner(&self) -> Result {
         #[cfg(feature = "logging")]
         info!("Aborting transaction id={}", self.transaction_id);
         self.table_tree.borrow_mut().clear_table_root_updates();
@@ -319,10 +754,12 @@ impl<'db> WriteTransaction<'db> {
     }
 
     pub(crate) fn durable_commit(&mut self, eventual: bool) -> Result {
+        // A snapshot pins the state it captured, like a read transaction that never ends.
+        // Pages freed by the snapshot's own transaction are retained too, which is conservative
         let oldest_live_read = self
             .db
-            .oldest_live_read_transaction()
-            .unwrap_or(self.transaction_id);
+            .oldest_pinned_transaction(self.oldest_snapshot()?)
+            .map_or(self.transaction_id, |x| min(x, self.transaction_id));
 
         let root = self.table_tree.borrow_mut().flush_table_root_updates()?;
 
@@ -340,9 +777,15 @@ impl<'db> WriteTransaction<'db> {
         }
 
         let freed_root = self.freed_tree.get_root();
-
-        self.mem
-            .commit(root, freed_root, self.transaction_id, eventual)?;
+        let snapshot_root = self.snapshot_tree.borrow().get_root();
+
+        self.mem.commit(
+            root,
+            freed_root,
+            snapshot_root,
+            self.transaction_id,
+            eventual,
+        )?;
         Ok(())
     }
 
@@ -355,9 +798,10 @@ impl<'db> WriteTransaction<'db> {
         self.store_freed_pages()?;
 
         let freed_root = self.freed_tree.get_root();
+        let snapshot_root = self.snapshot_tree.borrow().get_root();
 
         self.mem
-            .non_durable_commit(root, freed_root, self.transaction_id)?;
+            .non_durable_commit(root, freed_root, snapshot_root, self.transaction_id)?;
         Ok(())
     }
 
@@ -373,7 +817,7 @@ impl<'db> WriteTransaction<'db> {
 
         let mut to_remove = vec![];
         let mut iter = self.freed_tree.range(..lookup_key)?;
-        while let Some(entry) = iter.next() {
+        for entry in iter.by_ref() {
             to_remove.push(FreedTableKey::from_bytes(entry.key()));
             let value = entry.value();
             let length = u64::from_le_bytes(value[..size_of::<u64>()].try_into().unwrap()) as usize;
@@ -409,24 +853,23 @@ impl<'db> WriteTransaction<'db> {
                 transaction_id: self.transaction_id,
                 pagination_id: pagination_counter,
             };
-            // Safety: The freed table is only accessed from the writer, so only this function
-            // is using it. The only reference retrieved, access_guard, is dropped before the next call
-            // to this method
-            let mut access_guard = unsafe { self.freed_tree.insert_reserve(&key, buffer_size)? };
-
+            // Build the value before inserting it, rather than using insert_reserve(), so that the
+            // checksum of the leaf covers the page list. Otherwise the freed table would fail
+            // verification during repair
+            let mut buffer = vec![0u8; buffer_size];
             let len = self.freed_pages.borrow().len();
-            access_guard.as_mut()[..8]
-                .copy_from_slice(&min(len as u64, chunk_size as u64).to_le_bytes());
+            buffer[..8].copy_from_slice(&min(len as u64, chunk_size as u64).to_le_bytes());
             for (i, page) in self
                 .freed_pages
                 .borrow_mut()
                 .drain(len - min(len, chunk_size)..)
                 .enumerate()
             {
-                access_guard.as_mut()[(i + 1) * 8..(i + 2) * 8]
-                    .copy_from_slice(&page.to_le_bytes());
+                buffer[(i + 1) * 8..(i + 2) * 8].copy_from_slice(&page.to_le_bytes());
             }
-            drop(access_guard);
+            // Safety: The freed table is only accessed from the writer, so only this function
+            // is using it. No references are retrieved
+            unsafe { self.freed_tree.insert(&key, buffer.as_slice())? };
 
             pagination_counter += 1;
         }
@@ -439,11 +882,15 @@ impl<'db> WriteTransaction<'db> {
         let table_tree = self.table_tree.borrow();
         let data_tree_stats = table_tree.stats()?;
         let freed_tree_stats = self.freed_tree.stats();
+        let snapshot_tree_stats = self.snapshot_tree.borrow().stats();
         let total_metadata_bytes = data_tree_stats.metadata_bytes()
             + freed_tree_stats.metadata_bytes
-            + freed_tree_stats.stored_leaf_bytes;
-        let total_fragmented =
-            data_tree_stats.fragmented_bytes() + freed_tree_stats.fragmented_bytes;
+            + freed_tree_stats.stored_leaf_bytes
+            + snapshot_tree_stats.metadata_bytes
+            + snapshot_tree_stats.stored_leaf_bytes;
+        let total_fragmented = data_tree_stats.fragmented_bytes()
+            + freed_tree_stats.fragmented_bytes
+            + snapshot_tree_stats.fragmented_bytes;
 
         Ok(DatabaseStats {
             tree_height: data_tree_stats.tree_height(),
@@ -476,7 +923,13 @@ impl<'db> WriteTransaction<'db> {
 impl<'a> Drop for WriteTransaction<'a> {
     fn drop(&mut self) {
         if !self.completed.load(Ordering::Acquire) {
-            self.db.record_leaked_write_transaction(self.transaction_id);
+            if self.db.leaked_write_transaction_detection() {
+                self.db.record_leaked_write_transaction(self.transaction_id);
+            } else if self.abort_inner().is_err() {
+                // The uncommitted writes may not have been fully rolled back, so prevent any
+                // further writes
+                self.db.record_leaked_write_transaction(self.transaction_id);
+            }
         }
     }
 }
@@ -491,8 +944,11 @@ pub struct ReadTransaction<'a> {
 }
 
 impl<'db> ReadTransaction<'db> {
-    pub(crate) fn new(db: &'db Database, transaction_id: TransactionId) -> Self {
-        let root_page = db.get_memory().get_data_root();
+    pub(crate) fn new(
+        db: &'db Database,
+        transaction_id: TransactionId,
+        root_page: Option<(PageNumber, Checksum)>,
+    ) -> Self {
         Self {
             db,
             tree: TableTree::new(root_page, db.get_memory(), Default::default()),
@@ -510,7 +966,31 @@ impl<'db> ReadTransaction<'db> {
             .get_table::<K, V>(definition.name(), TableType::Normal)?
             .ok_or_else(|| Error::TableDoesNotExist(definition.name().to_string()))?;
 
-        Ok(ReadOnlyTable::new(header.get_root(), self.db.get_memory()))
+        Ok(ReadOnlyTable::new(
+            header.get_root(),
+            header.get_blob_root(),
+            self.db.get_memory(),
+        ))
+    }
+
+    /// Open the table named `name`, without specifying its key and value types
+    ///
+    /// This is intended for tools which inspect or export arbitrary databases. Use
+    /// [`Self::open_table`] when the types are known
+    pub fn open_untyped_table(&self, name: &str) -> Result<ReadOnlyUntypedTable> {
+        let header = self
+            .tree
+            .get_untyped_table(name, TableType::Normal)?
+            .ok_or_else(|| Error::TableDoesNotExist(name.to_string()))?;
+
+        Ok(ReadOnlyUntypedTable::new(
+            header.get_root(),
+            header.get_fixed_key_size(),
+            header.get_fixed_value_size(),
+            header.get_key_type().to_string(),
+            header.get_value_type().to_string(),
+            self.db.get_memory(),
+        ))
     }
 
     /// Open the given table
@@ -529,6 +1009,27 @@ impl<'db> ReadTransaction<'db> {
         ))
     }
 
+    /// Returns the changes made to the table named `name` by transactions after `transaction_id`,
+    /// in the order they were made
+    ///
+    /// Pass `0` to read the whole changelog. Returns [`Error::TableDoesNotExist`] if the table does
+    /// not exist, and [`Error::ChangelogNotEnabled`] if it does not have a changelog
+    pub fn changes_since(&self, name: &str, transaction_id: u64) -> Result<ChangeIter> {
+        let header = self
+            .tree
+            .get_definition(name)?
+            .ok_or_else(|| Error::TableDoesNotExist(name.to_string()))?;
+        if header.get_changelog_mode() == ChangelogMode::Disabled {
+            return Err(Error::ChangelogNotEnabled(name.to_string()));
+        }
+
+        Ok(ChangeIter::new(
+            header.get_changelog_root(),
+            transaction_id,
+            self.db.get_memory(),
+        ))
+    }
+
     /// List all the tables
     // TODO: should return an iterator of &str, once GATs are available
     pub fn list_tables(&self) -> Result<impl Iterator<Item = String>> {
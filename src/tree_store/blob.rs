@@ -0,0 +1,379 @@
+use crate::tree_store::btree_base::{Checksum, LeafAccessor, LEAF};
+use crate::tree_store::page_store::{Page, TransactionalMemory};
+use crate::tree_store::{AllPageNumbersBtreeIter, BtreeMut, PageNumber};
+use crate::types::RedbKey;
+use crate::Result;
+use std::cmp::{max, min};
+use std::collections::HashSet;
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::mem::size_of;
+
+// Maximum number of pages in a single chunk of a blob. Every chunk, except the last one, is this
+// size, unless the database's regions are smaller
+const CHUNK_PAGES: usize = 64;
+
+const CHUNK_REFERENCE_BYTES: usize = PageNumber::serialized_size() + size_of::<Checksum>();
+
+// Blobs are stored in chunks of contiguous pages, outside of the btree. The value stored in the
+// blob tree for each key is a header with the following format:
+// 8 bytes: length of the blob
+// repeating (one per chunk):
+// 8 bytes: page number
+// 16 bytes: checksum of the chunk's pages
+#[derive(Clone, Debug, Default)]
+struct BlobHeader {
+    len: u64,
+    chunks: Vec<(PageNumber, Checksum)>,
+}
+
+impl BlobHeader {
+    fn from_bytes(data: &[u8]) -> Self {
+        let len = u64::from_le_bytes(data[..size_of::<u64>()].try_into().unwrap());
+        let chunks = data[size_of::<u64>()..]
+            .chunks_exact(CHUNK_REFERENCE_BYTES)
+            .map(|chunk| {
+                let page = PageNumber::from_le_bytes(
+                    chunk[..PageNumber::serialized_size()].try_into().unwrap(),
+                );
+                let checksum = Checksum::from_le_bytes(
+                    chunk[PageNumber::serialized_size()..].try_into().unwrap(),
+                );
+                (page, checksum)
+            })
+            .collect();
+        Self { len, chunks }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut result =
+            Vec::with_capacity(size_of::<u64>() + self.chunks.len() * CHUNK_REFERENCE_BYTES);
+        result.extend_from_slice(&self.len.to_le_bytes());
+        for (page, checksum) in self.chunks.iter() {
+            result.extend_from_slice(&page.to_le_bytes());
+            result.extend_from_slice(&checksum.to_le_bytes());
+        }
+        result
+    }
+}
+
+fn chunk_size(mem: &TransactionalMemory) -> u64 {
+    min(
+        CHUNK_PAGES * mem.get_page_size(),
+        mem.get_max_allocation_size(),
+    ) as u64
+}
+
+fn seek_position(current: u64, len: u64, pos: SeekFrom) -> io::Result<u64> {
+    let (base, offset) = match pos {
+        SeekFrom::Start(offset) => return Ok(offset),
+        SeekFrom::End(offset) => (len, offset),
+        SeekFrom::Current(offset) => (current, offset),
+    };
+    if offset >= 0 {
+        base.checked_add(offset as u64)
+    } else {
+        base.checked_sub(offset.unsigned_abs())
+    }
+    .ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "invalid seek to a negative or overflowing position",
+        )
+    })
+}
+
+// Returns all the pages used by the blob tree rooted at root, including the chunks of every blob
+pub(crate) fn all_blob_pages(
+    root: PageNumber,
+    fixed_key_size: Option<usize>,
+    mem: &TransactionalMemory,
+) -> Vec<PageNumber> {
+    let mut result = vec![];
+    for page_number in AllPageNumbersBtreeIter::new(root, fixed_key_size, None, mem) {
+        result.push(page_number);
+        let page = mem.get_page(page_number);
+        if page.memory()[0] == LEAF {
+            let accessor = LeafAccessor::new(&page, fixed_key_size, None);
+            for i in 0..accessor.num_pairs() {
+                let header = BlobHeader::from_bytes(accessor.entry(i).unwrap().value());
+                result.extend(header.chunks.iter().map(|(page, _)| *page));
+            }
+        }
+    }
+    result
+}
+
+// Verifies the checksums of the chunks of every blob in the blob tree rooted at root. The
+// checksums of the tree itself must be verified separately
+pub(crate) fn verify_blob_checksums(
+    root: PageNumber,
+    fixed_key_size: Option<usize>,
+    mem: &TransactionalMemory,
+) -> bool {
+    for page_number in AllPageNumbersBtreeIter::new(root, fixed_key_size, None, mem) {
+        let page = mem.get_page(page_number);
+        if page.memory()[0] == LEAF {
+            let accessor = LeafAccessor::new(&page, fixed_key_size, None);
+            for i in 0..accessor.num_pairs() {
+                let header = BlobHeader::from_bytes(accessor.entry(i).unwrap().value());
+                for (chunk, checksum) in header.chunks {
+                    let chunk = mem.get_page(chunk);
+                    if mem.checksum_type().checksum(chunk.memory()) != checksum {
+                        return false;
+                    }
+                }
+            }
+        }
+    }
+    true
+}
+
+/// A reader over a blob stored with [`Table::insert_blob`](crate::Table::insert_blob)
+pub struct BlobReader<'a> {
+    header: BlobHeader,
+    position: u64,
+    mem: &'a TransactionalMemory,
+}
+
+impl<'a> BlobReader<'a> {
+    pub(crate) fn new(header: &[u8], mem: &'a TransactionalMemory) -> Self {
+        Self {
+            header: BlobHeader::from_bytes(header),
+            position: 0,
+            mem,
+        }
+    }
+
+    /// Returns the length of the blob, in bytes
+    pub fn len(&self) -> u64 {
+        self.header.len
+    }
+
+    /// Returns `true` if the blob is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<'a> Read for BlobReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.header.len {
+            return Ok(0);
+        }
+        let chunk_size = chunk_size(self.mem);
+        let (page, _) = self.header.chunks[(self.position / chunk_size) as usize];
+        let offset = self.position % chunk_size;
+        let available = min(chunk_size - offset, self.header.len - self.position);
+        let n = min(buf.len() as u64, available) as usize;
+        let offset = offset as usize;
+        let page = self.mem.get_page(page);
+        buf[..n].copy_from_slice(&page.memory()[offset..(offset + n)]);
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl<'a> Seek for BlobReader<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = seek_position(self.position, self.header.len, pos)?;
+        Ok(self.position)
+    }
+}
+
+/// A writer to a blob, returned by [`Table::insert_blob`](crate::Table::insert_blob) and
+/// [`Table::open_blob`](crate::Table::open_blob)
+///
+/// Writes past the end of the blob extend it, and any gap left by seeking past the end is filled
+/// with zeros. The blob is stored in the table when the writer is flushed or dropped. Errors which
+/// occur when dropping the writer are ignored, so [`BlobWriter::finish`] should be called to observe them
+pub struct BlobWriter<'a, 'txn, K: RedbKey + ?Sized> {
+    tree: &'a mut BtreeMut<'txn, K, [u8]>,
+    key: &'a K,
+    mem: &'txn TransactionalMemory,
+    header: BlobHeader,
+    // Chunks which have been written since the header was last stored
+    dirty_chunks: HashSet<usize>,
+    modified: bool,
+    position: u64,
+}
+
+impl<'a, 'txn, K: RedbKey + ?Sized> BlobWriter<'a, 'txn, K> {
+    // Creates a writer to a new, empty blob. Any existing blob with the same key must already have
+    // been removed
+    pub(crate) fn new(tree: &'a mut BtreeMut<'txn, K, [u8]>, key: &'a K) -> Self {
+        let mem = tree.mem();
+        Self {
+            tree,
+            key,
+            mem,
+            header: BlobHeader::default(),
+            dirty_chunks: Default::default(),
+            // Store the header, so that the blob exists even if nothing is written
+            modified: true,
+            position: 0,
+        }
+    }
+
+    // Opens a writer to the existing blob with the given key
+    pub(crate) fn open(tree: &'a mut BtreeMut<'txn, K, [u8]>, key: &'a K) -> Result<Option<Self>> {
+        let mem = tree.mem();
+        let header = if let Some(header) = tree.get(key)? {
+            BlobHeader::from_bytes(header)
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            tree,
+            key,
+            mem,
+            header,
+            dirty_chunks: Default::default(),
+            modified: false,
+            position: 0,
+        }))
+    }
+
+    // Removes the blob with the given key, and frees its chunks. Returns true if it existed
+    //
+    // Safety: caller must ensure that no references to the blob tree, or the chunks of its blobs, exist
+    pub(crate) unsafe fn remove(tree: &mut BtreeMut<'txn, K, [u8]>, key: &K) -> Result<bool> {
+        let header = if let Some(guard) = tree.remove(key)? {
+            BlobHeader::from_bytes(guard.to_value())
+        } else {
+            return Ok(false);
+        };
+        for (page, _) in header.chunks {
+            tree.free_page(page)?;
+        }
+        Ok(true)
+    }
+
+    /// Returns the length of the blob, in bytes
+    pub fn len(&self) -> u64 {
+        self.header.len
+    }
+
+    /// Returns `true` if the blob is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Stores the blob in the table, and returns any error which occurred
+    pub fn finish(mut self) -> Result {
+        self.store_header()
+    }
+
+    fn store_header(&mut self) -> Result {
+        if !self.modified {
+            return Ok(());
+        }
+        for i in self.dirty_chunks.drain() {
+            let page = self.mem.get_page(self.header.chunks[i].0);
+            self.header.chunks[i].1 = self.mem.checksum_type().checksum(page.memory());
+        }
+        // Safety: the writer holds the only reference to the blob tree
+        unsafe {
+            self.tree.insert(self.key, &self.header.to_bytes())?;
+        }
+        self.modified = false;
+        Ok(())
+    }
+
+    // Ensures that the given chunk exists, was allocated in this transaction, and can hold at least
+    // required bytes. Returns its page
+    fn prepare_chunk(&mut self, index: usize, required: usize) -> Result<PageNumber> {
+        let chunk_size = chunk_size(self.mem) as usize;
+        // Any chunk before the one being written must be full size
+        while self.header.chunks.len() <= index {
+            if let Some(last) = self.header.chunks.len().checked_sub(1) {
+                self.resize_chunk(last, chunk_size)?;
+            }
+            let size = if self.header.chunks.len() < index {
+                chunk_size
+            } else {
+                required
+            };
+            let mut page = self.mem.allocate(size)?;
+            page.memory_mut().fill(0);
+            self.header.chunks.push((page.get_page_number(), 0));
+            self.dirty_chunks.insert(self.header.chunks.len() - 1);
+        }
+        self.resize_chunk(index, required)?;
+        Ok(self.header.chunks[index].0)
+    }
+
+    // Ensures that the chunk can hold at least required bytes, and may be written in place
+    fn resize_chunk(&mut self, index: usize, required: usize) -> Result {
+        let page_size = self.mem.get_page_size();
+        let (old_page, _) = self.header.chunks[index];
+        let capacity = old_page.page_size_bytes(page_size);
+        if capacity >= required && self.mem.uncommitted(old_page) {
+            return Ok(());
+        }
+        // Grow geometrically to amortize the cost of copying
+        let new_capacity = if capacity >= required {
+            capacity
+        } else {
+            min(max(required, 2 * capacity), chunk_size(self.mem) as usize)
+        };
+        let mut page = self.mem.allocate(new_capacity)?;
+        {
+            let old = self.mem.get_page(old_page);
+            let new_mem = page.memory_mut();
+            new_mem[..capacity].copy_from_slice(old.memory());
+            new_mem[capacity..].fill(0);
+        }
+        self.header.chunks[index].0 = page.get_page_number();
+        drop(page);
+        // Safety: the chunk was copied, and the writer holds the only reference to it
+        unsafe {
+            self.tree.free_page(old_page)?;
+        }
+        self.dirty_chunks.insert(index);
+        Ok(())
+    }
+
+    fn write_helper(&mut self, buf: &[u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let chunk_size = chunk_size(self.mem);
+        let index = (self.position / chunk_size) as usize;
+        let offset = (self.position % chunk_size) as usize;
+        let n = min(buf.len(), chunk_size as usize - offset);
+        let page = self.prepare_chunk(index, offset + n)?;
+        // Safety: the chunk was allocated in this transaction, and the writer holds the only
+        // reference to it
+        let mut page = unsafe { self.mem.get_page_mut(page) };
+        page.memory_mut()[offset..(offset + n)].copy_from_slice(&buf[..n]);
+        self.dirty_chunks.insert(index);
+        self.modified = true;
+        self.position += n as u64;
+        self.header.len = max(self.header.len, self.position);
+        Ok(n)
+    }
+}
+
+impl<'a, 'txn, K: RedbKey + ?Sized> Write for BlobWriter<'a, 'txn, K> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(self.write_helper(buf)?)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(self.store_header()?)
+    }
+}
+
+impl<'a, 'txn, K: RedbKey + ?Sized> Seek for BlobWriter<'a, 'txn, K> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = seek_position(self.position, self.header.len, pos)?;
+        Ok(self.position)
+    }
+}
+
+impl<'a, 'txn, K: RedbKey + ?Sized> Drop for BlobWriter<'a, 'txn, K> {
+    fn drop(&mut self) {
+        let _ = self.store_header();
+    }
+}
@@ -1,10 +1,10 @@
 use crate::tree_store::btree_base::{
-    branch_checksum, leaf_checksum, BranchAccessor, Checksum, FreePolicy, LeafAccessor, BRANCH,
-    LEAF,
+    branch_checksum, leaf_checksum, resolve_value, BranchAccessor, Checksum, FreePolicy,
+    LeafAccessor, ValueReference, BRANCH, LEAF,
 };
-use crate::tree_store::btree_mutator::MutateHelper;
+use crate::tree_store::btree_mutator::{InsertCondition, MutateHelper};
 use crate::tree_store::page_store::{Page, PageImpl, TransactionalMemory};
-use crate::tree_store::{AccessGuardMut, BtreeRangeIter, PageNumber};
+use crate::tree_store::{AccessGuardMut, BtreeCursor, BtreeRangeIter, PageNumber};
 use crate::types::{RedbKey, RedbValue, WithLifetime};
 use crate::{AccessGuard, Result};
 #[cfg(feature = "logging")]
@@ -13,7 +13,7 @@ use std::borrow::Borrow;
 use std::cell::RefCell;
 use std::cmp::max;
 use std::marker::PhantomData;
-use std::ops::{RangeBounds, RangeFull};
+use std::ops::{Bound, RangeBounds};
 use std::rc::Rc;
 
 pub(crate) struct BtreeStats {
@@ -29,6 +29,10 @@ pub(crate) struct BtreeMut<'a, K: RedbKey + ?Sized, V: RedbValue + ?Sized> {
     mem: &'a TransactionalMemory,
     root: Option<(PageNumber, Checksum)>,
     freed_pages: Rc<RefCell<Vec<PageNumber>>>,
+    // Values longer than this are stored out of line
+    max_inline_value_size: Option<usize>,
+    // Whether the tree may contain out of line values. See InternalTableDefinition
+    out_of_line_values: bool,
     _key_type: PhantomData<K>,
     _value_type: PhantomData<V>,
 }
@@ -43,15 +47,38 @@ impl<'a, K: RedbKey + ?Sized, V: RedbValue + ?Sized> BtreeMut<'a, K, V> {
             mem,
             root,
             freed_pages,
+            max_inline_value_size: None,
+            out_of_line_values: false,
             _key_type: Default::default(),
             _value_type: Default::default(),
         }
     }
 
+    pub(crate) fn set_max_inline_value_size(&mut self, size: Option<usize>) {
+        self.max_inline_value_size = size;
+    }
+
+    pub(crate) fn set_out_of_line_values(&mut self, enabled: bool) {
+        self.out_of_line_values = enabled;
+    }
+
     pub(crate) fn get_root(&self) -> Option<(PageNumber, Checksum)> {
         self.root
     }
 
+    pub(crate) fn mem(&self) -> &'a TransactionalMemory {
+        self.mem
+    }
+
+    // Frees a page which is referenced from within this tree's values, such as a blob chunk.
+    // Uncommitted pages are freed immediately, and others are deferred until commit
+    //
+    // Safety: caller must ensure that no references to the page exist
+    pub(crate) unsafe fn free_page(&mut self, page: PageNumber) -> Result {
+        let mut freed_pages = self.freed_pages.borrow_mut();
+        FreePolicy::Uncommitted.conditional_free(page, freed_pages.as_mut(), self.mem)
+    }
+
     // Safety: caller must ensure that no uncommitted data is accessed within this tree, from other references
     pub(crate) unsafe fn insert(&mut self, key: &K, value: &V) -> Result<Option<AccessGuard<V>>> {
         #[cfg(feature = "logging")]
@@ -68,6 +95,7 @@ impl<'a, K: RedbKey + ?Sized, V: RedbValue + ?Sized> BtreeMut<'a, K, V> {
             self.mem,
             freed_pages.as_mut(),
         );
+        operation.set_max_inline_value_size(self.max_inline_value_size);
         let (old_value, _) = operation.insert(key, value)?;
         Ok(old_value)
     }
@@ -95,10 +123,92 @@ impl<'a, K: RedbKey + ?Sized, V: RedbValue + ?Sized> BtreeMut<'a, K, V> {
             self.mem,
             freed_pages.as_mut(),
         );
+        // If the value is stored out of line, the guard references its own page
+        operation.set_max_inline_value_size(self.max_inline_value_size);
         let (_, guard) = operation.insert(key, value.as_slice())?;
         Ok(guard)
     }
 
+    // Inserts the value, if the key is not present. Returns the value stored under the key, and
+    // whether it was inserted
+    // Safety: caller must ensure that no uncommitted data is accessed within this tree, from other references
+    pub(crate) unsafe fn get_or_insert(
+        &mut self,
+        key: &K,
+        value: &V,
+    ) -> Result<(AccessGuard<V>, bool)> {
+        #[cfg(feature = "logging")]
+        trace!(
+            "Btree(root={:?}): Inserting {:?} if absent, with value of length {}",
+            &self.root,
+            key,
+            value.as_bytes().as_ref().len()
+        );
+        let mut freed_pages = self.freed_pages.borrow_mut();
+        let mut operation: MutateHelper<K, V> = MutateHelper::new(
+            &mut self.root,
+            FreePolicy::Uncommitted,
+            self.mem,
+            freed_pages.as_mut(),
+        );
+        operation.set_max_inline_value_size(self.max_inline_value_size);
+        operation.get_or_insert(key.as_bytes().as_ref(), value.as_bytes().as_ref())
+    }
+
+    // Safety: caller must ensure that no uncommitted data is accessed within this tree, from other references
+    pub(crate) unsafe fn insert_if_absent(&mut self, key: &K, value: &V) -> Result<bool> {
+        self.insert_conditional(key, value, &InsertCondition::Absent)
+    }
+
+    // Safety: caller must ensure that no uncommitted data is accessed within this tree, from other references
+    pub(crate) unsafe fn compare_and_swap(
+        &mut self,
+        key: &K,
+        expected: Option<&V>,
+        new: &V,
+    ) -> Result<bool> {
+        if let Some(expected) = expected {
+            self.insert_conditional(
+                key,
+                new,
+                &InsertCondition::Equals(expected.as_bytes().as_ref()),
+            )
+        } else {
+            self.insert_conditional(key, new, &InsertCondition::Absent)
+        }
+    }
+
+    // Returns true if the condition held, and the value was inserted
+    // Safety: caller must ensure that no uncommitted data is accessed within this tree, from other references
+    unsafe fn insert_conditional(
+        &mut self,
+        key: &K,
+        value: &V,
+        condition: &InsertCondition,
+    ) -> Result<bool> {
+        #[cfg(feature = "logging")]
+        trace!(
+            "Btree(root={:?}): Conditionally inserting {:?} with value of length {}",
+            &self.root,
+            key,
+            value.as_bytes().as_ref().len()
+        );
+        let mut freed_pages = self.freed_pages.borrow_mut();
+        let mut operation: MutateHelper<K, V> = MutateHelper::new(
+            &mut self.root,
+            FreePolicy::Uncommitted,
+            self.mem,
+            freed_pages.as_mut(),
+        );
+        operation.set_max_inline_value_size(self.max_inline_value_size);
+        let result = operation.insert_conditional(
+            key.as_bytes().as_ref(),
+            value.as_bytes().as_ref(),
+            condition,
+        )?;
+        Ok(result.is_some())
+    }
+
     // Safety: caller must ensure that no uncommitted data is accessed within this tree, from other references
     pub(crate) unsafe fn remove(&mut self, key: &K) -> Result<Option<AccessGuard<V>>> {
         #[cfg(feature = "logging")]
@@ -114,6 +224,167 @@ impl<'a, K: RedbKey + ?Sized, V: RedbValue + ?Sized> BtreeMut<'a, K, V> {
         Ok(result)
     }
 
+    /// Removes all entries in the range for which predicate returns true
+    /// The predicate is called with the key and value bytes of each entry in the range
+    // Safety: caller must ensure that no uncommitted data is accessed within this tree, from other references
+    pub(crate) unsafe fn delete_filter<
+        T: RangeBounds<KR>,
+        KR: Borrow<K> + ?Sized,
+        F: FnMut(&[u8], &[u8]) -> bool,
+    >(
+        &mut self,
+        range: T,
+        mut predicate: F,
+    ) -> Result {
+        let start = owned_bound::<K, KR>(range.start_bound());
+        let end = owned_bound::<K, KR>(range.end_bound());
+        #[cfg(feature = "logging")]
+        trace!(
+            "Btree(root={:?}): Deleting range {:?}..{:?}",
+            &self.root,
+            start,
+            end
+        );
+        let mut freed_pages = self.freed_pages.borrow_mut();
+        let mut operation: MutateHelper<K, V> = MutateHelper::new(
+            &mut self.root,
+            FreePolicy::Uncommitted,
+            self.mem,
+            freed_pages.as_mut(),
+        );
+        operation.set_out_of_line_values(self.out_of_line_values);
+        operation.delete_filter(&(borrow_bound(&start), borrow_bound(&end)), &mut predicate)
+    }
+
+    /// Removes all entries in the range
+    // Safety: caller must ensure that no uncommitted data is accessed within this tree, from other references
+    pub(crate) unsafe fn delete_range<T: RangeBounds<KR>, KR: Borrow<K> + ?Sized>(
+        &mut self,
+        range: T,
+    ) -> Result {
+        let start = owned_bound::<K, KR>(range.start_bound());
+        let end = owned_bound::<K, KR>(range.end_bound());
+        #[cfg(feature = "logging")]
+        trace!(
+            "Btree(root={:?}): Deleting range {:?}..{:?}",
+            &self.root,
+            start,
+            end
+        );
+        let mut freed_pages = self.freed_pages.borrow_mut();
+        let mut operation: MutateHelper<K, V> = MutateHelper::new(
+            &mut self.root,
+            FreePolicy::Uncommitted,
+            self.mem,
+            freed_pages.as_mut(),
+        );
+        operation.set_out_of_line_values(self.out_of_line_values);
+        operation.delete_range(&(borrow_bound(&start), borrow_bound(&end)))
+    }
+
+    // Safety: caller must ensure that no uncommitted data is accessed within this tree, from other references
+    pub(crate) unsafe fn pop_first(
+        &mut self,
+    ) -> Result<Option<(AccessGuard<'a, K>, AccessGuard<'a, V>)>> {
+        self.pop(false)
+    }
+
+    // Safety: caller must ensure that no uncommitted data is accessed within this tree, from other references
+    pub(crate) unsafe fn pop_last(
+        &mut self,
+    ) -> Result<Option<(AccessGuard<'a, K>, AccessGuard<'a, V>)>> {
+        self.pop(true)
+    }
+
+    // Safety: caller must ensure that no uncommitted data is accessed within this tree, from other references
+    unsafe fn pop(
+        &mut self,
+        last: bool,
+    ) -> Result<Option<(AccessGuard<'a, K>, AccessGuard<'a, V>)>> {
+        #[cfg(feature = "logging")]
+        trace!(
+            "Btree(root={:?}): Popping the {} entry",
+            &self.root,
+            if last { "last" } else { "first" }
+        );
+        let mut freed_pages = self.freed_pages.borrow_mut();
+        let mut operation: MutateHelper<K, V> = MutateHelper::new(
+            &mut self.root,
+            FreePolicy::Uncommitted,
+            self.mem,
+            freed_pages.as_mut(),
+        );
+        Ok(operation
+            .delete_edge(last)?
+            .map(|(key, value)| (AccessGuard::with_owned_value(key, self.mem), value)))
+    }
+
+    // Safety: caller must ensure that no uncommitted data is accessed within this tree, from other references
+    pub(crate) unsafe fn insert_bytes(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<Option<AccessGuard<'a, V>>> {
+        self.insert_bytes_at(key, value, vec![])
+    }
+
+    // Inserts the key, which is at the given path. See BtreeCursor::path_indices()
+    // Safety: caller must ensure that no uncommitted data is accessed within this tree, from other references
+    pub(crate) unsafe fn insert_bytes_at(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+        path: Vec<usize>,
+    ) -> Result<Option<AccessGuard<'a, V>>> {
+        #[cfg(feature = "logging")]
+        trace!(
+            "Btree(root={:?}): Inserting {:?} with value of length {}",
+            &self.root,
+            K::from_bytes(key),
+            value.len()
+        );
+        let mut freed_pages = self.freed_pages.borrow_mut();
+        let mut operation: MutateHelper<K, V> = MutateHelper::new(
+            &mut self.root,
+            FreePolicy::Uncommitted,
+            self.mem,
+            freed_pages.as_mut(),
+        );
+        operation.set_max_inline_value_size(self.max_inline_value_size);
+        operation.set_path_hint(path);
+        let (old_value, _) = operation.insert_bytes(key, value)?;
+        Ok(old_value)
+    }
+
+    // Safety: caller must ensure that no uncommitted data is accessed within this tree, from other references
+    pub(crate) unsafe fn remove_bytes(&mut self, key: &[u8]) -> Result<Option<AccessGuard<'a, V>>> {
+        self.remove_bytes_at(key, vec![])
+    }
+
+    // Removes the key, which is at the given path. See BtreeCursor::path_indices()
+    // Safety: caller must ensure that no uncommitted data is accessed within this tree, from other references
+    pub(crate) unsafe fn remove_bytes_at(
+        &mut self,
+        key: &[u8],
+        path: Vec<usize>,
+    ) -> Result<Option<AccessGuard<'a, V>>> {
+        #[cfg(feature = "logging")]
+        trace!(
+            "Btree(root={:?}): Deleting {:?}",
+            &self.root,
+            K::from_bytes(key)
+        );
+        let mut freed_pages = self.freed_pages.borrow_mut();
+        let mut operation: MutateHelper<K, V> = MutateHelper::new(
+            &mut self.root,
+            FreePolicy::Uncommitted,
+            self.mem,
+            freed_pages.as_mut(),
+        );
+        operation.set_path_hint(path);
+        operation.delete_bytes(key)
+    }
+
     // Like remove(), but does not free uncommitted data
     pub(crate) fn remove_retain_uncommitted(&mut self, key: &K) -> Result<Option<AccessGuard<V>>> {
         let mut freed_pages = self.freed_pages.borrow_mut();
@@ -152,6 +423,11 @@ impl<'a, K: RedbKey + ?Sized, V: RedbValue + ?Sized> BtreeMut<'a, K, V> {
         self.read_tree().get(key)
     }
 
+    // Returns a copy of the serialized value for the key, if present
+    pub(crate) fn get_owned(&self, key: &K) -> Result<Option<AccessGuard<'a, V>>> {
+        Btree::<K, V>::new(self.root, self.mem).get_owned(key)
+    }
+
     pub(crate) fn range<T: RangeBounds<KR>, KR: Borrow<K> + 'a>(
         &self,
         range: T,
@@ -159,9 +435,136 @@ impl<'a, K: RedbKey + ?Sized, V: RedbValue + ?Sized> BtreeMut<'a, K, V> {
         self.read_tree().range(range)
     }
 
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn first(
+        &self,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.read_tree().first()
+    }
+
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn last(
+        &self,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.read_tree().last()
+    }
+
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn floor(
+        &self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.read_tree().floor(key)
+    }
+
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn ceiling(
+        &self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.read_tree().ceiling(key)
+    }
+
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn predecessor(
+        &self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.read_tree().predecessor(key)
+    }
+
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn successor(
+        &self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.read_tree().successor(key)
+    }
+
+    pub(crate) fn cursor(&self) -> BtreeCursor<'a, K, V> {
+        BtreeCursor::new(self.root.map(|(p, _)| p), self.mem)
+    }
+
     pub(crate) fn len(&self) -> Result<usize> {
         self.read_tree().len()
     }
+
+    pub(crate) fn rank(&self, key: &K) -> Result<usize> {
+        self.read_tree().rank(key)
+    }
+
+    pub(crate) fn range_len<T: RangeBounds<KR>, KR: Borrow<K> + 'a>(
+        &self,
+        range: T,
+    ) -> Result<usize> {
+        self.read_tree().range_len(range)
+    }
+
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn get_by_index(
+        &self,
+        index: usize,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime>::Out,
+            <<V as RedbValue>::View as WithLifetime>::Out,
+        )>,
+    > {
+        self.read_tree().get_by_index(index)
+    }
+}
+
+fn owned_bound<K: RedbKey + ?Sized, KR: Borrow<K> + ?Sized>(bound: Bound<&KR>) -> Bound<Vec<u8>> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key.borrow().as_bytes().as_ref().to_vec()),
+        Bound::Excluded(key) => Bound::Excluded(key.borrow().as_bytes().as_ref().to_vec()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+fn borrow_bound(bound: &Bound<Vec<u8>>) -> Bound<&[u8]> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key.as_slice()),
+        Bound::Excluded(key) => Bound::Excluded(key.as_slice()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
 }
 
 pub(crate) struct RawBtree<'a> {
@@ -186,6 +589,39 @@ impl<'a> RawBtree<'a> {
         }
     }
 
+    pub(crate) fn fixed_key_size(&self) -> Option<usize> {
+        self.fixed_key_size
+    }
+
+    pub(crate) fn fixed_value_size(&self) -> Option<usize> {
+        self.fixed_value_size
+    }
+
+    pub(crate) fn len(&self) -> Result<usize> {
+        let len = if let Some((p, _)) = self.root {
+            let page = self.mem.get_page(p);
+            match page.memory()[0] {
+                LEAF => LeafAccessor::new(&page, self.fixed_key_size, self.fixed_value_size)
+                    .num_pairs() as u64,
+                BRANCH => BranchAccessor::new(&page, self.fixed_key_size).subtree_len(),
+                _ => unreachable!(),
+            }
+        } else {
+            0
+        };
+        Ok(len as usize)
+    }
+
+    // Iterates over all the entries, in the order they are stored
+    pub(crate) fn iter(&self) -> BtreeRangeIter<'a, [u8], [u8]> {
+        BtreeRangeIter::new_untyped(
+            self.root.map(|(p, _)| p),
+            self.fixed_key_size,
+            self.fixed_value_size,
+            self.mem,
+        )
+    }
+
     pub(crate) fn verify_checksum(&self) -> bool {
         if let Some((root, checksum)) = self.root {
             self.verify_checksum_helper(root, checksum)
@@ -199,13 +635,23 @@ impl<'a> RawBtree<'a> {
         let node_mem = page.memory();
         match node_mem[0] {
             LEAF => {
-                expected_checksum
-                    == leaf_checksum(
+                if expected_checksum
+                    != leaf_checksum(
                         &page,
                         self.fixed_key_size,
                         self.fixed_value_size,
                         self.mem.checksum_type(),
                     )
+                {
+                    return false;
+                }
+                let accessor = LeafAccessor::new(&page, self.fixed_key_size, self.fixed_value_size);
+                (0..accessor.num_pairs())
+                    .filter(|i| accessor.value_is_out_of_line(*i))
+                    .all(|i| {
+                        ValueReference::from_bytes(accessor.entry(i).unwrap().value())
+                            .verify_checksum(self.mem)
+                    })
             }
             BRANCH => {
                 if expected_checksum
@@ -246,6 +692,10 @@ impl<'a, K: RedbKey + ?Sized, V: RedbValue + ?Sized> Btree<'a, K, V> {
         }
     }
 
+    pub(crate) fn mem(&self) -> &'a TransactionalMemory {
+        self.mem
+    }
+
     pub(crate) fn get(
         &self,
         key: &K,
@@ -258,29 +708,251 @@ impl<'a, K: RedbKey + ?Sized, V: RedbValue + ?Sized> Btree<'a, K, V> {
         }
     }
 
+    // Returns a copy of the serialized value for the key, if present
+    pub(crate) fn get_owned(&self, key: &K) -> Result<Option<AccessGuard<'a, V>>> {
+        if let Some((p, _)) = self.root {
+            let root_page = self.mem.get_page(p);
+            let value = self.get_bytes_helper(root_page, key.as_bytes().as_ref());
+            Ok(value.map(|value| AccessGuard::with_owned_value(value.to_vec(), self.mem)))
+        } else {
+            Ok(None)
+        }
+    }
+
     // Returns the value for the queried key, if present
     fn get_helper(
         &self,
         page: PageImpl<'a>,
         query: &[u8],
     ) -> Option<<<V as RedbValue>::View as WithLifetime<'a>>::Out> {
+        self.get_bytes_helper(page, query).map(V::from_bytes)
+    }
+
+    // Returns the serialized value for the queried key, if present
+    fn get_bytes_helper(&self, page: PageImpl<'a>, query: &[u8]) -> Option<&'a [u8]> {
         let node_mem = page.memory();
         match node_mem[0] {
             LEAF => {
                 let accessor = LeafAccessor::new(&page, K::fixed_width(), V::fixed_width());
                 let entry_index = accessor.find_key::<K>(query)?;
                 let (start, end) = accessor.value_range(entry_index).unwrap();
-                Some(V::from_bytes(&page.into_memory()[start..end]))
+                let out_of_line = accessor.value_is_out_of_line(entry_index);
+                drop(accessor);
+                let value = &page.into_memory()[start..end];
+                Some(resolve_value(value, out_of_line, self.mem))
             }
             BRANCH => {
                 let accessor = BranchAccessor::new(&page, K::fixed_width());
                 let (_, child_page) = accessor.child_for_key::<K>(query);
-                self.get_helper(self.mem.get_page(child_page), query)
+                self.get_bytes_helper(self.mem.get_page(child_page), query)
             }
             _ => unreachable!(),
         }
     }
 
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn first(
+        &self,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+            <<V as RedbValue>::View as WithLifetime<'a>>::Out,
+        )>,
+    > {
+        Ok(self
+            .edge_entry(false)
+            .map(|(key, value)| (K::from_bytes(key), V::from_bytes(value))))
+    }
+
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn last(
+        &self,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+            <<V as RedbValue>::View as WithLifetime<'a>>::Out,
+        )>,
+    > {
+        Ok(self
+            .edge_entry(true)
+            .map(|(key, value)| (K::from_bytes(key), V::from_bytes(value))))
+    }
+
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn floor(
+        &self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+            <<V as RedbValue>::View as WithLifetime<'a>>::Out,
+        )>,
+    > {
+        Ok(self
+            .nearest_entry(key.as_bytes().as_ref(), true, true)
+            .map(|(key, value)| (K::from_bytes(key), V::from_bytes(value))))
+    }
+
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn ceiling(
+        &self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+            <<V as RedbValue>::View as WithLifetime<'a>>::Out,
+        )>,
+    > {
+        Ok(self
+            .nearest_entry(key.as_bytes().as_ref(), false, true)
+            .map(|(key, value)| (K::from_bytes(key), V::from_bytes(value))))
+    }
+
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn predecessor(
+        &self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+            <<V as RedbValue>::View as WithLifetime<'a>>::Out,
+        )>,
+    > {
+        Ok(self
+            .nearest_entry(key.as_bytes().as_ref(), true, false)
+            .map(|(key, value)| (K::from_bytes(key), V::from_bytes(value))))
+    }
+
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn successor(
+        &self,
+        key: &K,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+            <<V as RedbValue>::View as WithLifetime<'a>>::Out,
+        )>,
+    > {
+        Ok(self
+            .nearest_entry(key.as_bytes().as_ref(), false, false)
+            .map(|(key, value)| (K::from_bytes(key), V::from_bytes(value))))
+    }
+
+    // Returns the key and value of the entry nearest to the query, which is below it if `below` is
+    // true and above it otherwise. An entry equal to the query is only returned if `inclusive` is true
+    fn nearest_entry(
+        &self,
+        query: &[u8],
+        below: bool,
+        inclusive: bool,
+    ) -> Option<(&'a [u8], &'a [u8])> {
+        let (p, _) = self.root?;
+        self.nearest_helper(self.mem.get_page(p), query, below, inclusive)
+    }
+
+    fn nearest_helper(
+        &self,
+        page: PageImpl<'a>,
+        query: &[u8],
+        below: bool,
+        inclusive: bool,
+    ) -> Option<(&'a [u8], &'a [u8])> {
+        match page.memory()[0] {
+            LEAF => {
+                let accessor = LeafAccessor::new(&page, K::fixed_width(), V::fixed_width());
+                let (position, found) = accessor.position::<K>(query);
+                let entry = if below {
+                    if found && inclusive {
+                        Some(position)
+                    } else {
+                        position.checked_sub(1)
+                    }
+                } else if found && !inclusive {
+                    Some(position + 1)
+                } else {
+                    Some(position)
+                };
+                let (key_start, key_end) = accessor.key_range(entry?)?;
+                let (value_start, value_end) = accessor.value_range(entry?)?;
+                let out_of_line = accessor.value_is_out_of_line(entry?);
+                drop(accessor);
+                let memory = page.into_memory();
+                let value = &memory[value_start..value_end];
+                Some((
+                    &memory[key_start..key_end],
+                    resolve_value(value, out_of_line, self.mem),
+                ))
+            }
+            BRANCH => {
+                let accessor = BranchAccessor::new(&page, K::fixed_width());
+                let (child_index, child_page) = accessor.child_for_key::<K>(query);
+                // If the child has no suitable entry, the nearest one is at the adjacent edge of
+                // its sibling
+                let sibling = if below {
+                    child_index.checked_sub(1)
+                } else {
+                    Some(child_index + 1)
+                }
+                .and_then(|i| accessor.child_page(i));
+                drop(accessor);
+                self.nearest_helper(self.mem.get_page(child_page), query, below, inclusive)
+                    .or_else(|| {
+                        sibling.and_then(|s| self.edge_entry_helper(self.mem.get_page(s), below))
+                    })
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // Returns the key and value of the first, or last, entry in the tree
+    fn edge_entry(&self, last: bool) -> Option<(&'a [u8], &'a [u8])> {
+        let (p, _) = self.root?;
+        self.edge_entry_helper(self.mem.get_page(p), last)
+    }
+
+    fn edge_entry_helper(
+        &self,
+        mut page: PageImpl<'a>,
+        last: bool,
+    ) -> Option<(&'a [u8], &'a [u8])> {
+        loop {
+            match page.memory()[0] {
+                LEAF => {
+                    let accessor = LeafAccessor::new(&page, K::fixed_width(), V::fixed_width());
+                    let entry = if last { accessor.num_pairs() - 1 } else { 0 };
+                    let (key_start, key_end) = accessor.key_range(entry).unwrap();
+                    let (value_start, value_end) = accessor.value_range(entry).unwrap();
+                    let out_of_line = accessor.value_is_out_of_line(entry);
+                    drop(accessor);
+                    let memory = page.into_memory();
+                    let value = &memory[value_start..value_end];
+                    return Some((
+                        &memory[key_start..key_end],
+                        resolve_value(value, out_of_line, self.mem),
+                    ));
+                }
+                BRANCH => {
+                    let accessor = BranchAccessor::new(&page, K::fixed_width());
+                    let child = if last {
+                        accessor.count_children() - 1
+                    } else {
+                        0
+                    };
+                    let child_page = accessor.child_page(child).unwrap();
+                    drop(accessor);
+                    page = self.mem.get_page(child_page);
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
     pub(crate) fn range<T: RangeBounds<KR>, KR: Borrow<K> + 'a>(
         &self,
         range: T,
@@ -292,14 +964,122 @@ impl<'a, K: RedbKey + ?Sized, V: RedbValue + ?Sized> Btree<'a, K, V> {
         ))
     }
 
+    pub(crate) fn cursor(&self) -> BtreeCursor<'a, K, V> {
+        BtreeCursor::new(self.root.map(|(p, _)| p), self.mem)
+    }
+
     pub(crate) fn len(&self) -> Result<usize> {
-        let mut iter: BtreeRangeIter<K, V> =
-            BtreeRangeIter::new::<RangeFull, K>(.., self.root.map(|(p, _)| p), self.mem);
+        RawBtree::new(self.root, K::fixed_width(), V::fixed_width(), self.mem).len()
+    }
+
+    pub(crate) fn rank(&self, key: &K) -> Result<usize> {
+        Ok(self.count_below(key.as_bytes().as_ref(), false))
+    }
+
+    pub(crate) fn range_len<T: RangeBounds<KR>, KR: Borrow<K> + 'a>(
+        &self,
+        range: T,
+    ) -> Result<usize> {
+        let start = match range.start_bound() {
+            Bound::Included(key) => self.count_below(key.borrow().as_bytes().as_ref(), false),
+            Bound::Excluded(key) => self.count_below(key.borrow().as_bytes().as_ref(), true),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(key) => self.count_below(key.borrow().as_bytes().as_ref(), true),
+            Bound::Excluded(key) => self.count_below(key.borrow().as_bytes().as_ref(), false),
+            Bound::Unbounded => self.len()?,
+        };
+        Ok(end.saturating_sub(start))
+    }
+
+    // TODO: Simplify this when GATs are stable
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn get_by_index(
+        &self,
+        index: usize,
+    ) -> Result<
+        Option<(
+            <<K as RedbValue>::View as WithLifetime<'a>>::Out,
+            <<V as RedbValue>::View as WithLifetime<'a>>::Out,
+        )>,
+    > {
+        Ok(self
+            .entry_at(index as u64)
+            .map(|(key, value)| (K::from_bytes(key), V::from_bytes(value))))
+    }
+
+    // Returns the number of entries with keys less than the query, or less than or equal to it if
+    // `inclusive` is true
+    fn count_below(&self, query: &[u8], inclusive: bool) -> usize {
+        let mut page = if let Some((p, _)) = self.root {
+            self.mem.get_page(p)
+        } else {
+            return 0;
+        };
         let mut count = 0;
-        while iter.next().is_some() {
-            count += 1;
+        loop {
+            match page.memory()[0] {
+                LEAF => {
+                    let accessor = LeafAccessor::new(&page, K::fixed_width(), V::fixed_width());
+                    let (position, found) = accessor.position::<K>(query);
+                    count += position as u64;
+                    if found && inclusive {
+                        count += 1;
+                    }
+                    return count as usize;
+                }
+                BRANCH => {
+                    let accessor = BranchAccessor::new(&page, K::fixed_width());
+                    // Every entry in the children before this one is less than the query
+                    let (child_index, child_page) = accessor.child_for_key::<K>(query);
+                    count += (0..child_index)
+                        .map(|i| accessor.child_len(i).unwrap())
+                        .sum::<u64>();
+                    drop(accessor);
+                    page = self.mem.get_page(child_page);
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    // Returns the key and value of the entry at the given position in the tree
+    fn entry_at(&self, mut index: u64) -> Option<(&'a [u8], &'a [u8])> {
+        let (p, _) = self.root?;
+        let mut page = self.mem.get_page(p);
+        loop {
+            match page.memory()[0] {
+                LEAF => {
+                    let accessor = LeafAccessor::new(&page, K::fixed_width(), V::fixed_width());
+                    let (key_start, key_end) = accessor.key_range(index.try_into().ok()?)?;
+                    let (value_start, value_end) = accessor.value_range(index.try_into().ok()?)?;
+                    let out_of_line = accessor.value_is_out_of_line(index.try_into().ok()?);
+                    drop(accessor);
+                    let memory = page.into_memory();
+                    let value = &memory[value_start..value_end];
+                    return Some((
+                        &memory[key_start..key_end],
+                        resolve_value(value, out_of_line, self.mem),
+                    ));
+                }
+                BRANCH => {
+                    let accessor = BranchAccessor::new(&page, K::fixed_width());
+                    let mut child_page = None;
+                    for i in 0..accessor.count_children() {
+                        let child_len = accessor.child_len(i).unwrap();
+                        if index < child_len {
+                            child_page = accessor.child_page(i);
+                            break;
+                        }
+                        index -= child_len;
+                    }
+                    drop(accessor);
+                    page = self.mem.get_page(child_page?);
+                }
+                _ => unreachable!(),
+            }
         }
-        Ok(count)
     }
 
     #[allow(dead_code)]
@@ -366,9 +1146,18 @@ fn stats_helper(
     match node_mem[0] {
         LEAF => {
             let accessor = LeafAccessor::new(&page, fixed_key_size, fixed_value_size);
-            let leaf_bytes = accessor.length_of_pairs(0, accessor.num_pairs());
-            let overhead_bytes = accessor.total_length() - leaf_bytes;
-            let fragmented_bytes = page.memory().len() - accessor.total_length();
+            let mut leaf_bytes = accessor.length_of_pairs(0, accessor.num_pairs());
+            let mut overhead_bytes = accessor.total_length() - leaf_bytes;
+            let mut fragmented_bytes = page.memory().len() - accessor.total_length();
+            // Values stored out of line are counted as stored bytes, and their references as metadata
+            for i in 0..accessor.num_pairs() {
+                if accessor.value_is_out_of_line(i) {
+                    let reference = ValueReference::from_bytes(accessor.entry(i).unwrap().value());
+                    leaf_bytes = leaf_bytes + reference.len - ValueReference::SERIALIZED_SIZE;
+                    overhead_bytes += ValueReference::SERIALIZED_SIZE;
+                    fragmented_bytes += mem.get_page(reference.page).memory().len() - reference.len;
+                }
+            }
             BtreeStats {
                 tree_height: 1,
                 leaf_pages: 1,
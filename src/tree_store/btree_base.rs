@@ -2,7 +2,8 @@ use crate::tree_store::page_store::{ChecksumType, Page, PageImpl, PageMut, Trans
 use crate::tree_store::{page_store, PageNumber};
 use crate::types::{RedbKey, RedbValue, WithLifetime};
 use crate::Result;
-use std::cmp::Ordering;
+use std::cmp::{min, Ordering};
+use std::io;
 use std::marker::PhantomData;
 use std::mem::size_of;
 
@@ -11,6 +12,91 @@ pub(super) const BRANCH: u8 = 2;
 
 pub(crate) type Checksum = u128;
 
+// Bytes used by each child of a branch page, for its checksum, page number, and subtree length
+const BRANCH_CHILD_BYTES: usize =
+    size_of::<Checksum>() + PageNumber::serialized_size() + size_of::<u64>();
+
+// Set in the value end offset of entries whose value is stored out of line, in its own pages
+const OUT_OF_LINE_FLAG: u32 = 1 << 31;
+
+// A value stored out of line. The leaf stores this reference in place of the value
+// Layout is:
+// 8 bytes: page number
+// 8 bytes: length of the value
+// 16 bytes: checksum of the value
+pub(super) struct ValueReference {
+    pub(super) page: PageNumber,
+    pub(super) len: usize,
+    pub(super) checksum: Checksum,
+}
+
+impl ValueReference {
+    pub(super) const SERIALIZED_SIZE: usize =
+        PageNumber::serialized_size() + size_of::<u64>() + size_of::<Checksum>();
+
+    pub(super) fn from_bytes(data: &[u8]) -> Self {
+        debug_assert_eq!(data.len(), Self::SERIALIZED_SIZE);
+        let mut offset = 0;
+        let page = PageNumber::from_le_bytes(
+            data[offset..(offset + PageNumber::serialized_size())]
+                .try_into()
+                .unwrap(),
+        );
+        offset += PageNumber::serialized_size();
+        let len = u64::from_le_bytes(
+            data[offset..(offset + size_of::<u64>())]
+                .try_into()
+                .unwrap(),
+        ) as usize;
+        offset += size_of::<u64>();
+        let checksum = Checksum::from_le_bytes(
+            data[offset..(offset + size_of::<Checksum>())]
+                .try_into()
+                .unwrap(),
+        );
+        Self {
+            page,
+            len,
+            checksum,
+        }
+    }
+
+    pub(super) fn to_bytes(&self) -> [u8; Self::SERIALIZED_SIZE] {
+        let mut result = [0; Self::SERIALIZED_SIZE];
+        let mut offset = 0;
+        result[offset..(offset + PageNumber::serialized_size())]
+            .copy_from_slice(&self.page.to_le_bytes());
+        offset += PageNumber::serialized_size();
+        result[offset..(offset + size_of::<u64>())]
+            .copy_from_slice(&(self.len as u64).to_le_bytes());
+        offset += size_of::<u64>();
+        result[offset..(offset + size_of::<Checksum>())]
+            .copy_from_slice(&self.checksum.to_le_bytes());
+        result
+    }
+
+    pub(super) fn value<'a>(&self, mem: &'a TransactionalMemory) -> &'a [u8] {
+        &mem.get_page(self.page).into_memory()[..self.len]
+    }
+
+    pub(super) fn verify_checksum(&self, mem: &TransactionalMemory) -> bool {
+        mem.checksum_type().checksum(self.value(mem)) == self.checksum
+    }
+}
+
+// Returns the value which is stored in a leaf as the given bytes
+pub(super) fn resolve_value<'a>(
+    stored: &'a [u8],
+    out_of_line: bool,
+    mem: &'a TransactionalMemory,
+) -> &'a [u8] {
+    if out_of_line {
+        ValueReference::from_bytes(stored).value(mem)
+    } else {
+        stored
+    }
+}
+
 pub(super) fn leaf_checksum<T: Page>(
     page: &T,
     fixed_key_size: Option<usize>,
@@ -139,7 +225,7 @@ impl<'a, V: RedbValue + ?Sized> AccessGuard<'a, V> {
         }
     }
 
-    pub(super) fn with_owned_value(value: Vec<u8>, mem: &'a TransactionalMemory) -> Self {
+    pub(crate) fn with_owned_value(value: Vec<u8>, mem: &'a TransactionalMemory) -> Self {
         let len = value.len();
         Self {
             page: EitherPage::OwnedMemory(value),
@@ -174,9 +260,20 @@ impl<'a, V: RedbValue + ?Sized> AccessGuard<'a, V> {
         }
     }
 
+    // Copies the value out of its page, and performs any pending modification of that page
+    pub(crate) fn into_owned(self) -> Self {
+        let value = self.page.memory()[self.offset..(self.offset + self.len)].to_vec();
+        Self::with_owned_value(value, self.mem)
+    }
+
+    // Returns the serialized value
+    pub(crate) fn value_bytes(&self) -> &[u8] {
+        &self.page.memory()[self.offset..(self.offset + self.len)]
+    }
+
     // TODO: implement Deref instead of this to_value() method, when GAT is stable
     pub fn to_value(&self) -> <<V as RedbValue>::View as WithLifetime>::Out {
-        V::from_bytes(&self.page.memory()[self.offset..(self.offset + self.len)])
+        V::from_bytes(self.value_bytes())
     }
 }
 
@@ -205,15 +302,48 @@ impl<'a, V: RedbValue + ?Sized> Drop for AccessGuard<'a, V> {
     }
 }
 
+/// Space reserved for a value, which can be written in place
+///
+/// The guard borrows the table that the space was reserved in, so the table cannot be used until
+/// it is dropped. The value can be written either through [`AsMut`], or sequentially through
+/// [`io::Write`], which accepts no more than the reserved number of bytes
 pub struct AccessGuardMut<'a> {
     page: PageMut<'a>,
     offset: usize,
     len: usize,
+    // Number of bytes written through io::Write
+    position: usize,
 }
 
 impl<'a> AccessGuardMut<'a> {
     pub(crate) fn new(page: PageMut<'a>, offset: usize, len: usize) -> Self {
-        AccessGuardMut { page, offset, len }
+        AccessGuardMut {
+            page,
+            offset,
+            len,
+            position: 0,
+        }
+    }
+
+    /// Returns the number of bytes reserved for the value
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if no bytes were reserved for the value
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of reserved bytes that have not yet been written through [`io::Write`]
+    pub fn remaining(&self) -> usize {
+        self.len - self.position
+    }
+}
+
+impl<'a> AsRef<[u8]> for AccessGuardMut<'a> {
+    fn as_ref(&self) -> &[u8] {
+        &self.page.memory()[self.offset..(self.offset + self.len)]
     }
 }
 
@@ -224,15 +354,45 @@ impl<'a> AsMut<[u8]> for AccessGuardMut<'a> {
     }
 }
 
+impl<'a> io::Write for AccessGuardMut<'a> {
+    // Writes are truncated to the reserved length, so write_all() fails once it is exceeded
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = min(buf.len(), self.remaining());
+        let start = self.offset + self.position;
+        self.page.memory_mut()[start..(start + n)].copy_from_slice(&buf[..n]);
+        self.position += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 // Provides a simple zero-copy way to access entries
 pub struct EntryAccessor<'a> {
     key: &'a [u8],
     value: &'a [u8],
+    // value is a ValueReference
+    out_of_line: bool,
 }
 
 impl<'a> EntryAccessor<'a> {
-    fn new(key: &'a [u8], value: &'a [u8]) -> Self {
-        EntryAccessor { key, value }
+    pub(super) fn new(key: &'a [u8], value: &'a [u8], out_of_line: bool) -> Self {
+        EntryAccessor {
+            key,
+            value,
+            out_of_line,
+        }
+    }
+
+    // Reads the value from its own pages, if it is stored out of line
+    pub(super) fn resolve(&self, mem: &'a TransactionalMemory) -> Self {
+        EntryAccessor {
+            key: self.key,
+            value: resolve_value(self.value, self.out_of_line, mem),
+            out_of_line: false,
+        }
     }
 }
 
@@ -244,6 +404,10 @@ impl<'a: 'b, 'b> EntryAccessor<'a> {
     pub(crate) fn value(&'b self) -> &'a [u8] {
         self.value
     }
+
+    pub(super) fn out_of_line(&self) -> bool {
+        self.out_of_line
+    }
 }
 
 // Provides a simple zero-copy way to access a leaf page
@@ -373,19 +537,38 @@ impl<'a: 'b, 'b, T: Page + 'a> LeafAccessor<'a, 'b, T> {
             if let Some(fixed) = self.fixed_value_size {
                 return Some(self.key_end(self.num_pairs - 1).unwrap() + fixed * (n + 1));
             }
-            let mut offset = 4 + size_of::<u32>() * n;
-            if self.fixed_key_size.is_none() {
-                offset += size_of::<u32>() * self.num_pairs;
-            }
-            let end = u32::from_le_bytes(
-                self.page.memory()[offset..(offset + size_of::<u32>())]
-                    .try_into()
-                    .unwrap(),
-            ) as usize;
-            Some(end)
+            Some((self.raw_value_end(n) & !OUT_OF_LINE_FLAG) as usize)
         }
     }
 
+    // Returns the value end offset of a variable width value, including its flags
+    fn raw_value_end(&self, n: usize) -> u32 {
+        let mut offset = 4 + size_of::<u32>() * n;
+        if self.fixed_key_size.is_none() {
+            offset += size_of::<u32>() * self.num_pairs;
+        }
+        u32::from_le_bytes(
+            self.page.memory()[offset..(offset + size_of::<u32>())]
+                .try_into()
+                .unwrap(),
+        )
+    }
+
+    // Returns true if the nth value is stored out of line, and the leaf stores a ValueReference to it
+    pub(super) fn value_is_out_of_line(&self, n: usize) -> bool {
+        self.fixed_value_size.is_none()
+            && n < self.num_pairs()
+            && self.raw_value_end(n) & OUT_OF_LINE_FLAG != 0
+    }
+
+    // Returns the pages of all the values stored out of line
+    pub(super) fn out_of_line_pages(&self) -> Vec<PageNumber> {
+        (0..self.num_pairs())
+            .filter(|i| self.value_is_out_of_line(*i))
+            .map(|i| ValueReference::from_bytes(self.entry(i).unwrap().value()).page)
+            .collect()
+    }
+
     pub(super) fn num_pairs(&self) -> usize {
         self.num_pairs
     }
@@ -398,6 +581,10 @@ impl<'a: 'b, 'b, T: Page + 'a> LeafAccessor<'a, 'b, T> {
         self.value_start(n)
     }
 
+    pub(super) fn key_range(&self, n: usize) -> Option<(usize, usize)> {
+        Some((self.key_start(n)?, self.key_end(n)?))
+    }
+
     pub(super) fn value_range(&self, n: usize) -> Option<(usize, usize)> {
         Some((self.value_start(n)?, self.value_end(n)?))
     }
@@ -438,7 +625,7 @@ impl<'a: 'b, 'b, T: Page + 'a> LeafAccessor<'a, 'b, T> {
     pub(super) fn entry(&self, n: usize) -> Option<EntryAccessor<'b>> {
         let key = &self.page.memory()[self.key_start(n)?..self.key_end(n)?];
         let value = &self.page.memory()[self.value_start(n)?..self.value_end(n)?];
-        Some(EntryAccessor::new(key, value))
+        Some(EntryAccessor::new(key, value, self.value_is_out_of_line(n)))
     }
 
     pub(super) fn last_entry(&self) -> EntryAccessor<'b> {
@@ -447,7 +634,8 @@ impl<'a: 'b, 'b, T: Page + 'a> LeafAccessor<'a, 'b, T> {
 }
 
 pub(super) struct LeafBuilder<'a, 'b> {
-    pairs: Vec<(&'a [u8], &'a [u8])>,
+    // Key, value, and whether the value is a ValueReference
+    pairs: Vec<(&'a [u8], &'a [u8], bool)>,
     fixed_key_size: Option<usize>,
     fixed_value_size: Option<usize>,
     total_key_bytes: usize,
@@ -482,10 +670,15 @@ impl<'a, 'b> LeafBuilder<'a, 'b> {
         }
     }
 
-    pub(super) fn push(&mut self, key: &'a [u8], value: &'a [u8]) {
+    // out_of_line indicates that value is a ValueReference
+    pub(super) fn push(&mut self, key: &'a [u8], value: &'a [u8], out_of_line: bool) {
         self.total_key_bytes += key.len();
         self.total_value_bytes += value.len();
-        self.pairs.push((key, value))
+        self.pairs.push((key, value, out_of_line))
+    }
+
+    pub(super) fn push_entry(&mut self, entry: &EntryAccessor<'a>) {
+        self.push(entry.key(), entry.value(), entry.out_of_line());
     }
 
     pub(super) fn push_all_except<T: Page>(
@@ -499,8 +692,7 @@ impl<'a, 'b> LeafBuilder<'a, 'b> {
                     continue;
                 }
             }
-            let entry = accessor.entry(i).unwrap();
-            self.push(entry.key(), entry.value());
+            self.push_entry(&accessor.entry(i).unwrap());
         }
     }
 
@@ -517,7 +709,7 @@ impl<'a, 'b> LeafBuilder<'a, 'b> {
         let mut division = 0;
         let mut first_split_key_bytes = 0;
         let mut first_split_value_bytes = 0;
-        for (key, value) in self.pairs.iter().take(self.pairs.len() - 1) {
+        for (key, value, _) in self.pairs.iter().take(self.pairs.len() - 1) {
             first_split_key_bytes += key.len();
             first_split_value_bytes += value.len();
             division += 1;
@@ -536,8 +728,8 @@ impl<'a, 'b> LeafBuilder<'a, 'b> {
             self.fixed_value_size,
             first_split_key_bytes,
         );
-        for (key, value) in self.pairs.iter().take(division) {
-            builder.append(key, value);
+        for (key, value, out_of_line) in self.pairs.iter().take(division) {
+            builder.append(key, value, *out_of_line);
         }
         drop(builder);
 
@@ -555,8 +747,8 @@ impl<'a, 'b> LeafBuilder<'a, 'b> {
             self.fixed_value_size,
             self.total_key_bytes - first_split_key_bytes,
         );
-        for (key, value) in self.pairs[division..].iter() {
-            builder.append(key, value);
+        for (key, value, out_of_line) in self.pairs[division..].iter() {
+            builder.append(key, value, *out_of_line);
         }
         drop(builder);
 
@@ -576,8 +768,8 @@ impl<'a, 'b> LeafBuilder<'a, 'b> {
             self.fixed_value_size,
             self.total_key_bytes,
         );
-        for (key, value) in self.pairs {
-            builder.append(key, value);
+        for (key, value, out_of_line) in self.pairs {
+            builder.append(key, value, out_of_line);
         }
         drop(builder);
         Ok(page)
@@ -593,7 +785,7 @@ impl<'a, 'b> LeafBuilder<'a, 'b> {
 // (optional) repeating (num_entries times):
 // 4 bytes: key_end
 // (optional) repeating (num_entries times):
-// 4 bytes: value_end
+// 4 bytes: value_end. The high bit is set if the value is a reference to a value stored out of line
 // repeating (num_entries times):
 // * n bytes: key data
 // repeating (num_entries times):
@@ -637,17 +829,19 @@ impl<'a: 'b, 'b> RawLeafBuilder<'a, 'b> {
 
     fn value_end(&self, n: usize) -> usize {
         if let Some(fixed) = self.fixed_value_size {
-            return self.key_end(self.num_pairs - 1) + fixed * (n + 1);
+            // The end of the last key may not have been written yet, so use the provisioned size
+            return self.key_section_start() + self.provisioned_key_bytes + fixed * (n + 1);
         }
         let mut offset = 4 + size_of::<u32>() * n;
         if self.fixed_key_size.is_none() {
             offset += size_of::<u32>() * self.num_pairs;
         }
-        u32::from_le_bytes(
+        let end = u32::from_le_bytes(
             self.page.memory()[offset..(offset + size_of::<u32>())]
                 .try_into()
                 .unwrap(),
-        ) as usize
+        );
+        (end & !OUT_OF_LINE_FLAG) as usize
     }
 
     fn key_section_start(&self) -> usize {
@@ -674,7 +868,7 @@ impl<'a: 'b, 'b> RawLeafBuilder<'a, 'b> {
         ) as usize
     }
 
-    fn append(&mut self, key: &[u8], value: &[u8]) {
+    fn append(&mut self, key: &[u8], value: &[u8], out_of_line: bool) {
         let key_offset = if self.pairs_written == 0 {
             self.key_section_start()
         } else {
@@ -701,8 +895,15 @@ impl<'a: 'b, 'b> RawLeafBuilder<'a, 'b> {
             if self.fixed_key_size.is_none() {
                 offset += size_of::<u32>() * self.num_pairs;
             }
+            let mut end = (value_offset + value.len()) as u32;
+            debug_assert_eq!(end & OUT_OF_LINE_FLAG, 0);
+            if out_of_line {
+                end |= OUT_OF_LINE_FLAG;
+            }
             self.page.memory_mut()[offset..(offset + size_of::<u32>())]
-                .copy_from_slice(&((value_offset + value.len()) as u32).to_le_bytes());
+                .copy_from_slice(&end.to_le_bytes());
+        } else {
+            debug_assert!(!out_of_line);
         }
         self.page.memory_mut()[value_offset..(value_offset + value.len())].copy_from_slice(value);
         self.pairs_written += 1;
@@ -763,8 +964,16 @@ impl<'a: 'b, 'b> LeafMutator<'a, 'b> {
         }
     }
 
-    // Insert the given key, value pair at index i and shift all following pairs to the right
-    pub(super) fn insert(&mut self, i: usize, overwrite: bool, key: &[u8], value: &[u8]) {
+    // Insert the given key, value pair at index i and shift all following pairs to the right.
+    // out_of_line indicates that value is a ValueReference
+    pub(super) fn insert(
+        &mut self,
+        i: usize,
+        overwrite: bool,
+        key: &[u8],
+        value: &[u8],
+        out_of_line: bool,
+    ) {
         let accessor = LeafAccessor::new(self.page, self.fixed_key_size, self.fixed_value_size);
         let required_delta = if overwrite {
             (key.len() + value.len()) as isize - accessor.length_of_pairs(i, i + 1) as isize
@@ -839,9 +1048,15 @@ impl<'a: 'b, 'b> LeafMutator<'a, 'b> {
         self.page.memory_mut().copy_within(start..end, dest);
 
         // Insert the value
-        let inserted_value_end = dest as u32;
+        let mut inserted_value_end = dest as u32;
+        if out_of_line {
+            inserted_value_end |= OUT_OF_LINE_FLAG;
+        }
         dest -= value.len();
         self.page.memory_mut()[dest..(dest + value.len())].copy_from_slice(value);
+        if overwrite {
+            self.set_out_of_line(i, out_of_line);
+        }
 
         if !overwrite {
             // Right shift the trailing key data & preceding value data
@@ -993,6 +1208,33 @@ impl<'a: 'b, 'b> LeafMutator<'a, 'b> {
             .copy_from_slice(&ptr.to_le_bytes());
     }
 
+    fn set_out_of_line(&mut self, i: usize, out_of_line: bool) {
+        if self.fixed_value_size.is_some() {
+            debug_assert!(!out_of_line);
+            return;
+        }
+        let accessor = LeafAccessor::new(self.page, self.fixed_key_size, self.fixed_value_size);
+        let num_pairs = accessor.num_pairs();
+        drop(accessor);
+        let mut offset = 4 + size_of::<u32>() * i;
+        if self.fixed_key_size.is_none() {
+            offset += size_of::<u32>() * num_pairs;
+        }
+        let mut ptr = u32::from_le_bytes(
+            self.page.memory()[offset..(offset + size_of::<u32>())]
+                .try_into()
+                .unwrap(),
+        );
+        if out_of_line {
+            ptr |= OUT_OF_LINE_FLAG;
+        } else {
+            ptr &= !OUT_OF_LINE_FLAG;
+        }
+        self.page.memory_mut()[offset..(offset + size_of::<u32>())]
+            .copy_from_slice(&ptr.to_le_bytes());
+    }
+
+    // The delta is applied to the offset, and preserves its flags
     fn update_value_end(&mut self, i: usize, delta: isize) {
         if self.fixed_value_size.is_some() {
             return;
@@ -1080,10 +1322,9 @@ impl<'a: 'b, 'b, T: Page + 'a> BranchAccessor<'a, 'b, T> {
 
     fn key_section_start(&self) -> usize {
         if self.fixed_key_size.is_none() {
-            4 + (PageNumber::serialized_size() + size_of::<Checksum>()) * self.count_children()
-                + size_of::<u32>() * self.num_keys()
+            4 + BRANCH_CHILD_BYTES * self.count_children() + size_of::<u32>() * self.num_keys()
         } else {
-            4 + (PageNumber::serialized_size() + size_of::<Checksum>()) * self.count_children()
+            4 + BRANCH_CHILD_BYTES * self.count_children()
         }
     }
 
@@ -1099,9 +1340,7 @@ impl<'a: 'b, 'b, T: Page + 'a> BranchAccessor<'a, 'b, T> {
         if let Some(fixed) = self.fixed_key_size {
             return self.key_section_start() + fixed * (n + 1);
         }
-        let offset = 4
-            + (PageNumber::serialized_size() + size_of::<Checksum>()) * self.count_children()
-            + size_of::<u32>() * n;
+        let offset = 4 + BRANCH_CHILD_BYTES * self.count_children() + size_of::<u32>() * n;
         u32::from_le_bytes(
             self.page.memory()[offset..(offset + size_of::<u32>())]
                 .try_into()
@@ -1149,13 +1388,36 @@ impl<'a: 'b, 'b, T: Page + 'a> BranchAccessor<'a, 'b, T> {
         ))
     }
 
+    // Returns the number of entries in the subtree rooted at the nth child
+    pub(super) fn child_len(&self, n: usize) -> Option<u64> {
+        if n >= self.count_children() {
+            return None;
+        }
+
+        let offset = 4
+            + (size_of::<Checksum>() + PageNumber::serialized_size()) * self.count_children()
+            + size_of::<u64>() * n;
+        Some(u64::from_le_bytes(
+            self.page.memory()[offset..(offset + size_of::<u64>())]
+                .try_into()
+                .unwrap(),
+        ))
+    }
+
+    // Returns the number of entries in the subtree rooted at this page
+    pub(super) fn subtree_len(&self) -> u64 {
+        (0..self.count_children())
+            .map(|i| self.child_len(i).unwrap())
+            .sum()
+    }
+
     fn num_keys(&self) -> usize {
         self.num_keys
     }
 }
 
 pub(super) struct BranchBuilder<'a, 'b> {
-    children: Vec<(PageNumber, Checksum)>,
+    children: Vec<(PageNumber, Checksum, u64)>,
     keys: Vec<&'a [u8]>,
     total_key_bytes: usize,
     fixed_key_size: Option<usize>,
@@ -1177,12 +1439,18 @@ impl<'a, 'b> BranchBuilder<'a, 'b> {
         }
     }
 
-    pub(super) fn replace_child(&mut self, index: usize, child: PageNumber, checksum: Checksum) {
-        self.children[index] = (child, checksum);
+    pub(super) fn replace_child(
+        &mut self,
+        index: usize,
+        child: PageNumber,
+        checksum: Checksum,
+        len: u64,
+    ) {
+        self.children[index] = (child, checksum, len);
     }
 
-    pub(super) fn push_child(&mut self, child: PageNumber, checksum: Checksum) {
-        self.children.push((child, checksum));
+    pub(super) fn push_child(&mut self, child: PageNumber, checksum: Checksum, len: u64) {
+        self.children.push((child, checksum, len));
     }
 
     pub(super) fn push_key(&mut self, key: &'a [u8]) {
@@ -1194,14 +1462,15 @@ impl<'a, 'b> BranchBuilder<'a, 'b> {
         for i in 0..accessor.count_children() {
             let child = accessor.child_page(i).unwrap();
             let checksum = accessor.child_checksum(i).unwrap();
-            self.push_child(child, checksum);
+            let len = accessor.child_len(i).unwrap();
+            self.push_child(child, checksum, len);
         }
         for i in 0..(accessor.count_children() - 1) {
             self.push_key(accessor.key(i).unwrap());
         }
     }
 
-    pub(super) fn to_single_child(&self) -> Option<(PageNumber, Checksum)> {
+    pub(super) fn to_single_child(&self) -> Option<(PageNumber, Checksum, u64)> {
         if self.children.len() > 1 {
             None
         } else {
@@ -1218,10 +1487,12 @@ impl<'a, 'b> BranchBuilder<'a, 'b> {
         );
         let mut page = self.mem.allocate(size)?;
         let mut builder = RawBranchBuilder::new(&mut page, self.keys.len(), self.fixed_key_size);
-        builder.write_first_page(self.children[0].0, self.children[0].1);
+        let (page_number, checksum, len) = self.children[0];
+        builder.write_first_page(page_number, checksum, len);
         for i in 1..self.children.len() {
             let key = &self.keys[i - 1];
-            builder.write_nth_key(key.as_ref(), self.children[i].0, self.children[i].1, i - 1);
+            let (page_number, checksum, len) = self.children[i];
+            builder.write_nth_key(key.as_ref(), page_number, checksum, len, i - 1);
         }
         drop(builder);
 
@@ -1249,15 +1520,12 @@ impl<'a, 'b> BranchBuilder<'a, 'b> {
             RawBranchBuilder::required_bytes(division, first_split_key_len, self.fixed_key_size);
         let mut page1 = self.mem.allocate(size)?;
         let mut builder = RawBranchBuilder::new(&mut page1, division, self.fixed_key_size);
-        builder.write_first_page(self.children[0].0, self.children[0].1);
+        let (page_number, checksum, len) = self.children[0];
+        builder.write_first_page(page_number, checksum, len);
         for i in 0..division {
             let key = &self.keys[i];
-            builder.write_nth_key(
-                key.as_ref(),
-                self.children[i + 1].0,
-                self.children[i + 1].1,
-                i,
-            );
+            let (page_number, checksum, len) = self.children[i + 1];
+            builder.write_nth_key(key.as_ref(), page_number, checksum, len, i);
         }
         drop(builder);
 
@@ -1272,15 +1540,12 @@ impl<'a, 'b> BranchBuilder<'a, 'b> {
             self.keys.len() - division - 1,
             self.fixed_key_size,
         );
-        builder.write_first_page(self.children[division + 1].0, self.children[division + 1].1);
+        let (page_number, checksum, len) = self.children[division + 1];
+        builder.write_first_page(page_number, checksum, len);
         for i in (division + 1)..self.keys.len() {
             let key = &self.keys[i];
-            builder.write_nth_key(
-                key.as_ref(),
-                self.children[i + 1].0,
-                self.children[i + 1].1,
-                i - division - 1,
-            );
+            let (page_number, checksum, len) = self.children[i + 1];
+            builder.write_nth_key(key.as_ref(), page_number, checksum, len, i - division - 1);
         }
         drop(builder);
 
@@ -1298,6 +1563,8 @@ impl<'a, 'b> BranchBuilder<'a, 'b> {
 // 16 bytes: child page checksum
 // repeating (num_keys + 1 times):
 // 8 bytes: page number
+// repeating (num_keys + 1 times):
+// 8 bytes: number of entries in the child's subtree
 // (optional) repeating (num_keys times):
 // * 4 bytes: key end. Ending offset of the key, exclusive
 // repeating (num_keys times):
@@ -1316,13 +1583,10 @@ impl<'a: 'b, 'b> RawBranchBuilder<'a, 'b> {
         fixed_key_size: Option<usize>,
     ) -> usize {
         if fixed_key_size.is_none() {
-            let fixed_size = 4
-                + (PageNumber::serialized_size() + size_of::<Checksum>()) * (num_keys + 1)
-                + size_of::<u32>() * num_keys;
+            let fixed_size = 4 + BRANCH_CHILD_BYTES * (num_keys + 1) + size_of::<u32>() * num_keys;
             size_of_keys + fixed_size
         } else {
-            let fixed_size =
-                4 + (PageNumber::serialized_size() + size_of::<Checksum>()) * (num_keys + 1);
+            let fixed_size = 4 + BRANCH_CHILD_BYTES * (num_keys + 1);
             size_of_keys + fixed_size
         }
     }
@@ -1340,9 +1604,7 @@ impl<'a: 'b, 'b> RawBranchBuilder<'a, 'b> {
         {
             // Poison all the child pointers & key offsets, in case the caller forgets to write them
             let start = 4 + size_of::<Checksum>() * (num_keys + 1);
-            let last = 4
-                + (PageNumber::serialized_size() + size_of::<Checksum>()) * (num_keys + 1)
-                + size_of::<u32>() * num_keys;
+            let last = 4 + BRANCH_CHILD_BYTES * (num_keys + 1) + size_of::<u32>() * num_keys;
             for x in &mut page.memory_mut()[start..last] {
                 *x = 0xFF;
             }
@@ -1355,18 +1617,17 @@ impl<'a: 'b, 'b> RawBranchBuilder<'a, 'b> {
         }
     }
 
-    pub(super) fn write_first_page(&mut self, page_number: PageNumber, checksum: Checksum) {
-        let offset = 4;
-        self.page.memory_mut()[offset..(offset + size_of::<Checksum>())]
-            .copy_from_slice(&checksum.to_le_bytes());
-        let offset = 4 + size_of::<Checksum>() * (self.num_keys + 1);
-        self.page.memory_mut()[offset..(offset + PageNumber::serialized_size())]
-            .copy_from_slice(&page_number.to_le_bytes());
+    pub(super) fn write_first_page(
+        &mut self,
+        page_number: PageNumber,
+        checksum: Checksum,
+        len: u64,
+    ) {
+        write_branch_child(self.page, self.num_keys, 0, page_number, checksum, len);
     }
 
     fn key_section_start(&self) -> usize {
-        let mut offset =
-            4 + (PageNumber::serialized_size() + size_of::<Checksum>()) * (self.num_keys + 1);
+        let mut offset = 4 + BRANCH_CHILD_BYTES * (self.num_keys + 1);
         if self.fixed_key_size.is_none() {
             offset += size_of::<u32>() * self.num_keys;
         }
@@ -1378,9 +1639,7 @@ impl<'a: 'b, 'b> RawBranchBuilder<'a, 'b> {
         if let Some(fixed) = self.fixed_key_size {
             return self.key_section_start() + fixed * (n + 1);
         }
-        let offset = 4
-            + (PageNumber::serialized_size() + size_of::<Checksum>()) * (self.num_keys + 1)
-            + size_of::<u32>() * n;
+        let offset = 4 + BRANCH_CHILD_BYTES * (self.num_keys + 1) + size_of::<u32>() * n;
         u32::from_le_bytes(
             self.page.memory()[offset..(offset + size_of::<u32>())]
                 .try_into()
@@ -1395,19 +1654,13 @@ impl<'a: 'b, 'b> RawBranchBuilder<'a, 'b> {
         key: &[u8],
         page_number: PageNumber,
         checksum: Checksum,
+        len: u64,
         n: usize,
     ) {
         assert!(n < self.num_keys as usize);
         assert_eq!(n, self.keys_written);
         self.keys_written += 1;
-        let offset = 4 + size_of::<Checksum>() * (n + 1);
-        self.page.memory_mut()[offset..(offset + size_of::<Checksum>())]
-            .copy_from_slice(&checksum.to_le_bytes());
-        let offset = 4
-            + size_of::<Checksum>() * (self.num_keys + 1)
-            + PageNumber::serialized_size() * (n + 1);
-        self.page.memory_mut()[offset..(offset + PageNumber::serialized_size())]
-            .copy_from_slice(&page_number.to_le_bytes());
+        write_branch_child(self.page, self.num_keys, n + 1, page_number, checksum, len);
 
         let data_offset = if n > 0 {
             self.key_end(n - 1)
@@ -1415,14 +1668,12 @@ impl<'a: 'b, 'b> RawBranchBuilder<'a, 'b> {
             self.key_section_start()
         };
         if self.fixed_key_size.is_none() {
-            let offset = 4
-                + (PageNumber::serialized_size() + size_of::<Checksum>()) * (self.num_keys + 1)
-                + size_of::<u32>() * n;
+            let offset = 4 + BRANCH_CHILD_BYTES * (self.num_keys + 1) + size_of::<u32>() * n;
             self.page.memory_mut()[offset..(offset + size_of::<u32>())]
                 .copy_from_slice(&((data_offset + key.len()) as u32).to_le_bytes());
         }
 
-        debug_assert!(data_offset > offset);
+        debug_assert!(data_offset >= self.key_section_start());
         self.page.memory_mut()[data_offset..(data_offset + key.len())].copy_from_slice(key);
     }
 }
@@ -1452,14 +1703,31 @@ impl<'a: 'b, 'b> BranchMutator<'a, 'b> {
         i: usize,
         page_number: PageNumber,
         checksum: Checksum,
+        len: u64,
     ) {
         debug_assert!(i <= self.num_keys());
-        let offset = 4 + size_of::<Checksum>() * i;
-        self.page.memory_mut()[offset..(offset + size_of::<Checksum>())]
-            .copy_from_slice(&checksum.to_le_bytes());
-        let offset =
-            4 + size_of::<Checksum>() * (self.num_keys() + 1) + PageNumber::serialized_size() * i;
-        self.page.memory_mut()[offset..(offset + PageNumber::serialized_size())]
-            .copy_from_slice(&page_number.to_le_bytes());
+        let num_keys = self.num_keys();
+        write_branch_child(self.page, num_keys, i, page_number, checksum, len);
     }
 }
+
+// Writes the checksum, page number, and subtree length of the ith child of a branch page
+fn write_branch_child(
+    page: &mut PageMut,
+    num_keys: usize,
+    i: usize,
+    page_number: PageNumber,
+    checksum: Checksum,
+    len: u64,
+) {
+    let offset = 4 + size_of::<Checksum>() * i;
+    page.memory_mut()[offset..(offset + size_of::<Checksum>())]
+        .copy_from_slice(&checksum.to_le_bytes());
+    let offset = 4 + size_of::<Checksum>() * (num_keys + 1) + PageNumber::serialized_size() * i;
+    page.memory_mut()[offset..(offset + PageNumber::serialized_size())]
+        .copy_from_slice(&page_number.to_le_bytes());
+    let offset = 4
+        + (size_of::<Checksum>() + PageNumber::serialized_size()) * (num_keys + 1)
+        + size_of::<u64>() * i;
+    page.memory_mut()[offset..(offset + size_of::<u64>())].copy_from_slice(&len.to_le_bytes());
+}
@@ -5,6 +5,7 @@ use crate::tree_store::page_store::{Page, PageImpl, TransactionalMemory};
 use crate::tree_store::PageNumber;
 use crate::types::{RedbKey, RedbValue};
 use std::borrow::Borrow;
+use std::cmp::Ordering;
 use std::collections::Bound;
 use std::marker::PhantomData;
 use std::ops::RangeBounds;
@@ -118,7 +119,7 @@ impl<'a> RangeIterState<'a> {
         }
     }
 
-    fn get_entry(&self) -> Option<EntryAccessor> {
+    fn get_entry(&self) -> Option<EntryAccessor<'a>> {
         match self {
             Leaf {
                 page,
@@ -126,7 +127,17 @@ impl<'a> RangeIterState<'a> {
                 fixed_value_size,
                 entry,
                 ..
-            } => LeafAccessor::new(page, *fixed_key_size, *fixed_value_size).entry(*entry),
+            } => {
+                let accessor = LeafAccessor::new(page, *fixed_key_size, *fixed_value_size);
+                let (key_start, key_end) = accessor.key_range(*entry)?;
+                let (value_start, value_end) = accessor.value_range(*entry)?;
+                let memory = page.clone().into_memory();
+                Some(EntryAccessor::new(
+                    &memory[key_start..key_end],
+                    &memory[value_start..value_end],
+                    accessor.value_is_out_of_line(*entry),
+                ))
+            }
             _ => None,
         }
     }
@@ -134,6 +145,8 @@ impl<'a> RangeIterState<'a> {
 
 pub(crate) struct AllPageNumbersBtreeIter<'a> {
     next: Option<RangeIterState<'a>>,
+    // Pages of values stored out of line, from the most recently visited leaf
+    pending: Vec<PageNumber>,
     manager: &'a TransactionalMemory,
 }
 
@@ -165,6 +178,7 @@ impl<'a> AllPageNumbersBtreeIter<'a> {
         };
         Self {
             next: Some(start),
+            pending: vec![],
             manager,
         }
     }
@@ -174,6 +188,9 @@ impl<'a> Iterator for AllPageNumbersBtreeIter<'a> {
     type Item = PageNumber;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(page) = self.pending.pop() {
+            return Some(page);
+        }
         loop {
             let state = self.next.take()?;
             let value = state.page_number();
@@ -182,6 +199,16 @@ impl<'a> Iterator for AllPageNumbersBtreeIter<'a> {
                 Leaf { entry, .. } => entry == 0,
                 Internal { child, .. } => child == 0,
             };
+            if let Leaf {
+                ref page,
+                fixed_key_size,
+                fixed_value_size: None,
+                entry: 0,
+                ..
+            } = state
+            {
+                self.pending = LeafAccessor::new(page, fixed_key_size, None).out_of_line_pages();
+            }
             self.next = state.next(false, self.manager);
             if once {
                 return Some(value);
@@ -195,7 +222,6 @@ pub struct BtreeRangeIter<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized +
     right: Option<RangeIterState<'a>>, // Exclusive. The previous element returned
     include_left: bool,               // left is inclusive, instead of exclusive
     include_right: bool,              // right is inclusive, instead of exclusive
-    reversed: bool,
     manager: &'a TransactionalMemory,
     _key_type: PhantomData<K>,
     _value_type: PhantomData<V>,
@@ -206,47 +232,68 @@ impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> BtreeRangeIter<'a
         query_range: T,
         table_root: Option<PageNumber>,
         manager: &'a TransactionalMemory,
+    ) -> Self {
+        let start = match query_range.start_bound() {
+            Bound::Included(k) => Bound::Included(k.borrow().as_bytes()),
+            Bound::Excluded(k) => Bound::Excluded(k.borrow().as_bytes()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let end = match query_range.end_bound() {
+            Bound::Included(k) => Bound::Included(k.borrow().as_bytes()),
+            Bound::Excluded(k) => Bound::Excluded(k.borrow().as_bytes()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        Self::new_serialized(
+            as_slice_bound(&start),
+            as_slice_bound(&end),
+            table_root,
+            manager,
+        )
+    }
+
+    // Same as new(), but with the bounds of the range given as serialized keys
+    pub(crate) fn new_serialized(
+        start: Bound<&[u8]>,
+        end: Bound<&[u8]>,
+        table_root: Option<PageNumber>,
+        manager: &'a TransactionalMemory,
     ) -> Self {
         if let Some(root) = table_root {
-            let (include_left, left) = match query_range.start_bound() {
-                Bound::Included(k) => find_iter_left::<K, V>(
-                    manager.get_page(root),
-                    None,
-                    k.borrow().as_bytes().as_ref(),
-                    true,
-                    manager,
-                ),
-                Bound::Excluded(k) => find_iter_left::<K, V>(
-                    manager.get_page(root),
-                    None,
-                    k.borrow().as_bytes().as_ref(),
-                    false,
-                    manager,
-                ),
+            let (include_left, left) = match start {
+                Bound::Included(k) => {
+                    find_iter_left::<K, V>(manager.get_page(root), None, k, true, manager)
+                }
+                Bound::Excluded(k) => {
+                    find_iter_left::<K, V>(manager.get_page(root), None, k, false, manager)
+                }
                 Bound::Unbounded => {
-                    let state =
-                        find_iter_unbounded::<K, V>(manager.get_page(root), None, false, manager);
+                    let state = find_iter_unbounded(
+                        manager.get_page(root),
+                        None,
+                        false,
+                        K::fixed_width(),
+                        V::fixed_width(),
+                        manager,
+                    );
                     (true, state)
                 }
             };
-            let (include_right, right) = match query_range.end_bound() {
-                Bound::Included(k) => find_iter_right::<K, V>(
-                    manager.get_page(root),
-                    None,
-                    k.borrow().as_bytes().as_ref(),
-                    true,
-                    manager,
-                ),
-                Bound::Excluded(k) => find_iter_right::<K, V>(
-                    manager.get_page(root),
-                    None,
-                    k.borrow().as_bytes().as_ref(),
-                    false,
-                    manager,
-                ),
+            let (include_right, right) = match end {
+                Bound::Included(k) => {
+                    find_iter_right::<K, V>(manager.get_page(root), None, k, true, manager)
+                }
+                Bound::Excluded(k) => {
+                    find_iter_right::<K, V>(manager.get_page(root), None, k, false, manager)
+                }
                 Bound::Unbounded => {
-                    let state =
-                        find_iter_unbounded::<K, V>(manager.get_page(root), None, true, manager);
+                    let state = find_iter_unbounded(
+                        manager.get_page(root),
+                        None,
+                        true,
+                        K::fixed_width(),
+                        V::fixed_width(),
+                        manager,
+                    );
                     (true, state)
                 }
             };
@@ -255,7 +302,6 @@ impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> BtreeRangeIter<'a
                 right,
                 include_left,
                 include_right,
-                reversed: false,
                 manager,
                 _key_type: Default::default(),
                 _value_type: Default::default(),
@@ -266,7 +312,6 @@ impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> BtreeRangeIter<'a
                 right: None,
                 include_left: false,
                 include_right: false,
-                reversed: false,
                 manager,
                 _key_type: Default::default(),
                 _value_type: Default::default(),
@@ -274,21 +319,7 @@ impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> BtreeRangeIter<'a
         }
     }
 
-    pub(crate) fn reverse(self) -> Self {
-        Self {
-            left: self.left,
-            right: self.right,
-            include_left: self.include_left,
-            include_right: self.include_right,
-            reversed: !self.reversed,
-            manager: self.manager,
-            _key_type: Default::default(),
-            _value_type: Default::default(),
-        }
-    }
-
-    // TODO: we need generic-associated-types to implement Iterator
-    pub fn next(&mut self) -> Option<EntryAccessor> {
+    fn next_entry(&mut self, reverse: bool) -> Option<EntryAccessor<'a>> {
         if let (
             Some(Leaf {
                 page: left_page,
@@ -311,9 +342,9 @@ impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> BtreeRangeIter<'a
         }
 
         loop {
-            if !self.reversed {
+            if !reverse {
                 if !self.include_left {
-                    self.left = self.left.take()?.next(self.reversed, self.manager);
+                    self.left = self.left.take()?.next(false, self.manager);
                 }
                 // Return None if the next state is None
                 self.left.as_ref()?;
@@ -340,12 +371,12 @@ impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> BtreeRangeIter<'a
                 }
 
                 self.include_left = false;
-                if self.left.as_ref().unwrap().get_entry().is_some() {
-                    return self.left.as_ref().map(|s| s.get_entry().unwrap());
+                if let Some(entry) = self.left.as_ref().unwrap().get_entry() {
+                    return Some(entry.resolve(self.manager));
                 }
             } else {
                 if !self.include_right {
-                    self.right = self.right.take()?.next(self.reversed, self.manager);
+                    self.right = self.right.take()?.next(true, self.manager);
                 }
                 // Return None if the next state is None
                 self.right.as_ref()?;
@@ -372,35 +403,465 @@ impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> BtreeRangeIter<'a
                 }
 
                 self.include_right = false;
-                if self.right.as_ref().unwrap().get_entry().is_some() {
-                    return self.right.as_ref().map(|s| s.get_entry().unwrap());
+                if let Some(entry) = self.right.as_ref().unwrap().get_entry() {
+                    return Some(entry.resolve(self.manager));
+                }
+            }
+        }
+    }
+}
+
+impl<'a> BtreeRangeIter<'a, [u8], [u8]> {
+    // Iterates over all the entries of a tree whose key and value types are not known, using only
+    // their fixed widths. Keys are never compared, so the [u8] types are only placeholders
+    pub(crate) fn new_untyped(
+        table_root: Option<PageNumber>,
+        fixed_key_size: Option<usize>,
+        fixed_value_size: Option<usize>,
+        manager: &'a TransactionalMemory,
+    ) -> Self {
+        let (left, right) = if let Some(root) = table_root {
+            (
+                find_iter_unbounded(
+                    manager.get_page(root),
+                    None,
+                    false,
+                    fixed_key_size,
+                    fixed_value_size,
+                    manager,
+                ),
+                find_iter_unbounded(
+                    manager.get_page(root),
+                    None,
+                    true,
+                    fixed_key_size,
+                    fixed_value_size,
+                    manager,
+                ),
+            )
+        } else {
+            (None, None)
+        };
+        let include = table_root.is_some();
+        Self {
+            left,
+            right,
+            include_left: include,
+            include_right: include,
+            manager,
+            _key_type: Default::default(),
+            _value_type: Default::default(),
+        }
+    }
+}
+
+impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> Iterator
+    for BtreeRangeIter<'a, K, V>
+{
+    type Item = EntryAccessor<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_entry(false)
+    }
+}
+
+impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> DoubleEndedIterator
+    for BtreeRangeIter<'a, K, V>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.next_entry(true)
+    }
+}
+
+// A subtree which has not been expanded yet, or an entry of a leaf which has
+enum DiffItem<'a> {
+    Page(PageNumber),
+    Entry(PageImpl<'a>, usize),
+}
+
+// Returns true if the entries in the range differ between the two trees. Both trees are walked
+// together, and subtrees which are shared by them are skipped without being read, so the cost is
+// proportional to the number of pages which differ
+pub(crate) fn range_differs<K: RedbKey + ?Sized, V: RedbValue + ?Sized>(
+    start: Bound<&[u8]>,
+    end: Bound<&[u8]>,
+    old_root: Option<PageNumber>,
+    new_root: Option<PageNumber>,
+    mem: &TransactionalMemory,
+) -> bool {
+    // The items still to be compared, with the next one at the end
+    let mut old: Vec<DiffItem> = old_root.map(DiffItem::Page).into_iter().collect();
+    let mut new: Vec<DiffItem> = new_root.map(DiffItem::Page).into_iter().collect();
+    loop {
+        match (old.last(), new.last()) {
+            (None, None) => return false,
+            (Some(DiffItem::Page(x)), Some(DiffItem::Page(y))) if x == y => {
+                old.pop();
+                new.pop();
+            }
+            (Some(DiffItem::Entry(x, i)), Some(DiffItem::Entry(y, j))) => {
+                let x = LeafAccessor::new(x, K::fixed_width(), V::fixed_width())
+                    .entry(*i)
+                    .unwrap();
+                let y = LeafAccessor::new(y, K::fixed_width(), V::fixed_width())
+                    .entry(*j)
+                    .unwrap();
+                if x.key() != y.key() || x.resolve(mem).value() != y.resolve(mem).value() {
+                    return true;
+                }
+                old.pop();
+                new.pop();
+            }
+            (Some(DiffItem::Entry(..)) | None, Some(DiffItem::Entry(..)) | None) => return true,
+            (x, y) => {
+                // Expand the branches first, so that subtrees below them can still be matched
+                // against the other tree without being read
+                let (x_branch, y_branch) = (is_branch(x, mem), is_branch(y, mem));
+                if x_branch || !y_branch {
+                    expand::<K, V>(&mut old, start, end, mem);
+                }
+                if y_branch || !x_branch {
+                    expand::<K, V>(&mut new, start, end, mem);
                 }
             }
         }
     }
 }
 
-fn find_iter_unbounded<'a, K: RedbKey + ?Sized, V: RedbValue + ?Sized>(
+fn is_branch(item: Option<&DiffItem>, mem: &TransactionalMemory) -> bool {
+    match item {
+        Some(DiffItem::Page(page_number)) => mem.get_page(*page_number).memory()[0] == BRANCH,
+        _ => false,
+    }
+}
+
+// Replaces the page at the end of items with its children, or entries, which may be in the range
+fn expand<'a, K: RedbKey + ?Sized, V: RedbValue + ?Sized>(
+    items: &mut Vec<DiffItem<'a>>,
+    start: Bound<&[u8]>,
+    end: Bound<&[u8]>,
+    mem: &'a TransactionalMemory,
+) {
+    let page = match items.last() {
+        Some(DiffItem::Page(page_number)) => mem.get_page(*page_number),
+        _ => return,
+    };
+    items.pop();
+    let above_start = |key: &[u8]| match start {
+        Bound::Included(start) => K::compare(key, start).is_ge(),
+        Bound::Excluded(start) => K::compare(key, start).is_gt(),
+        Bound::Unbounded => true,
+    };
+    let below_end = |key: &[u8]| match end {
+        Bound::Included(end) => K::compare(key, end).is_le(),
+        Bound::Excluded(end) => K::compare(key, end).is_lt(),
+        Bound::Unbounded => true,
+    };
+    match page.memory()[0] {
+        LEAF => {
+            let accessor = LeafAccessor::new(&page, K::fixed_width(), V::fixed_width());
+            for i in (0..accessor.num_pairs()).rev() {
+                let key = accessor.entry(i).unwrap().key();
+                if above_start(key) && below_end(key) {
+                    items.push(DiffItem::Entry(page.clone(), i));
+                }
+            }
+        }
+        BRANCH => {
+            // The keys of the nth child are greater than key n - 1, and no greater than key n
+            let accessor = BranchAccessor::new(&page, K::fixed_width());
+            for i in (0..accessor.count_children()).rev() {
+                let below = accessor.key(i).map_or(false, |key| !above_start(key));
+                let above = match (i.checked_sub(1).and_then(|n| accessor.key(n)), end) {
+                    (Some(key), Bound::Included(end) | Bound::Excluded(end)) => {
+                        K::compare(key, end).is_ge()
+                    }
+                    _ => false,
+                };
+                if !below && !above {
+                    items.push(DiffItem::Page(accessor.child_page(i).unwrap()));
+                }
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+
+pub(crate) struct BtreeCursor<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> {
+    root: Option<PageNumber>,
+    // The pages from the root to the current leaf, with the index of the child (or entry) that
+    // the cursor is on in each. Empty when the cursor is not positioned on an entry
+    path: Vec<(PageImpl<'a>, usize)>,
+    manager: &'a TransactionalMemory,
+    _key_type: PhantomData<K>,
+    _value_type: PhantomData<V>,
+}
+
+impl<'a, K: RedbKey + ?Sized + 'a, V: RedbValue + ?Sized + 'a> BtreeCursor<'a, K, V> {
+    pub(crate) fn new(root: Option<PageNumber>, manager: &'a TransactionalMemory) -> Self {
+        Self {
+            root,
+            path: vec![],
+            manager,
+            _key_type: Default::default(),
+            _value_type: Default::default(),
+        }
+    }
+
+    pub(crate) fn current(&self) -> Option<EntryAccessor<'a>> {
+        let (page, entry) = self.path.last()?;
+        let accessor = LeafAccessor::new(page, K::fixed_width(), V::fixed_width());
+        let (key_start, key_end) = accessor.key_range(*entry)?;
+        let (value_start, value_end) = accessor.value_range(*entry)?;
+        let memory = page.clone().into_memory();
+        Some(
+            EntryAccessor::new(
+                &memory[key_start..key_end],
+                &memory[value_start..value_end],
+                accessor.value_is_out_of_line(*entry),
+            )
+            .resolve(self.manager),
+        )
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.path.clear();
+    }
+
+    // Returns the index of the child taken in each branch from the root, followed by the index of
+    // the current entry in its leaf
+    pub(crate) fn path_indices(&self) -> Vec<usize> {
+        self.path.iter().map(|(_, index)| *index).collect()
+    }
+
+    // Positions the cursor on the entry with the given key, or the first entry after it, if it
+    // was removed. `path` is from path_indices(), before the tree was modified. It's followed
+    // instead of searching for the key, unless the modification changed the structure of the tree.
+    // Returns false if the key was searched for
+    pub(crate) fn seek_modified(&mut self, path: &[usize], key: &[u8], removed: bool) -> bool {
+        if self.follow_path(path, key, removed) {
+            true
+        } else {
+            self.seek(key);
+            false
+        }
+    }
+
+    // Returns false if the path doesn't lead to the expected entry
+    fn follow_path(&mut self, path: &[usize], key: &[u8], removed: bool) -> bool {
+        self.path.clear();
+        let (position, branch_path) = match (self.root, path.split_last()) {
+            (Some(_), Some(x)) => x,
+            _ => return false,
+        };
+        let mut page = self.manager.get_page(self.root.unwrap());
+        // The deepest branch in which the path doesn't take the first child. Its key before that
+        // child is an upper bound of all the entries before the leaf
+        let mut bounding_branch = None;
+        for index in branch_path.iter().copied() {
+            if page.memory()[0] != BRANCH {
+                return false;
+            }
+            let accessor = BranchAccessor::new(&page, K::fixed_width());
+            let child = match accessor.child_page(index) {
+                Some(child) => child,
+                None => return false,
+            };
+            drop(accessor);
+            if index > 0 {
+                bounding_branch = Some(self.path.len());
+            }
+            self.path.push((page, index));
+            page = self.manager.get_page(child);
+        }
+        if page.memory()[0] != LEAF {
+            return false;
+        }
+
+        let position = *position;
+        let accessor = LeafAccessor::new(&page, K::fixed_width(), V::fixed_width());
+        let num_pairs = accessor.num_pairs();
+        if num_pairs == 0 {
+            return false;
+        }
+        let compare_entry = |i: usize| K::compare(accessor.entry(i).unwrap().key(), key);
+        let valid = if !removed {
+            position < num_pairs && compare_entry(position) == Ordering::Equal
+        } else {
+            // The entries before the position must be less than the removed key, and the entry
+            // at it greater
+            let after_previous = if position > 0 {
+                position <= num_pairs && compare_entry(position - 1) == Ordering::Less
+            } else if let Some(i) = bounding_branch {
+                let (branch, index) = &self.path[i];
+                let accessor = BranchAccessor::new(branch, K::fixed_width());
+                K::compare(accessor.key(index - 1).unwrap(), key) == Ordering::Less
+            } else {
+                true
+            };
+            after_previous
+                && (position == num_pairs || compare_entry(position) == Ordering::Greater)
+        };
+        drop(accessor);
+        if !valid {
+            return false;
+        }
+        if position < num_pairs {
+            self.path.push((page, position));
+        } else {
+            // The removed entry was the last in its leaf, so the next one is in the following leaf
+            self.path.push((page, num_pairs - 1));
+            self.step(false);
+        }
+        true
+    }
+
+    pub(crate) fn seek_first(&mut self) -> Option<EntryAccessor<'a>> {
+        self.path.clear();
+        let root = self.manager.get_page(self.root?);
+        self.descend_edge(root, false);
+        self.current()
+    }
+
+    pub(crate) fn seek_last(&mut self) -> Option<EntryAccessor<'a>> {
+        self.path.clear();
+        let root = self.manager.get_page(self.root?);
+        self.descend_edge(root, true);
+        self.current()
+    }
+
+    // Positions the cursor on the first entry with a key greater than or equal to the query
+    pub(crate) fn seek(&mut self, query: &[u8]) -> Option<EntryAccessor<'a>> {
+        self.path.clear();
+        let mut page = self.manager.get_page(self.root?);
+        loop {
+            match page.memory()[0] {
+                LEAF => {
+                    let accessor = LeafAccessor::new(&page, K::fixed_width(), V::fixed_width());
+                    let (position, _) = accessor.position::<K>(query);
+                    let num_pairs = accessor.num_pairs();
+                    drop(accessor);
+                    if position < num_pairs {
+                        self.path.push((page, position));
+                        return self.current();
+                    }
+                    // Every key in this leaf is less than the query, so the entry we want is the
+                    // first one after it
+                    self.path.push((page, num_pairs - 1));
+                    return self.step(false);
+                }
+                BRANCH => {
+                    let accessor = BranchAccessor::new(&page, K::fixed_width());
+                    let (child_index, child_page) = accessor.child_for_key::<K>(query);
+                    drop(accessor);
+                    self.path.push((page, child_index));
+                    page = self.manager.get_page(child_page);
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    // Moves to the next entry. If the cursor is not positioned, moves to the first entry
+    pub(crate) fn next(&mut self) -> Option<EntryAccessor<'a>> {
+        if self.path.is_empty() {
+            self.seek_first()
+        } else {
+            self.step(false)
+        }
+    }
+
+    // Moves to the previous entry. If the cursor is not positioned, moves to the last entry
+    pub(crate) fn prev(&mut self) -> Option<EntryAccessor<'a>> {
+        if self.path.is_empty() {
+            self.seek_last()
+        } else {
+            self.step(true)
+        }
+    }
+
+    // Moves one entry in the given direction, leaving the cursor unpositioned if there is none
+    fn step(&mut self, reverse: bool) -> Option<EntryAccessor<'a>> {
+        while let Some((page, index)) = self.path.pop() {
+            let count = match page.memory()[0] {
+                LEAF => LeafAccessor::new(&page, K::fixed_width(), V::fixed_width()).num_pairs(),
+                BRANCH => BranchAccessor::new(&page, K::fixed_width()).count_children(),
+                _ => unreachable!(),
+            };
+            let next_index = if reverse {
+                index.checked_sub(1)
+            } else {
+                Some(index + 1).filter(|i| *i < count)
+            };
+            if let Some(next_index) = next_index {
+                let is_branch = page.memory()[0] == BRANCH;
+                self.path.push((page, next_index));
+                if is_branch {
+                    let (page, _) = self.path.last().unwrap();
+                    let child = BranchAccessor::new(page, K::fixed_width())
+                        .child_page(next_index)
+                        .unwrap();
+                    let child = self.manager.get_page(child);
+                    self.descend_edge(child, reverse);
+                }
+                return self.current();
+            }
+        }
+        None
+    }
+
+    // Descends from the given page to its first, or last, entry
+    fn descend_edge(&mut self, mut page: PageImpl<'a>, last: bool) {
+        loop {
+            match page.memory()[0] {
+                LEAF => {
+                    let accessor = LeafAccessor::new(&page, K::fixed_width(), V::fixed_width());
+                    let entry = if last { accessor.num_pairs() - 1 } else { 0 };
+                    drop(accessor);
+                    self.path.push((page, entry));
+                    return;
+                }
+                BRANCH => {
+                    let accessor = BranchAccessor::new(&page, K::fixed_width());
+                    let child = if last {
+                        accessor.count_children() - 1
+                    } else {
+                        0
+                    };
+                    let child_page = accessor.child_page(child).unwrap();
+                    drop(accessor);
+                    self.path.push((page, child));
+                    page = self.manager.get_page(child_page);
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+fn find_iter_unbounded<'a>(
     page: PageImpl<'a>,
     mut parent: Option<Box<RangeIterState<'a>>>,
     reverse: bool,
+    fixed_key_size: Option<usize>,
+    fixed_value_size: Option<usize>,
     manager: &'a TransactionalMemory,
 ) -> Option<RangeIterState<'a>> {
     let node_mem = page.memory();
     match node_mem[0] {
         LEAF => {
-            let accessor = LeafAccessor::new(&page, K::fixed_width(), V::fixed_width());
+            let accessor = LeafAccessor::new(&page, fixed_key_size, fixed_value_size);
             let entry = if reverse { accessor.num_pairs() - 1 } else { 0 };
             Some(Leaf {
                 page,
-                fixed_key_size: K::fixed_width(),
-                fixed_value_size: V::fixed_width(),
+                fixed_key_size,
+                fixed_value_size,
                 entry,
                 parent,
             })
         }
         BRANCH => {
-            let accessor = BranchAccessor::new(&page, K::fixed_width());
+            let accessor = BranchAccessor::new(&page, fixed_key_size);
             let child_index = if reverse {
                 accessor.count_children() - 1
             } else {
@@ -411,17 +872,32 @@ fn find_iter_unbounded<'a, K: RedbKey + ?Sized, V: RedbValue + ?Sized>(
             let direction = if reverse { -1isize } else { 1 };
             parent = Some(Box::new(Internal {
                 page,
-                fixed_key_size: K::fixed_width(),
-                fixed_value_size: V::fixed_width(),
+                fixed_key_size,
+                fixed_value_size,
                 child: (child_index as isize + direction) as usize,
                 parent,
             }));
-            find_iter_unbounded::<K, V>(child_page, parent, reverse, manager)
+            find_iter_unbounded(
+                child_page,
+                parent,
+                reverse,
+                fixed_key_size,
+                fixed_value_size,
+                manager,
+            )
         }
         _ => unreachable!(),
     }
 }
 
+fn as_slice_bound<T: AsRef<[u8]>>(bound: &Bound<T>) -> Bound<&[u8]> {
+    match bound {
+        Bound::Included(x) => Bound::Included(x.as_ref()),
+        Bound::Excluded(x) => Bound::Excluded(x.as_ref()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
 // Returns a bool indicating whether the first entry pointed to by the state is included in the
 // queried range
 fn find_iter_left<'a, K: RedbKey + ?Sized, V: RedbValue + ?Sized>(
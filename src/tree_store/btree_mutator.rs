@@ -1,6 +1,7 @@
 use crate::tree_store::btree_base::{
     branch_checksum, leaf_checksum, BranchAccessor, BranchBuilder, BranchMutator, Checksum,
-    FreePolicy, LeafAccessor, LeafBuilder, LeafMutator, BRANCH, LEAF,
+    FreePolicy, LeafAccessor, LeafBuilder, LeafMutator, RawBranchBuilder, ValueReference, BRANCH,
+    LEAF,
 };
 use crate::tree_store::btree_mutator::DeletionResult::{
     DeletedBranch, DeletedLeaf, PartialBranch, PartialLeaf, Subtree,
@@ -9,13 +10,15 @@ use crate::tree_store::page_store::{ChecksumType, Page, PageImpl};
 use crate::tree_store::{AccessGuardMut, PageNumber, TransactionalMemory};
 use crate::types::{RedbKey, RedbValue};
 use crate::{AccessGuard, Result};
-use std::cmp::{max, min};
+use std::cmp::{max, min, Ordering};
 use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Bound, RangeBounds};
 
 #[derive(Debug)]
 enum DeletionResult {
-    // A proper subtree
-    Subtree(PageNumber, Checksum),
+    // A proper subtree, and the number of entries in it
+    Subtree(PageNumber, Checksum, u64),
     // A leaf with zero children
     DeletedLeaf,
     // A leaf with fewer entries than desired
@@ -23,7 +26,26 @@ enum DeletionResult {
     // A branch page subtree with fewer children than desired
     PartialBranch(PageNumber, Checksum),
     // Indicates that the branch node was deleted, and includes the only remaining child
-    DeletedBranch(PageNumber, Checksum),
+    DeletedBranch(PageNumber, Checksum, u64),
+}
+
+// Restricts an insertion, based on the value currently stored under the key
+pub(crate) enum InsertCondition<'c> {
+    Always,
+    // The key must not be present
+    Absent,
+    // The key must be present, and its value must have exactly these bytes
+    Equals(&'c [u8]),
+}
+
+impl<'c> InsertCondition<'c> {
+    fn holds(&self, current_value: Option<&[u8]>) -> bool {
+        match self {
+            InsertCondition::Always => true,
+            InsertCondition::Absent => current_value.is_none(),
+            InsertCondition::Equals(expected) => current_value == Some(*expected),
+        }
+    }
 }
 
 struct InsertionResult<'a, V: RedbValue + ?Sized> {
@@ -31,19 +53,122 @@ struct InsertionResult<'a, V: RedbValue + ?Sized> {
     new_root: PageNumber,
     // checksum of the root page
     root_checksum: Checksum,
+    // number of entries in the subtree of the root page
+    root_len: u64,
     // Following sibling, if the root had to be split
-    additional_sibling: Option<(Vec<u8>, PageNumber, Checksum)>,
+    additional_sibling: Option<(Vec<u8>, PageNumber, Checksum, u64)>,
     // The inserted value for .insert_reserve() to use
     inserted_value: AccessGuardMut<'a>,
     // The previous value, if any
     old_value: Option<AccessGuard<'a, V>>,
 }
 
+// A subtree produced by delete_filter()
+struct FilterNode {
+    page: PageNumber,
+    checksum: Checksum,
+    // Number of entries in this subtree
+    len: u64,
+    // Inclusive upper bound of the keys in this subtree. Only None for the right-most subtree
+    separator: Option<Vec<u8>>,
+    // Leaves have height 0
+    height: usize,
+    // Less than 33% full, and should be merged with a sibling
+    underfull: bool,
+}
+
+enum FilterResult {
+    // No entries were removed from the subtree
+    Unchanged,
+    // The subtree was replaced by these subtrees, which all have the same height. They may be shorter
+    // than the original subtree
+    Changed(Vec<FilterNode>),
+}
+
+fn key_in_range<K: RedbKey + ?Sized>(key: &[u8], range: &(Bound<&[u8]>, Bound<&[u8]>)) -> bool {
+    let after_start = match range.start_bound() {
+        Bound::Included(start) => K::compare(key, start) != Ordering::Less,
+        Bound::Excluded(start) => K::compare(key, start) == Ordering::Greater,
+        Bound::Unbounded => true,
+    };
+    let before_end = match range.end_bound() {
+        Bound::Included(end) => K::compare(key, end) != Ordering::Greater,
+        Bound::Excluded(end) => K::compare(key, end) == Ordering::Less,
+        Bound::Unbounded => true,
+    };
+    after_start && before_end
+}
+
+// Returns true if a subtree whose keys are in (lower, upper] may contain keys in the range
+fn subtree_in_range<K: RedbKey + ?Sized>(
+    lower: Option<&[u8]>,
+    upper: Option<&[u8]>,
+    range: &(Bound<&[u8]>, Bound<&[u8]>),
+) -> bool {
+    if let Some(upper) = upper {
+        match range.start_bound() {
+            Bound::Included(start) if K::compare(upper, start) == Ordering::Less => {
+                return false;
+            }
+            Bound::Excluded(start) if K::compare(upper, start) != Ordering::Greater => {
+                return false;
+            }
+            _ => {}
+        }
+    }
+    if let Some(lower) = lower {
+        match range.end_bound() {
+            Bound::Included(end) | Bound::Excluded(end)
+                if K::compare(lower, end) != Ordering::Less =>
+            {
+                return false;
+            }
+            _ => {}
+        }
+    }
+    true
+}
+
+// Returns true if a subtree whose keys are in (lower, upper] can only contain keys in the range
+fn subtree_within_range<K: RedbKey + ?Sized>(
+    lower: Option<&[u8]>,
+    upper: Option<&[u8]>,
+    range: &(Bound<&[u8]>, Bound<&[u8]>),
+) -> bool {
+    let after_start = match (range.start_bound(), lower) {
+        (Bound::Unbounded, _) => true,
+        (Bound::Included(start) | Bound::Excluded(start), Some(lower)) => {
+            K::compare(lower, start) != Ordering::Less
+        }
+        (_, None) => false,
+    };
+    let before_end = match (range.end_bound(), upper) {
+        (Bound::Unbounded, _) => true,
+        (Bound::Included(end), Some(upper)) => K::compare(upper, end) != Ordering::Greater,
+        (Bound::Excluded(end), Some(upper)) => K::compare(upper, end) == Ordering::Less,
+        (_, None) => false,
+    };
+    after_start && before_end
+}
+
 pub(crate) struct MutateHelper<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> {
     root: &'b mut Option<(PageNumber, Checksum)>,
     free_policy: FreePolicy,
     mem: &'a TransactionalMemory,
     freed: &'b mut Vec<PageNumber>,
+    // Variable width values longer than this are stored out of line, in their own pages
+    max_inline_value_size: Option<usize>,
+    // Whether leaves may reference out of line values, whose pages must be freed with them
+    out_of_line_values: bool,
+    // Child indices from the root to the leaf containing the key, followed by the key's position
+    // in the leaf, in reverse order. Used instead of searching for the key, when it's known
+    path_hint: Vec<usize>,
+    // If set, the first (false) or last (true) entry is deleted, instead of searching for a key
+    edge: Option<bool>,
+    // The key of the entry at the edge, once the leaf containing it has been reached
+    edge_key: Option<Vec<u8>>,
+    // The value currently stored under the key, if a conditional insertion didn't hold
+    rejected_value: Option<AccessGuard<'a, V>>,
     _key_type: PhantomData<K>,
     _value_type: PhantomData<V>,
 }
@@ -60,11 +185,74 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
             free_policy,
             mem,
             freed,
+            max_inline_value_size: None,
+            out_of_line_values: false,
+            path_hint: vec![],
+            edge: None,
+            edge_key: None,
+            rejected_value: None,
             _key_type: Default::default(),
             _value_type: Default::default(),
         }
     }
 
+    pub(crate) fn set_max_inline_value_size(&mut self, size: Option<usize>) {
+        self.max_inline_value_size = size;
+    }
+
+    pub(crate) fn set_out_of_line_values(&mut self, enabled: bool) {
+        self.out_of_line_values = enabled;
+    }
+
+    // Sets the position of the key that will be modified, which must be present in the tree. See
+    // BtreeCursor::path_indices()
+    pub(crate) fn set_path_hint(&mut self, mut path: Vec<usize>) {
+        path.reverse();
+        self.path_hint = path;
+    }
+
+    // Returns the position of the key in the leaf, and whether it's present
+    fn leaf_position<T: Page>(
+        &mut self,
+        accessor: &LeafAccessor<'_, '_, T>,
+        key: &[u8],
+    ) -> (usize, bool) {
+        if let Some(last) = self.edge {
+            if accessor.num_pairs() == 0 {
+                return (0, false);
+            }
+            let position = if last { accessor.num_pairs() - 1 } else { 0 };
+            self.edge_key = Some(accessor.entry(position).unwrap().key().to_vec());
+            (position, true)
+        } else if let Some(position) = self.path_hint.pop() {
+            debug_assert_eq!((position, true), accessor.position::<K>(key));
+            (position, true)
+        } else {
+            accessor.position::<K>(key)
+        }
+    }
+
+    // Returns the index and page number of the child which contains the key
+    fn branch_child<T: Page>(
+        &mut self,
+        accessor: &BranchAccessor<'_, '_, T>,
+        key: &[u8],
+    ) -> (usize, PageNumber) {
+        if let Some(last) = self.edge {
+            let child_index = if last {
+                accessor.count_children() - 1
+            } else {
+                0
+            };
+            (child_index, accessor.child_page(child_index).unwrap())
+        } else if let Some(child_index) = self.path_hint.pop() {
+            debug_assert_eq!(child_index, accessor.child_for_key::<K>(key).0);
+            (child_index, accessor.child_page(child_index).unwrap())
+        } else {
+            accessor.child_for_key::<K>(key)
+        }
+    }
+
     pub(crate) fn safe_delete(&mut self, key: &K) -> Result<Option<AccessGuard<'a, V>>> {
         assert_eq!(self.free_policy, FreePolicy::Never);
         // Safety: we asserted that the free policy is Never
@@ -73,11 +261,32 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
 
     // Safety: caller must ensure that no references to uncommitted pages in this table exist
     pub(crate) unsafe fn delete(&mut self, key: &K) -> Result<Option<AccessGuard<'a, V>>> {
+        self.delete_bytes(key.as_bytes().as_ref())
+    }
+
+    // Removes the first entry, or the last one, descending the edge of the tree once. Returns its
+    // key and value
+    //
+    // Safety: caller must ensure that no references to uncommitted pages in this table exist
+    pub(crate) unsafe fn delete_edge(
+        &mut self,
+        last: bool,
+    ) -> Result<Option<(Vec<u8>, AccessGuard<'a, V>)>> {
+        self.edge = Some(last);
+        // The key is not searched for, so any key will do
+        let result = self.delete_bytes(&[]);
+        self.edge = None;
+        let key = self.edge_key.take();
+        Ok(result?.map(|value| (key.unwrap(), value)))
+    }
+
+    // Safety: caller must ensure that no references to uncommitted pages in this table exist
+    pub(crate) unsafe fn delete_bytes(&mut self, key: &[u8]) -> Result<Option<AccessGuard<'a, V>>> {
         if let Some((p, checksum)) = *self.root {
             let (deletion_result, found) =
-                self.delete_helper(self.mem.get_page(p), checksum, key.as_bytes().as_ref())?;
+                self.delete_helper(self.mem.get_page(p), checksum, key)?;
             let new_root = match deletion_result {
-                Subtree(page, checksum) => Some((page, checksum)),
+                Subtree(page, checksum, _) => Some((page, checksum)),
                 DeletedLeaf => None,
                 PartialLeaf { deleted_pair } => {
                     let page = self.mem.get_page(p);
@@ -93,7 +302,7 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                     Some((page.get_page_number(), self.checksum_helper(&page)))
                 }
                 PartialBranch(page_number, checksum) => Some((page_number, checksum)),
-                DeletedBranch(remaining_child, checksum) => Some((remaining_child, checksum)),
+                DeletedBranch(remaining_child, checksum, _) => Some((remaining_child, checksum)),
             };
             *self.root = new_root;
             Ok(found)
@@ -108,44 +317,134 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
         key: &K,
         value: &V,
     ) -> Result<(Option<AccessGuard<'a, V>>, AccessGuardMut<'a>)> {
+        self.insert_bytes(key.as_bytes().as_ref(), value.as_bytes().as_ref())
+    }
+
+    // Safety: caller must ensure that no references to uncommitted pages in this tree exist
+    pub(crate) unsafe fn insert_bytes(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(Option<AccessGuard<'a, V>>, AccessGuardMut<'a>)> {
+        Ok(self
+            .insert_conditional(key, value, &InsertCondition::Always)?
+            .unwrap())
+    }
+
+    // Inserts the key-value pair, if the key is not present. Returns the value stored under the key
+    // afterwards, and whether it was inserted. The leaf is located once, either way
+    //
+    // Safety: caller must ensure that no references to uncommitted pages in this tree exist
+    pub(crate) unsafe fn get_or_insert(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(AccessGuard<'a, V>, bool)> {
+        if self
+            .insert_conditional(key, value, &InsertCondition::Absent)?
+            .is_some()
+        {
+            Ok((
+                AccessGuard::with_owned_value(value.to_vec(), self.mem),
+                true,
+            ))
+        } else {
+            Ok((self.rejected_value.take().unwrap(), false))
+        }
+    }
+
+    // Inserts the key-value pair, if the condition holds for the value currently stored under the key.
+    // Returns None, without modifying the tree, if it does not
+    // Safety: caller must ensure that no references to uncommitted pages in this tree exist
+    pub(crate) unsafe fn insert_conditional(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+        condition: &InsertCondition,
+    ) -> Result<Option<(Option<AccessGuard<'a, V>>, AccessGuardMut<'a>)>> {
+        let out_of_line = V::fixed_width().is_none()
+            && self
+                .max_inline_value_size
+                .map_or(false, |max| value.len() > max);
+        if !out_of_line {
+            return self.insert_stored(key, value, false, condition);
+        }
+
+        let mut value_page = self.mem.allocate(value.len())?;
+        value_page.memory_mut()[..value.len()].copy_from_slice(value);
+        let reference = ValueReference {
+            page: value_page.get_page_number(),
+            len: value.len(),
+            checksum: self.mem.checksum_type().checksum(value),
+        };
+        if let Some((old_value, _)) =
+            self.insert_stored(key, &reference.to_bytes(), true, condition)?
+        {
+            Ok(Some((
+                old_value,
+                AccessGuardMut::new(value_page, 0, value.len()),
+            )))
+        } else {
+            drop(value_page);
+            // Safety: the page was allocated above, and never referenced from the tree
+            self.mem.free(reference.page)?;
+            Ok(None)
+        }
+    }
+
+    // Inserts the bytes stored in the leaf for the value, which is a ValueReference if out_of_line is true
+    // Safety: caller must ensure that no references to uncommitted pages in this tree exist
+    unsafe fn insert_stored(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+        out_of_line: bool,
+        condition: &InsertCondition,
+    ) -> Result<Option<(Option<AccessGuard<'a, V>>, AccessGuardMut<'a>)>> {
         let (new_root, old_value, guard) = if let Some((p, checksum)) = *self.root {
-            let result = self.insert_helper(
+            let result = if let Some(result) = self.insert_helper(
                 self.mem.get_page(p),
                 checksum,
-                key.as_bytes().as_ref(),
-                value.as_bytes().as_ref(),
-            )?;
-
-            let new_root = if let Some((key, page2, page2_checksum)) = result.additional_sibling {
-                let mut builder = BranchBuilder::new(self.mem, 2, K::fixed_width());
-                builder.push_child(result.new_root, result.root_checksum);
-                builder.push_key(&key);
-                builder.push_child(page2, page2_checksum);
-                let new_page = builder.build()?;
-                (new_page.get_page_number(), self.checksum_helper(&new_page))
+                key,
+                value,
+                out_of_line,
+                condition,
+            )? {
+                result
             } else {
-                (result.new_root, result.root_checksum)
+                return Ok(None);
             };
+
+            let new_root =
+                if let Some((key, page2, page2_checksum, page2_len)) = result.additional_sibling {
+                    let mut builder = BranchBuilder::new(self.mem, 2, K::fixed_width());
+                    builder.push_child(result.new_root, result.root_checksum, result.root_len);
+                    builder.push_key(&key);
+                    builder.push_child(page2, page2_checksum, page2_len);
+                    let new_page = builder.build()?;
+                    (new_page.get_page_number(), self.checksum_helper(&new_page))
+                } else {
+                    (result.new_root, result.root_checksum)
+                };
             (new_root, result.old_value, result.inserted_value)
         } else {
-            let key_bytes = key.as_bytes();
-            let value_bytes = value.as_bytes();
-            let key_bytes = key_bytes.as_ref();
-            let value_bytes = value_bytes.as_ref();
+            if !condition.holds(None) {
+                return Ok(None);
+            }
             let mut builder = LeafBuilder::new(self.mem, 1, K::fixed_width(), V::fixed_width());
-            builder.push(key_bytes, value_bytes);
+            builder.push(key, value, out_of_line);
             let page = builder.build()?;
 
             let accessor = LeafAccessor::new(&page, K::fixed_width(), V::fixed_width());
             let offset = accessor.offset_of_first_value();
             let page_num = page.get_page_number();
             let checksum = self.checksum_helper(&page);
-            let guard = AccessGuardMut::new(page, offset, value_bytes.len());
+            let guard = AccessGuardMut::new(page, offset, value.len());
 
             ((page_num, checksum), None, guard)
         };
         *self.root = Some(new_root);
-        Ok((old_value, guard))
+        Ok(Some((old_value, guard)))
     }
 
     // Safety: caller must ensure that no references to uncommitted pages in this table exist
@@ -155,12 +454,24 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
         page_checksum: Checksum,
         key: &[u8],
         value: &[u8],
-    ) -> Result<InsertionResult<'a, V>> {
+        out_of_line: bool,
+        condition: &InsertCondition,
+    ) -> Result<Option<InsertionResult<'a, V>>> {
         let node_mem = page.memory();
-        Ok(match node_mem[0] {
+        Ok(Some(match node_mem[0] {
             LEAF => {
                 let accessor = LeafAccessor::new(&page, K::fixed_width(), V::fixed_width());
-                let (position, found) = accessor.position::<K>(key);
+                let (position, found) = self.leaf_position(&accessor, key);
+                let current_value = if found {
+                    Some(accessor.entry(position).unwrap().resolve(self.mem).value())
+                } else {
+                    None
+                };
+                if !condition.holds(current_value) {
+                    self.rejected_value = current_value
+                        .map(|value| AccessGuard::with_owned_value(value.to_vec(), self.mem));
+                    return Ok(None);
+                }
 
                 // Fast-path to avoid re-building and splitting pages with a single large value
                 let single_large_value = accessor.num_pairs() == 1
@@ -168,7 +479,7 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                 if !found && single_large_value {
                     let mut builder =
                         LeafBuilder::new(self.mem, 1, K::fixed_width(), V::fixed_width());
-                    builder.push(key, value);
+                    builder.push(key, value, out_of_line);
                     let new_page = builder.build()?;
                     let new_page_number = new_page.get_page_number();
                     let new_page_checksum = self.checksum_helper(&new_page);
@@ -178,30 +489,34 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                     drop(new_page_accessor);
                     let guard = AccessGuardMut::new(new_page, offset, value.len());
                     return if position == 0 {
-                        Ok(InsertionResult {
+                        Ok(Some(InsertionResult {
                             new_root: new_page_number,
                             root_checksum: new_page_checksum,
+                            root_len: 1,
                             additional_sibling: Some((
                                 key.to_vec(),
                                 page.get_page_number(),
                                 page_checksum,
+                                1,
                             )),
                             inserted_value: guard,
                             old_value: None,
-                        })
+                        }))
                     } else {
                         let split_key = accessor.last_entry().key().to_vec();
-                        Ok(InsertionResult {
+                        Ok(Some(InsertionResult {
                             new_root: page.get_page_number(),
                             root_checksum: page_checksum,
+                            root_len: 1,
                             additional_sibling: Some((
                                 split_key,
                                 new_page_number,
                                 new_page_checksum,
+                                1,
                             )),
                             inserted_value: guard,
                             old_value: None,
-                        })
+                        }))
                     };
                 }
 
@@ -218,7 +533,10 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                     )
                 {
                     let page_number = page.get_page_number();
-                    let existing_value = if found {
+                    let existing_value = if found && accessor.value_is_out_of_line(position) {
+                        let reference = accessor.entry(position).unwrap().value();
+                        Some(self.removed_out_of_line_value(reference))
+                    } else if found {
                         let copied_value = accessor.entry(position).unwrap().value().to_vec();
                         Some(AccessGuard::with_owned_value(copied_value, self.mem))
                     } else {
@@ -228,20 +546,22 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                     let mut page_mut = self.mem.get_page_mut(page_number);
                     let mut mutator =
                         LeafMutator::new(&mut page_mut, K::fixed_width(), V::fixed_width());
-                    mutator.insert(position, found, key, value);
+                    mutator.insert(position, found, key, value, out_of_line);
                     let new_page_accessor =
                         LeafAccessor::new(&page_mut, K::fixed_width(), V::fixed_width());
                     let offset = new_page_accessor.offset_of_value(position).unwrap();
                     drop(new_page_accessor);
                     let new_checksum = self.checksum_helper(&page_mut);
+                    let new_len = self.len_helper(&page_mut);
                     let guard = AccessGuardMut::new(page_mut, offset, value.len());
-                    return Ok(InsertionResult {
+                    return Ok(Some(InsertionResult {
                         new_root: page_number,
                         root_checksum: new_checksum,
+                        root_len: new_len,
                         additional_sibling: None,
                         inserted_value: guard,
                         old_value: existing_value,
-                    });
+                    }));
                 }
 
                 let mut builder = LeafBuilder::new(
@@ -252,33 +572,21 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                 );
                 for i in 0..accessor.num_pairs() {
                     if i == position {
-                        builder.push(key, value);
+                        builder.push(key, value, out_of_line);
                     }
                     if !found || i != position {
-                        let entry = accessor.entry(i).unwrap();
-                        builder.push(entry.key(), entry.value());
+                        builder.push_entry(&accessor.entry(i).unwrap());
                     }
                 }
                 if accessor.num_pairs() == position {
-                    builder.push(key, value);
+                    builder.push(key, value, out_of_line);
                 }
                 if !builder.should_split() {
                     let new_page = builder.build()?;
 
                     let page_number = page.get_page_number();
                     let existing_value = if found {
-                        let (start, end) = accessor.value_range(position).unwrap();
-                        let free_on_drop = self.free_policy.free_on_drop(page_number, self.mem);
-                        if !free_on_drop {
-                            self.freed.push(page_number);
-                        }
-                        Some(AccessGuard::new(
-                            page,
-                            start,
-                            end - start,
-                            free_on_drop,
-                            self.mem,
-                        ))
+                        Some(self.removed_value(page, position)?)
                     } else {
                         drop(page);
                         self.free_policy
@@ -288,6 +596,7 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
 
                     let new_page_number = new_page.get_page_number();
                     let new_page_checksum = self.checksum_helper(&new_page);
+                    let new_page_len = self.len_helper(&new_page);
                     let accessor = LeafAccessor::new(&new_page, K::fixed_width(), V::fixed_width());
                     let offset = accessor.offset_of_value(position).unwrap();
                     let guard = AccessGuardMut::new(new_page, offset, value.len());
@@ -295,6 +604,7 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                     InsertionResult {
                         new_root: new_page_number,
                         root_checksum: new_page_checksum,
+                        root_len: new_page_len,
                         additional_sibling: None,
                         inserted_value: guard,
                         old_value: existing_value,
@@ -304,18 +614,7 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                     let split_key = split_key.to_vec();
                     let page_number = page.get_page_number();
                     let existing_value = if found {
-                        let (start, end) = accessor.value_range(position).unwrap();
-                        let free_on_drop = self.free_policy.free_on_drop(page_number, self.mem);
-                        if !free_on_drop {
-                            self.freed.push(page_number);
-                        }
-                        Some(AccessGuard::new(
-                            page,
-                            start,
-                            end - start,
-                            free_on_drop,
-                            self.mem,
-                        ))
+                        Some(self.removed_value(page, position)?)
                     } else {
                         drop(page);
                         self.free_policy
@@ -325,8 +624,10 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
 
                     let new_page_number = new_page1.get_page_number();
                     let new_page_checksum = self.checksum_helper(&new_page1);
+                    let new_page_len = self.len_helper(&new_page1);
                     let new_page_number2 = new_page2.get_page_number();
                     let new_page2_checksum = self.checksum_helper(&new_page2);
+                    let new_page2_len = self.len_helper(&new_page2);
                     let accessor =
                         LeafAccessor::new(&new_page1, K::fixed_width(), V::fixed_width());
                     let division = accessor.num_pairs();
@@ -345,7 +646,13 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                     InsertionResult {
                         new_root: new_page_number,
                         root_checksum: new_page_checksum,
-                        additional_sibling: Some((split_key, new_page_number2, new_page2_checksum)),
+                        root_len: new_page_len,
+                        additional_sibling: Some((
+                            split_key,
+                            new_page_number2,
+                            new_page2_checksum,
+                            new_page2_len,
+                        )),
                         inserted_value: guard,
                         old_value: existing_value,
                     }
@@ -353,25 +660,38 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
             }
             BRANCH => {
                 let accessor = BranchAccessor::new(&page, K::fixed_width());
-                let (child_index, child_page) = accessor.child_for_key::<K>(key);
+                let (child_index, child_page) = self.branch_child(&accessor, key);
                 let child_checksum = accessor.child_checksum(child_index).unwrap();
-                let sub_result =
-                    self.insert_helper(self.mem.get_page(child_page), child_checksum, key, value)?;
+                let child_len = accessor.child_len(child_index).unwrap();
+                let sub_result = if let Some(result) = self.insert_helper(
+                    self.mem.get_page(child_page),
+                    child_checksum,
+                    key,
+                    value,
+                    out_of_line,
+                    condition,
+                )? {
+                    result
+                } else {
+                    return Ok(None);
+                };
 
                 if sub_result.additional_sibling.is_none() {
                     // Check fast-path if no children were added / changed. Generally, this can only happen
-                    // when checksums are disabled
+                    // when checksums are disabled and an existing value was overwritten
                     if sub_result.new_root == child_page
                         && sub_result.root_checksum == child_checksum
+                        && sub_result.root_len == child_len
                     {
                         // NO-OP. One of our descendants is uncommitted, so there was no change
-                        return Ok(InsertionResult {
+                        return Ok(Some(InsertionResult {
                             new_root: page.get_page_number(),
                             root_checksum: self.checksum_helper(&page),
+                            root_len: accessor.subtree_len(),
                             additional_sibling: None,
                             inserted_value: sub_result.inserted_value,
                             old_value: sub_result.old_value,
-                        });
+                        }));
                     } else if self.mem.uncommitted(page.get_page_number()) {
                         let page_number = page.get_page_number();
                         drop(page);
@@ -383,14 +703,16 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                             child_index,
                             sub_result.new_root,
                             sub_result.root_checksum,
+                            sub_result.root_len,
                         );
-                        return Ok(InsertionResult {
+                        return Ok(Some(InsertionResult {
                             new_root: mutpage.get_page_number(),
                             root_checksum: self.checksum_helper(&mutpage),
+                            root_len: self.len_helper(&mutpage),
                             additional_sibling: None,
                             inserted_value: sub_result.inserted_value,
                             old_value: sub_result.old_value,
-                        });
+                        }));
                     }
                 }
 
@@ -398,34 +720,44 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                 let mut builder =
                     BranchBuilder::new(self.mem, accessor.count_children() + 1, K::fixed_width());
                 if child_index == 0 {
-                    builder.push_child(sub_result.new_root, sub_result.root_checksum);
-                    if let Some((ref index_key2, page2, page2_checksum)) =
+                    builder.push_child(
+                        sub_result.new_root,
+                        sub_result.root_checksum,
+                        sub_result.root_len,
+                    );
+                    if let Some((ref index_key2, page2, page2_checksum, page2_len)) =
                         sub_result.additional_sibling
                     {
                         builder.push_key(index_key2);
-                        builder.push_child(page2, page2_checksum);
+                        builder.push_child(page2, page2_checksum, page2_len);
                     }
                 } else {
                     builder.push_child(
                         accessor.child_page(0).unwrap(),
                         accessor.child_checksum(0).unwrap(),
+                        accessor.child_len(0).unwrap(),
                     );
                 }
                 for i in 1..accessor.count_children() {
                     if let Some(key) = accessor.key(i - 1) {
                         builder.push_key(key);
                         if i == child_index {
-                            builder.push_child(sub_result.new_root, sub_result.root_checksum);
-                            if let Some((ref index_key2, page2, page2_checksum)) =
+                            builder.push_child(
+                                sub_result.new_root,
+                                sub_result.root_checksum,
+                                sub_result.root_len,
+                            );
+                            if let Some((ref index_key2, page2, page2_checksum, page2_len)) =
                                 sub_result.additional_sibling
                             {
                                 builder.push_key(index_key2);
-                                builder.push_child(page2, page2_checksum);
+                                builder.push_child(page2, page2_checksum, page2_len);
                             }
                         } else {
                             builder.push_child(
                                 accessor.child_page(i).unwrap(),
                                 accessor.child_checksum(i).unwrap(),
+                                accessor.child_len(i).unwrap(),
                             );
                         }
                     } else {
@@ -438,10 +770,12 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                     InsertionResult {
                         new_root: new_page1.get_page_number(),
                         root_checksum: self.checksum_helper(&new_page1),
+                        root_len: self.len_helper(&new_page1),
                         additional_sibling: Some((
                             split_key.to_vec(),
                             new_page2.get_page_number(),
                             self.checksum_helper(&new_page2),
+                            self.len_helper(&new_page2),
                         )),
                         inserted_value: sub_result.inserted_value,
                         old_value: sub_result.old_value,
@@ -451,6 +785,7 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                     InsertionResult {
                         new_root: new_page.get_page_number(),
                         root_checksum: self.checksum_helper(&new_page),
+                        root_len: self.len_helper(&new_page),
                         additional_sibling: None,
                         inserted_value: sub_result.inserted_value,
                         old_value: sub_result.old_value,
@@ -467,7 +802,56 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                 result
             }
             _ => unreachable!(),
-        })
+        }))
+    }
+
+    // Returns the value at the given position of a leaf, which has been replaced in the tree.
+    // The leaf is kept alive by the returned guard, or freed if the value is stored out of line
+    // Safety: caller must ensure that no references to uncommitted pages in this table exist
+    unsafe fn removed_value(
+        &mut self,
+        page: PageImpl<'a>,
+        position: usize,
+    ) -> Result<AccessGuard<'a, V>> {
+        let page_number = page.get_page_number();
+        let accessor = LeafAccessor::new(&page, K::fixed_width(), V::fixed_width());
+        if accessor.value_is_out_of_line(position) {
+            let guard = self.removed_out_of_line_value(accessor.entry(position).unwrap().value());
+            drop(page);
+            self.free_policy
+                .conditional_free(page_number, self.freed, self.mem)?;
+            return Ok(guard);
+        }
+        let (start, end) = accessor.value_range(position).unwrap();
+        let free_on_drop = self.free_policy.free_on_drop(page_number, self.mem);
+        if !free_on_drop {
+            self.freed.push(page_number);
+        }
+        Ok(AccessGuard::new(
+            page,
+            start,
+            end - start,
+            free_on_drop,
+            self.mem,
+        ))
+    }
+
+    // Returns the value referenced by a ValueReference, which has been removed from the tree.
+    // Its pages are freed when the guard is dropped, if they are uncommitted
+    // Safety: caller must ensure that no references to uncommitted pages in this table exist
+    unsafe fn removed_out_of_line_value(&mut self, reference: &[u8]) -> AccessGuard<'a, V> {
+        let reference = ValueReference::from_bytes(reference);
+        let free_on_drop = self.free_policy.free_on_drop(reference.page, self.mem);
+        if !free_on_drop {
+            self.freed.push(reference.page);
+        }
+        AccessGuard::new(
+            self.mem.get_page(reference.page),
+            0,
+            reference.len,
+            free_on_drop,
+            self.mem,
+        )
     }
 
     // Safety: caller must ensure that no references to uncommitted pages in this table exist
@@ -478,22 +862,41 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
         key: &[u8],
     ) -> Result<(DeletionResult, Option<AccessGuard<'a, V>>)> {
         let accessor = LeafAccessor::new(&page, K::fixed_width(), V::fixed_width());
-        let (position, found) = accessor.position::<K>(key);
+        let (position, found) = self.leaf_position(&accessor, key);
         if !found {
-            return Ok((Subtree(page.get_page_number(), checksum), None));
+            let len = accessor.num_pairs() as u64;
+            return Ok((Subtree(page.get_page_number(), checksum, len), None));
         }
         let new_kv_bytes = accessor.length_of_pairs(0, accessor.num_pairs())
             - accessor.length_of_pairs(position, position + 1);
         let new_required_bytes =
             LeafBuilder::required_bytes(accessor.num_pairs() - 1, new_kv_bytes);
         let uncommitted = self.mem.uncommitted(page.get_page_number());
+        let out_of_line = accessor.value_is_out_of_line(position);
 
         // Fast-path for dirty pages
         if uncommitted
             && new_required_bytes >= self.mem.get_page_size() / 2
             && accessor.num_pairs() > 1
         {
+            if out_of_line {
+                let guard =
+                    self.removed_out_of_line_value(accessor.entry(position).unwrap().value());
+                let len = accessor.num_pairs() as u64 - 1;
+                let page_number = page.get_page_number();
+                drop(page);
+                // Safety: caller guaranteed that no other references to uncommitted data exist,
+                // and we just dropped the reference to page
+                let mut page_mut = self.mem.get_page_mut(page_number);
+                // The guard doesn't reference the leaf, so the entry can be removed immediately
+                let mut mutator =
+                    LeafMutator::new(&mut page_mut, K::fixed_width(), V::fixed_width());
+                mutator.remove(position);
+                let checksum = self.checksum_helper(&page_mut);
+                return Ok((Subtree(page_number, checksum, len), Some(guard)));
+            }
             let (start, end) = accessor.value_range(position).unwrap();
+            let len = accessor.num_pairs() as u64 - 1;
             let page_number = page.get_page_number();
             drop(page);
             // Safety: caller guaranteed that no other references to uncommitted data exist,
@@ -517,7 +920,7 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                 K::fixed_width(),
                 self.mem,
             );
-            return Ok((Subtree(page_number, checksum), Some(guard)));
+            return Ok((Subtree(page_number, checksum, len), Some(guard)));
         }
 
         let result = if accessor.num_pairs() == 1 {
@@ -535,16 +938,27 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                 K::fixed_width(),
                 V::fixed_width(),
             );
-            for i in 0..accessor.num_pairs() {
-                if i == position {
-                    continue;
-                }
-                let entry = accessor.entry(i).unwrap();
-                builder.push(entry.key(), entry.value());
-            }
+            builder.push_all_except(&accessor, Some(position));
             let new_page = builder.build()?;
-            Subtree(new_page.get_page_number(), self.checksum_helper(&new_page))
+            Subtree(
+                new_page.get_page_number(),
+                self.checksum_helper(&new_page),
+                self.len_helper(&new_page),
+            )
         };
+        if out_of_line {
+            let guard = self.removed_out_of_line_value(accessor.entry(position).unwrap().value());
+            let page_number = page.get_page_number();
+            drop(page);
+            if matches!(result, PartialLeaf { .. }) {
+                // The caller still reads the remaining entries from this page
+                self.freed.push(page_number);
+            } else {
+                self.free_policy
+                    .conditional_free(page_number, self.freed, self.mem)?;
+            }
+            return Ok((result, Some(guard)));
+        }
         let free_on_drop = if !uncommitted || matches!(self.free_policy, FreePolicy::Never) {
             // Won't be freed until the end of the transaction, so returning the page
             // in the AccessGuard below is still safe
@@ -565,8 +979,8 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
     }
 
     fn finalize_branch_builder(&self, builder: BranchBuilder<'_, '_>) -> Result<DeletionResult> {
-        let result = if let Some((only_child, checksum)) = builder.to_single_child() {
-            DeletedBranch(only_child, checksum)
+        let result = if let Some((only_child, checksum, len)) = builder.to_single_child() {
+            DeletedBranch(only_child, checksum, len)
         } else {
             // TODO: can we optimize away this page allocation?
             // The PartialInternal gets returned, and then the caller has to merge it immediately
@@ -574,15 +988,26 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
             let accessor = BranchAccessor::new(&new_page, K::fixed_width());
             // Merge when less than 33% full. Splits occur when a page is full and produce two 50%
             // full pages, so we use 33% instead of 50% to avoid oscillating
+            let page_number = new_page.get_page_number();
+            let checksum = self.checksum_helper(&new_page);
             if accessor.total_length() < self.mem.get_page_size() / 3 {
-                PartialBranch(new_page.get_page_number(), self.checksum_helper(&new_page))
+                PartialBranch(page_number, checksum)
             } else {
-                Subtree(new_page.get_page_number(), self.checksum_helper(&new_page))
+                Subtree(page_number, checksum, accessor.subtree_len())
             }
         };
         Ok(result)
     }
 
+    // Returns the number of entries in the subtree rooted at the given page
+    fn len_helper<T: Page>(&self, page: &T) -> u64 {
+        match page.memory()[0] {
+            LEAF => LeafAccessor::new(page, K::fixed_width(), V::fixed_width()).num_pairs() as u64,
+            BRANCH => BranchAccessor::new(page, K::fixed_width()).subtree_len(),
+            _ => unreachable!(),
+        }
+    }
+
     fn checksum_helper<T: Page>(&self, page: &T) -> Checksum {
         if self.mem.checksum_type() == ChecksumType::Zero {
             return 0;
@@ -608,40 +1033,52 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
     ) -> Result<(DeletionResult, Option<AccessGuard<'a, V>>)> {
         let accessor = BranchAccessor::new(&page, K::fixed_width());
         let original_page_number = page.get_page_number();
-        let (child_index, child_page_number) = accessor.child_for_key::<K>(key);
+        let (child_index, child_page_number) = self.branch_child(&accessor, key);
         let child_checksum = accessor.child_checksum(child_index).unwrap();
         let (result, found) =
             self.delete_helper(self.mem.get_page(child_page_number), child_checksum, key)?;
         if found.is_none() {
-            return Ok((Subtree(original_page_number, checksum), None));
+            let len = accessor.subtree_len();
+            return Ok((Subtree(original_page_number, checksum, len), None));
         }
-        if let Subtree(new_child, new_child_checksum) = result {
-            let (result_page, result_checksum) = if self.mem.uncommitted(original_page_number) {
+        if let Subtree(new_child, new_child_checksum, new_child_len) = result {
+            let (result_page, result_checksum, result_len) = if self
+                .mem
+                .uncommitted(original_page_number)
+            {
                 drop(page);
                 // Safety: Caller guarantees there are no references to uncommitted pages,
                 // and we just dropped our reference to it on the line above
                 let mut mutpage = self.mem.get_page_mut(original_page_number);
                 let mut mutator = BranchMutator::new(&mut mutpage);
-                mutator.write_child_page(child_index, new_child, new_child_checksum);
-                (original_page_number, self.checksum_helper(&mutpage))
+                mutator.write_child_page(child_index, new_child, new_child_checksum, new_child_len);
+                (
+                    original_page_number,
+                    self.checksum_helper(&mutpage),
+                    self.len_helper(&mutpage),
+                )
             } else {
                 let mut builder =
                     BranchBuilder::new(self.mem, accessor.count_children(), K::fixed_width());
                 builder.push_all(&accessor);
-                builder.replace_child(child_index, new_child, new_child_checksum);
+                builder.replace_child(child_index, new_child, new_child_checksum, new_child_len);
                 let new_page = builder.build()?;
                 self.free_policy
                     .conditional_free(original_page_number, self.freed, self.mem)?;
-                (new_page.get_page_number(), self.checksum_helper(&new_page))
+                (
+                    new_page.get_page_number(),
+                    self.checksum_helper(&new_page),
+                    self.len_helper(&new_page),
+                )
             };
-            return Ok((Subtree(result_page, result_checksum), found));
+            return Ok((Subtree(result_page, result_checksum, result_len), found));
         }
 
         // Child is requesting to be merged with a sibling
         let mut builder = BranchBuilder::new(self.mem, accessor.count_children(), K::fixed_width());
 
         let final_result = match result {
-            Subtree(_, _) => {
+            Subtree(..) => {
                 // Handled in the if above
                 unreachable!();
             }
@@ -653,6 +1090,7 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                     builder.push_child(
                         accessor.child_page(i).unwrap(),
                         accessor.child_checksum(i).unwrap(),
+                        accessor.child_len(i).unwrap(),
                     );
                 }
                 let end = if child_index == accessor.count_children() - 1 {
@@ -698,6 +1136,7 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                         child_index,
                         new_page.get_page_number(),
                         self.checksum_helper(&new_page),
+                        self.len_helper(&new_page),
                     );
 
                     let result = self.finalize_branch_builder(builder)?;
@@ -720,6 +1159,7 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                     }
                     let page_number = accessor.child_page(i).unwrap();
                     let page_checksum = accessor.child_checksum(i).unwrap();
+                    let page_len = accessor.child_len(i).unwrap();
                     if i == merge_with {
                         let mut child_builder = LeafBuilder::new(
                             self.mem,
@@ -743,16 +1183,19 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                             builder.push_child(
                                 new_page1.get_page_number(),
                                 self.checksum_helper(&new_page1),
+                                self.len_helper(&new_page1),
                             );
                             builder.push_child(
                                 new_page2.get_page_number(),
                                 self.checksum_helper(&new_page2),
+                                self.len_helper(&new_page2),
                             );
                         } else {
                             let new_page = child_builder.build()?;
                             builder.push_child(
                                 new_page.get_page_number(),
                                 self.checksum_helper(&new_page),
+                                self.len_helper(&new_page),
                             );
                         }
 
@@ -761,7 +1204,7 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                             builder.push_key(accessor.key(merged_key_index).unwrap());
                         }
                     } else {
-                        builder.push_child(page_number, page_checksum);
+                        builder.push_child(page_number, page_checksum, page_len);
                         if i < accessor.count_children() - 1 {
                             builder.push_key(accessor.key(i).unwrap());
                         }
@@ -779,7 +1222,7 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
 
                 result
             }
-            DeletedBranch(only_grandchild, grandchild_checksum) => {
+            DeletedBranch(only_grandchild, grandchild_checksum, grandchild_len) => {
                 let merge_with = if child_index == 0 { 1 } else { child_index - 1 };
                 let merge_with_page = self.mem.get_page(accessor.child_page(merge_with).unwrap());
                 let merge_with_accessor = BranchAccessor::new(&merge_with_page, K::fixed_width());
@@ -790,6 +1233,7 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                     }
                     let page_number = accessor.child_page(i).unwrap();
                     let page_checksum = accessor.child_checksum(i).unwrap();
+                    let page_len = accessor.child_len(i).unwrap();
                     if i == merge_with {
                         let mut child_builder = BranchBuilder::new(
                             self.mem,
@@ -798,30 +1242,41 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                         );
                         let separator_key = accessor.key(min(child_index, merge_with)).unwrap();
                         if child_index < merge_with {
-                            child_builder.push_child(only_grandchild, grandchild_checksum);
+                            child_builder.push_child(
+                                only_grandchild,
+                                grandchild_checksum,
+                                grandchild_len,
+                            );
                             child_builder.push_key(separator_key);
                         }
                         child_builder.push_all(&merge_with_accessor);
                         if child_index > merge_with {
                             child_builder.push_key(separator_key);
-                            child_builder.push_child(only_grandchild, grandchild_checksum);
+                            child_builder.push_child(
+                                only_grandchild,
+                                grandchild_checksum,
+                                grandchild_len,
+                            );
                         }
                         if child_builder.should_split() {
                             let (new_page1, separator, new_page2) = child_builder.build_split()?;
                             builder.push_child(
                                 new_page1.get_page_number(),
                                 self.checksum_helper(&new_page1),
+                                self.len_helper(&new_page1),
                             );
                             builder.push_key(separator);
                             builder.push_child(
                                 new_page2.get_page_number(),
                                 self.checksum_helper(&new_page2),
+                                self.len_helper(&new_page2),
                             );
                         } else {
                             let new_page = child_builder.build()?;
                             builder.push_child(
                                 new_page.get_page_number(),
                                 self.checksum_helper(&new_page),
+                                self.len_helper(&new_page),
                             );
                         }
 
@@ -830,7 +1285,7 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                             builder.push_key(accessor.key(merged_key_index).unwrap());
                         }
                     } else {
-                        builder.push_child(page_number, page_checksum);
+                        builder.push_child(page_number, page_checksum, page_len);
                         if i < accessor.count_children() - 1 {
                             builder.push_key(accessor.key(i).unwrap());
                         }
@@ -859,6 +1314,7 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                     }
                     let page_number = accessor.child_page(i).unwrap();
                     let page_checksum = accessor.child_checksum(i).unwrap();
+                    let page_len = accessor.child_len(i).unwrap();
                     if i == merge_with {
                         let mut child_builder = BranchBuilder::new(
                             self.mem,
@@ -881,17 +1337,20 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                             builder.push_child(
                                 new_page1.get_page_number(),
                                 self.checksum_helper(&new_page1),
+                                self.len_helper(&new_page1),
                             );
                             builder.push_key(separator);
                             builder.push_child(
                                 new_page2.get_page_number(),
                                 self.checksum_helper(&new_page2),
+                                self.len_helper(&new_page2),
                             );
                         } else {
                             let new_page = child_builder.build()?;
                             builder.push_child(
                                 new_page.get_page_number(),
                                 self.checksum_helper(&new_page),
+                                self.len_helper(&new_page),
                             );
                         }
 
@@ -900,7 +1359,7 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
                             builder.push_key(accessor.key(merged_key_index).unwrap());
                         }
                     } else {
-                        builder.push_child(page_number, page_checksum);
+                        builder.push_child(page_number, page_checksum, page_len);
                         if i < accessor.count_children() - 1 {
                             builder.push_key(accessor.key(i).unwrap());
                         }
@@ -943,4 +1402,471 @@ impl<'a, 'b, K: RedbKey + ?Sized, V: RedbValue + ?Sized> MutateHelper<'a, 'b, K,
             _ => unreachable!(),
         }
     }
+
+    // Removes all entries in the range for which predicate returns true. The tree is traversed
+    // once, and only subtrees which overlap the range are visited
+    //
+    // Safety: caller must ensure that no references to uncommitted pages in this table exist
+    pub(crate) unsafe fn delete_filter<F: FnMut(&[u8], &[u8]) -> bool>(
+        &mut self,
+        range: &(Bound<&[u8]>, Bound<&[u8]>),
+        predicate: &mut F,
+    ) -> Result {
+        self.filter(range, &mut Some(predicate))
+    }
+
+    // Removes all entries in the range. Subtrees which are entirely within the range are freed
+    // without visiting their leaves
+    //
+    // Safety: caller must ensure that no references to uncommitted pages in this table exist
+    pub(crate) unsafe fn delete_range(&mut self, range: &(Bound<&[u8]>, Bound<&[u8]>)) -> Result {
+        self.filter::<fn(&[u8], &[u8]) -> bool>(range, &mut None)
+    }
+
+    // A predicate of None removes every entry in the range
+    //
+    // Safety: caller must ensure that no references to uncommitted pages in this table exist
+    unsafe fn filter<F: FnMut(&[u8], &[u8]) -> bool>(
+        &mut self,
+        range: &(Bound<&[u8]>, Bound<&[u8]>),
+        predicate: &mut Option<&mut F>,
+    ) -> Result {
+        if let Some((p, _)) = *self.root {
+            let height = self.tree_height(p);
+            if predicate.is_none() && subtree_within_range::<K>(None, None, range) {
+                self.free_subtree(p, height)?;
+                *self.root = None;
+                return Ok(());
+            }
+            let mut nodes = match self.filter_helper(p, None, height, range, predicate)? {
+                FilterResult::Unchanged => {
+                    return Ok(());
+                }
+                FilterResult::Changed(nodes) => nodes,
+            };
+            while nodes.len() > 1 {
+                nodes = self.build_branches(nodes)?;
+            }
+            *self.root = nodes.pop().map(|node| (node.page, node.checksum));
+        }
+        Ok(())
+    }
+
+    // Frees every page in the subtree. Leaves are not read, since their height is known, unless
+    // they may reference out of line values
+    //
+    // Safety: caller must ensure that no references to uncommitted pages in this table exist
+    unsafe fn free_subtree(&mut self, page_number: PageNumber, height: usize) -> Result {
+        if height > 0 {
+            let page = self.mem.get_page(page_number);
+            let accessor = BranchAccessor::new(&page, K::fixed_width());
+            let children: Vec<PageNumber> = (0..accessor.count_children())
+                .map(|i| accessor.child_page(i).unwrap())
+                .collect();
+            drop(accessor);
+            drop(page);
+            for child in children {
+                self.free_subtree(child, height - 1)?;
+            }
+        } else if V::fixed_width().is_none() && self.out_of_line_values {
+            let page = self.mem.get_page(page_number);
+            let accessor = LeafAccessor::new(&page, K::fixed_width(), V::fixed_width());
+            let value_pages = accessor.out_of_line_pages();
+            drop(accessor);
+            drop(page);
+            for value_page in value_pages {
+                self.free_policy
+                    .conditional_free(value_page, self.freed, self.mem)?;
+            }
+        }
+        self.free_policy
+            .conditional_free(page_number, self.freed, self.mem)
+    }
+
+    fn tree_height(&self, root: PageNumber) -> usize {
+        let mut height = 0;
+        let mut page = self.mem.get_page(root);
+        while page.memory()[0] == BRANCH {
+            let child = BranchAccessor::new(&page, K::fixed_width())
+                .child_page(0)
+                .unwrap();
+            page = self.mem.get_page(child);
+            height += 1;
+        }
+        height
+    }
+
+    // Safety: caller must ensure that no references to uncommitted pages in this table exist
+    unsafe fn filter_helper<F: FnMut(&[u8], &[u8]) -> bool>(
+        &mut self,
+        page_number: PageNumber,
+        upper: Option<&[u8]>,
+        height: usize,
+        range: &(Bound<&[u8]>, Bound<&[u8]>),
+        predicate: &mut Option<&mut F>,
+    ) -> Result<FilterResult> {
+        if height == 0 {
+            self.filter_leaf(page_number, upper, range, predicate)
+        } else {
+            self.filter_branch(page_number, upper, height, range, predicate)
+        }
+    }
+
+    // Safety: caller must ensure that no references to uncommitted pages in this table exist
+    unsafe fn filter_leaf<F: FnMut(&[u8], &[u8]) -> bool>(
+        &mut self,
+        page_number: PageNumber,
+        upper: Option<&[u8]>,
+        range: &(Bound<&[u8]>, Bound<&[u8]>),
+        predicate: &mut Option<&mut F>,
+    ) -> Result<FilterResult> {
+        let page = self.mem.get_page(page_number);
+        let accessor = LeafAccessor::new(&page, K::fixed_width(), V::fixed_width());
+        let mut retained = vec![];
+        let mut removed_value_pages = vec![];
+        for i in 0..accessor.num_pairs() {
+            let entry = accessor.entry(i).unwrap();
+            let remove = key_in_range::<K>(entry.key(), range)
+                && predicate.as_mut().map_or(true, |predicate| {
+                    predicate(entry.key(), entry.resolve(self.mem).value())
+                });
+            if !remove {
+                retained.push(i);
+            } else if accessor.value_is_out_of_line(i) {
+                removed_value_pages.push(ValueReference::from_bytes(entry.value()).page);
+            }
+        }
+        if retained.len() == accessor.num_pairs() {
+            return Ok(FilterResult::Unchanged);
+        }
+
+        let mut result = vec![];
+        if !retained.is_empty() {
+            let mut builder =
+                LeafBuilder::new(self.mem, retained.len(), K::fixed_width(), V::fixed_width());
+            for i in retained {
+                builder.push_entry(&accessor.entry(i).unwrap());
+            }
+            let new_page = builder.build()?;
+            result.push(self.filter_node(&new_page, upper.map(|x| x.to_vec()), 0));
+        }
+        drop(accessor);
+        drop(page);
+        // Safety: caller guarantees there are no references to uncommitted pages,
+        // and we just dropped our reference to it on the line above
+        self.free_policy
+            .conditional_free(page_number, self.freed, self.mem)?;
+        for value_page in removed_value_pages {
+            self.free_policy
+                .conditional_free(value_page, self.freed, self.mem)?;
+        }
+
+        Ok(FilterResult::Changed(result))
+    }
+
+    // Safety: caller must ensure that no references to uncommitted pages in this table exist
+    unsafe fn filter_branch<F: FnMut(&[u8], &[u8]) -> bool>(
+        &mut self,
+        page_number: PageNumber,
+        upper: Option<&[u8]>,
+        height: usize,
+        range: &(Bound<&[u8]>, Bound<&[u8]>),
+        predicate: &mut Option<&mut F>,
+    ) -> Result<FilterResult> {
+        let page = self.mem.get_page(page_number);
+        let accessor = BranchAccessor::new(&page, K::fixed_width());
+        let mut changed = false;
+        let mut nodes = vec![];
+        for i in 0..accessor.count_children() {
+            let child = accessor.child_page(i).unwrap();
+            let lower = if i > 0 { accessor.key(i - 1) } else { None };
+            let child_upper = if i < accessor.count_children() - 1 {
+                accessor.key(i)
+            } else {
+                upper
+            };
+            let result =
+                if predicate.is_none() && subtree_within_range::<K>(lower, child_upper, range) {
+                    self.free_subtree(child, height - 1)?;
+                    FilterResult::Changed(vec![])
+                } else if subtree_in_range::<K>(lower, child_upper, range) {
+                    self.filter_helper(child, child_upper, height - 1, range, predicate)?
+                } else {
+                    FilterResult::Unchanged
+                };
+            match result {
+                FilterResult::Unchanged => {
+                    nodes.push(FilterNode {
+                        page: child,
+                        checksum: accessor.child_checksum(i).unwrap(),
+                        len: accessor.child_len(i).unwrap(),
+                        separator: child_upper.map(|x| x.to_vec()),
+                        height: height - 1,
+                        underfull: false,
+                    });
+                }
+                FilterResult::Changed(mut new_nodes) => {
+                    changed = true;
+                    nodes.append(&mut new_nodes);
+                }
+            }
+        }
+        if !changed {
+            return Ok(FilterResult::Unchanged);
+        }
+        drop(accessor);
+        drop(page);
+        // Safety: caller guarantees there are no references to uncommitted pages,
+        // and we just dropped our reference to it on the line above
+        self.free_policy
+            .conditional_free(page_number, self.freed, self.mem)?;
+
+        let nodes = self.equalize_heights(nodes)?;
+        let mut nodes = self.merge_underfull(nodes)?;
+        if nodes.len() > 1 {
+            nodes = self.build_branches(nodes)?;
+        }
+
+        Ok(FilterResult::Changed(nodes))
+    }
+
+    fn filter_node<T: Page>(
+        &self,
+        page: &T,
+        separator: Option<Vec<u8>>,
+        height: usize,
+    ) -> FilterNode {
+        let length = match page.memory()[0] {
+            LEAF => LeafAccessor::new(page, K::fixed_width(), V::fixed_width()).total_length(),
+            BRANCH => BranchAccessor::new(page, K::fixed_width()).total_length(),
+            _ => unreachable!(),
+        };
+        FilterNode {
+            page: page.get_page_number(),
+            checksum: self.checksum_helper(page),
+            len: self.len_helper(page),
+            separator,
+            height,
+            // Merge when less than 33% full, same as delete()
+            underfull: length < self.mem.get_page_size() / 3,
+        }
+    }
+
+    // Returns the children of the given branch
+    fn branch_children(&self, node: &FilterNode) -> Vec<FilterNode> {
+        let page = self.mem.get_page(node.page);
+        let accessor = BranchAccessor::new(&page, K::fixed_width());
+        let mut result = vec![];
+        for i in 0..accessor.count_children() {
+            let separator = if i < accessor.count_children() - 1 {
+                accessor.key(i).map(|x| x.to_vec())
+            } else {
+                node.separator.clone()
+            };
+            result.push(FilterNode {
+                page: accessor.child_page(i).unwrap(),
+                checksum: accessor.child_checksum(i).unwrap(),
+                len: accessor.child_len(i).unwrap(),
+                separator,
+                height: node.height - 1,
+                underfull: false,
+            });
+        }
+        result
+    }
+
+    // Builds branches over the given nodes, which must all have the same height. As many branches
+    // as necessary are created, and each has at least two children
+    fn build_branches(&self, nodes: Vec<FilterNode>) -> Result<Vec<FilterNode>> {
+        debug_assert!(nodes.len() > 1);
+        let height = nodes[0].height + 1;
+        let mut groups: Vec<Vec<FilterNode>> = vec![];
+        let mut group: Vec<FilterNode> = vec![];
+        let mut key_bytes = 0;
+        for node in nodes {
+            debug_assert_eq!(node.height + 1, height);
+            if let Some(last) = group.last() {
+                let last_key_len = last.separator.as_ref().unwrap().len();
+                let required = RawBranchBuilder::required_bytes(
+                    group.len(),
+                    key_bytes + last_key_len,
+                    K::fixed_width(),
+                );
+                if group.len() > 1 && required > self.mem.get_page_size() {
+                    groups.push(mem::take(&mut group));
+                    key_bytes = 0;
+                } else {
+                    key_bytes += last_key_len;
+                }
+            }
+            group.push(node);
+        }
+        if group.len() == 1 {
+            // Every branch needs at least two children
+            let previous = groups.last_mut().unwrap();
+            if previous.len() > 2 {
+                group.insert(0, previous.pop().unwrap());
+                groups.push(group);
+            } else {
+                previous.append(&mut group);
+            }
+        } else {
+            groups.push(group);
+        }
+
+        let mut result = vec![];
+        for group in groups {
+            let mut builder = BranchBuilder::new(self.mem, group.len(), K::fixed_width());
+            for (i, node) in group.iter().enumerate() {
+                builder.push_child(node.page, node.checksum, node.len);
+                if i < group.len() - 1 {
+                    builder.push_key(node.separator.as_ref().unwrap());
+                }
+            }
+            let page = builder.build()?;
+            let separator = group.last().unwrap().separator.clone();
+            result.push(self.filter_node(&page, separator, height));
+        }
+
+        Ok(result)
+    }
+
+    // Joins shorter nodes into their neighbors, so that all the nodes have the same height
+    //
+    // Safety: caller must ensure that no references to uncommitted pages in this table exist
+    unsafe fn equalize_heights(&mut self, nodes: Vec<FilterNode>) -> Result<Vec<FilterNode>> {
+        let height = if let Some(height) = nodes.iter().map(|x| x.height).max() {
+            height
+        } else {
+            return Ok(nodes);
+        };
+        let mut result: Vec<FilterNode> = vec![];
+        // Shorter nodes which precede the first node of full height
+        let mut pending: Vec<FilterNode> = vec![];
+        for node in nodes {
+            if node.height == height {
+                let mut joined = vec![node];
+                while let Some(short) = pending.pop() {
+                    let mut rest = joined.split_off(1);
+                    joined = self.join(short, joined.pop().unwrap())?;
+                    joined.append(&mut rest);
+                }
+                result.append(&mut joined);
+            } else if let Some(last) = result.pop() {
+                let mut joined = self.join(last, node)?;
+                result.append(&mut joined);
+            } else {
+                pending.push(node);
+            }
+        }
+        debug_assert!(pending.is_empty());
+
+        Ok(result)
+    }
+
+    // Joins two adjacent nodes of different heights. The result has the height of the taller node
+    //
+    // Safety: caller must ensure that no references to uncommitted pages in this table exist
+    unsafe fn join(&mut self, left: FilterNode, right: FilterNode) -> Result<Vec<FilterNode>> {
+        let (tall, short, short_is_left) = if left.height > right.height {
+            (left, right, false)
+        } else {
+            (right, left, true)
+        };
+        debug_assert!(short.height < tall.height);
+        let mut children = self.branch_children(&tall);
+        if short.height + 1 == tall.height {
+            if short_is_left {
+                children.insert(0, short);
+            } else {
+                children.push(short);
+            }
+        } else if short_is_left {
+            let mut rest = children.split_off(1);
+            children = self.join(short, children.pop().unwrap())?;
+            children.append(&mut rest);
+        } else {
+            let last = children.pop().unwrap();
+            children.append(&mut self.join(last, short)?);
+        }
+        let result = self.build_branches(children)?;
+        self.free_policy
+            .conditional_free(tall.page, self.freed, self.mem)?;
+
+        Ok(result)
+    }
+
+    // Merges underfull nodes with their siblings. All the nodes must have the same height
+    //
+    // Safety: caller must ensure that no references to uncommitted pages in this table exist
+    unsafe fn merge_underfull(&mut self, mut nodes: Vec<FilterNode>) -> Result<Vec<FilterNode>> {
+        let mut i = 0;
+        while nodes.len() > 1 && i < nodes.len() {
+            if !nodes[i].underfull {
+                i += 1;
+                continue;
+            }
+            let left = if i == nodes.len() - 1 { i - 1 } else { i };
+            let right_node = nodes.remove(left + 1);
+            let left_node = nodes.remove(left);
+            let merged = self.merge_nodes(left_node, right_node)?;
+            let merged_len = merged.len();
+            nodes.splice(left..left, merged);
+            // If the merge produced a single node, it may still be underfull
+            i = if merged_len == 1 {
+                left
+            } else {
+                left + merged_len
+            };
+        }
+
+        Ok(nodes)
+    }
+
+    // Merges two adjacent nodes of the same height
+    //
+    // Safety: caller must ensure that no references to uncommitted pages in this table exist
+    unsafe fn merge_nodes(
+        &mut self,
+        left: FilterNode,
+        right: FilterNode,
+    ) -> Result<Vec<FilterNode>> {
+        debug_assert_eq!(left.height, right.height);
+        let result = if left.height == 0 {
+            let left_page = self.mem.get_page(left.page);
+            let right_page = self.mem.get_page(right.page);
+            let left_accessor = LeafAccessor::new(&left_page, K::fixed_width(), V::fixed_width());
+            let right_accessor = LeafAccessor::new(&right_page, K::fixed_width(), V::fixed_width());
+            let mut builder = LeafBuilder::new(
+                self.mem,
+                left_accessor.num_pairs() + right_accessor.num_pairs(),
+                K::fixed_width(),
+                V::fixed_width(),
+            );
+            builder.push_all_except(&left_accessor, None);
+            builder.push_all_except(&right_accessor, None);
+            if builder.should_split() {
+                let (new_page1, split_key, new_page2) = builder.build_split()?;
+                vec![
+                    self.filter_node(&new_page1, Some(split_key.to_vec()), 0),
+                    self.filter_node(&new_page2, right.separator.clone(), 0),
+                ]
+            } else {
+                let new_page = builder.build()?;
+                vec![self.filter_node(&new_page, right.separator.clone(), 0)]
+            }
+        } else {
+            let mut children = self.branch_children(&left);
+            children.append(&mut self.branch_children(&right));
+            self.build_branches(children)?
+        };
+        // Safety: caller guarantees there are no references to uncommitted pages,
+        // and the accessors above have been dropped
+        self.free_policy
+            .conditional_free(left.page, self.freed, self.mem)?;
+        self.free_policy
+            .conditional_free(right.page, self.freed, self.mem)?;
+
+        Ok(result)
+    }
 }
@@ -0,0 +1,282 @@
+use crate::tree_store::page_store::TransactionalMemory;
+use crate::tree_store::{BtreeMut, BtreeRangeIter, Checksum, PageNumber};
+use crate::types::RedbValue;
+use crate::Result;
+use std::cell::RefCell;
+use std::mem::size_of;
+use std::ops::Bound;
+use std::rc::Rc;
+
+// The changelog of a table is a tree mapping (transaction id, sequence number) to a change, where
+// the sequence number orders the changes within a transaction. Changes are serialized as:
+// 1 byte: kind
+// 4 bytes: key length
+// n bytes: key
+// followed by the old value, and then the new value, each of which is:
+// 1 byte: non-null
+// 4 bytes: length (only if non-null)
+// n bytes: value (only if non-null)
+pub(crate) type ChangelogKey = (u64, u64);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum ChangelogMode {
+    Disabled,
+    Keys,
+    KeysAndValues,
+}
+
+#[allow(clippy::from_over_into)]
+impl Into<u8> for ChangelogMode {
+    fn into(self) -> u8 {
+        match self {
+            ChangelogMode::Disabled => 0,
+            ChangelogMode::Keys => 1,
+            ChangelogMode::KeysAndValues => 2,
+        }
+    }
+}
+
+impl From<u8> for ChangelogMode {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => ChangelogMode::Disabled,
+            1 => ChangelogMode::Keys,
+            2 => ChangelogMode::KeysAndValues,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// The kind of modification recorded by a [`Change`]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ChangeKind {
+    /// A key, or a key-value pair of a multimap table, was inserted
+    Insert,
+    /// The value of an existing key was replaced
+    Update,
+    /// A key, or a key-value pair of a multimap table, was removed
+    Remove,
+}
+
+#[allow(clippy::from_over_into)]
+impl Into<u8> for ChangeKind {
+    fn into(self) -> u8 {
+        match self {
+            ChangeKind::Insert => 1,
+            ChangeKind::Update => 2,
+            ChangeKind::Remove => 3,
+        }
+    }
+}
+
+impl From<u8> for ChangeKind {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => ChangeKind::Insert,
+            2 => ChangeKind::Update,
+            3 => ChangeKind::Remove,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// A modification of a table, recorded in its changelog
+///
+/// Keys and values are in their serialized form, and can be decoded with
+/// [`RedbValue::from_bytes`](crate::RedbValue::from_bytes). For multimap tables, the value is the
+/// value of the key-value pair that was inserted or removed
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Change {
+    transaction_id: u64,
+    kind: ChangeKind,
+    key: Vec<u8>,
+    old_value: Option<Vec<u8>>,
+    new_value: Option<Vec<u8>>,
+}
+
+impl Change {
+    fn from_bytes(transaction_id: u64, data: &[u8]) -> Self {
+        let kind = ChangeKind::from(data[0]);
+        let mut offset = 1;
+        let key_len = u32::from_le_bytes(
+            data[offset..(offset + size_of::<u32>())]
+                .try_into()
+                .unwrap(),
+        ) as usize;
+        offset += size_of::<u32>();
+        let key = data[offset..(offset + key_len)].to_vec();
+        offset += key_len;
+        let old_value = read_optional(data, &mut offset);
+        let new_value = read_optional(data, &mut offset);
+
+        Self {
+            transaction_id,
+            kind,
+            key,
+            old_value,
+            new_value,
+        }
+    }
+
+    /// Id of the transaction which made this change, as reported by
+    /// [`CommitEvent::transaction_id`](crate::CommitEvent::transaction_id)
+    pub fn transaction_id(&self) -> u64 {
+        self.transaction_id
+    }
+
+    pub fn kind(&self) -> ChangeKind {
+        self.kind
+    }
+
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+
+    /// The value before this change, if the changelog includes values and there was one
+    pub fn old_value(&self) -> Option<&[u8]> {
+        self.old_value.as_deref()
+    }
+
+    /// The value after this change, if the changelog includes values and there is one
+    pub fn new_value(&self) -> Option<&[u8]> {
+        self.new_value.as_deref()
+    }
+}
+
+fn read_optional(data: &[u8], offset: &mut usize) -> Option<Vec<u8>> {
+    let non_null = data[*offset] != 0;
+    *offset += 1;
+    if non_null {
+        let len = u32::from_le_bytes(
+            data[*offset..(*offset + size_of::<u32>())]
+                .try_into()
+                .unwrap(),
+        ) as usize;
+        *offset += size_of::<u32>();
+        let value = data[*offset..(*offset + len)].to_vec();
+        *offset += len;
+        Some(value)
+    } else {
+        None
+    }
+}
+
+fn write_optional(output: &mut Vec<u8>, value: Option<&[u8]>) {
+    if let Some(value) = value {
+        output.push(1);
+        output.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        output.extend_from_slice(value);
+    } else {
+        output.push(0);
+    }
+}
+
+// Appends changes to the changelog of a table, if it is enabled
+pub(crate) struct ChangelogWriter<'txn> {
+    tree: BtreeMut<'txn, ChangelogKey, [u8]>,
+    mode: ChangelogMode,
+    transaction_id: u64,
+    // The changelog may already contain changes from this transaction, if the table was opened
+    // before, so this is read from the tree when the first change is recorded
+    next_sequence: Option<u64>,
+}
+
+impl<'txn> ChangelogWriter<'txn> {
+    pub(crate) fn new(
+        root: Option<(PageNumber, Checksum)>,
+        mode: ChangelogMode,
+        transaction_id: u64,
+        mem: &'txn TransactionalMemory,
+        freed_pages: Rc<RefCell<Vec<PageNumber>>>,
+    ) -> Self {
+        Self {
+            tree: BtreeMut::new(root, mem, freed_pages),
+            mode,
+            transaction_id,
+            next_sequence: None,
+        }
+    }
+
+    pub(crate) fn get_root(&self) -> Option<(PageNumber, Checksum)> {
+        self.tree.get_root()
+    }
+
+    pub(crate) fn enabled(&self) -> bool {
+        self.mode != ChangelogMode::Disabled
+    }
+
+    // Records a change. The values are only stored if the changelog includes them
+    pub(crate) fn record(
+        &mut self,
+        kind: ChangeKind,
+        key: &[u8],
+        old_value: Option<&[u8]>,
+        new_value: Option<&[u8]>,
+    ) -> Result {
+        if !self.enabled() {
+            return Ok(());
+        }
+        let sequence = match self.next_sequence {
+            Some(sequence) => sequence,
+            None => match self.tree.last()? {
+                Some(((transaction_id, sequence), _)) if transaction_id == self.transaction_id => {
+                    sequence + 1
+                }
+                _ => 0,
+            },
+        };
+        self.next_sequence = Some(sequence + 1);
+
+        let mut change = vec![kind.into()];
+        change.extend_from_slice(&(key.len() as u32).to_le_bytes());
+        change.extend_from_slice(key);
+        if self.mode == ChangelogMode::KeysAndValues {
+            write_optional(&mut change, old_value);
+            write_optional(&mut change, new_value);
+        } else {
+            write_optional(&mut change, None);
+            write_optional(&mut change, None);
+        }
+        // Safety: References into the changelog are never returned to the user
+        unsafe {
+            self.tree
+                .insert(&(self.transaction_id, sequence), change.as_slice())?
+        };
+
+        Ok(())
+    }
+}
+
+/// An iterator over the changes in the changelog of a table, in the order they were made
+pub struct ChangeIter<'a> {
+    inner: BtreeRangeIter<'a, ChangelogKey, [u8]>,
+}
+
+impl<'a> ChangeIter<'a> {
+    // Iterates over the changes made by transactions after transaction_id
+    pub(crate) fn new(
+        root: Option<(PageNumber, Checksum)>,
+        transaction_id: u64,
+        mem: &'a TransactionalMemory,
+    ) -> Self {
+        let start = (transaction_id, u64::MAX);
+        Self {
+            inner: BtreeRangeIter::new::<_, ChangelogKey>(
+                (Bound::Excluded(start), Bound::Unbounded),
+                root.map(|(p, _)| p),
+                mem,
+            ),
+        }
+    }
+}
+
+impl<'a> Iterator for ChangeIter<'a> {
+    type Item = Change;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| {
+            let (transaction_id, _) = ChangelogKey::from_bytes(entry.key());
+            Change::from_bytes(transaction_id, entry.value())
+        })
+    }
+}
@@ -1,14 +1,23 @@
+mod blob;
 mod btree;
 mod btree_base;
 mod btree_iters;
 mod btree_mutator;
+mod changelog;
 mod page_store;
 mod table_tree;
 
+pub(crate) use blob::{all_blob_pages, verify_blob_checksums};
+pub use blob::{BlobReader, BlobWriter};
 pub(crate) use btree::{Btree, BtreeMut, RawBtree};
 pub use btree_base::AccessGuard;
-pub(crate) use btree_base::AccessGuardMut;
+pub use btree_base::AccessGuardMut;
 pub(crate) use btree_base::Checksum;
-pub(crate) use btree_iters::{AllPageNumbersBtreeIter, BtreeRangeIter};
+pub(crate) use btree_base::EntryAccessor;
+pub(crate) use btree_iters::{range_differs, AllPageNumbersBtreeIter, BtreeCursor, BtreeRangeIter};
+pub use changelog::{Change, ChangeIter, ChangeKind};
+pub(crate) use changelog::{ChangelogKey, ChangelogMode, ChangelogWriter};
 pub(crate) use page_store::{get_db_size, PageNumber, TransactionalMemory};
-pub(crate) use table_tree::{FreedTableKey, InternalTableDefinition, TableTree, TableType};
+pub(crate) use table_tree::{
+    FreedTableKey, InternalTableDefinition, SnapshotRecord, TableRoots, TableTree, TableType,
+};
@@ -14,7 +14,7 @@ use std::convert::TryInto;
 use std::fs::File;
 use std::io;
 use std::io::{Read, Seek, SeekFrom};
-use std::mem::size_of;
+use std::mem::{self, size_of};
 use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Mutex, MutexGuard};
@@ -32,11 +32,14 @@ use std::sync::{Mutex, MutexGuard};
 // 1 byte: version
 // 1 byte: != 0 if root page is non-null
 // 1 byte: != 0 if freed table root page is non-null
-// 5 bytes: padding
+// 1 byte: != 0 if snapshot table root page is non-null
+// 4 bytes: padding
 // 8 bytes: root page
 // 16 bytes: root checksum
 // 8 bytes: freed table root page
 // 16 bytes: freed table root checksum
+// 8 bytes: snapshot table root page
+// 16 bytes: snapshot table root checksum
 // 8 bytes: last committed transaction id
 // (db layout size) bytes: active database layout
 // 16 bytes: slot checksum
@@ -52,7 +55,7 @@ pub(super) const MIN_USABLE_PAGES: usize = 10;
 const MIN_DESIRED_USABLE_BYTES: usize = 1024 * 1024;
 
 // TODO: set to 1, when version 1.0 is released
-const FILE_FORMAT_VERSION: u8 = 102;
+const FILE_FORMAT_VERSION: u8 = 106;
 
 // Inspired by PNG's magic number
 const MAGICNUMBER: [u8; 9] = [b'r', b'e', b'd', b'b', 0x1A, 0x0A, 0xA9, 0x0D, 0x0A];
@@ -75,12 +78,15 @@ const ALLOCATOR_STATE_DIRTY: u8 = 2;
 const VERSION_OFFSET: usize = 0;
 const ROOT_NON_NULL_OFFSET: usize = size_of::<u8>();
 const FREED_ROOT_NON_NULL_OFFSET: usize = ROOT_NON_NULL_OFFSET + size_of::<u8>();
-const PADDING: usize = 5;
-const ROOT_PAGE_OFFSET: usize = FREED_ROOT_NON_NULL_OFFSET + size_of::<u8>() + PADDING;
+const SNAPSHOT_ROOT_NON_NULL_OFFSET: usize = FREED_ROOT_NON_NULL_OFFSET + size_of::<u8>();
+const PADDING: usize = 4;
+const ROOT_PAGE_OFFSET: usize = SNAPSHOT_ROOT_NON_NULL_OFFSET + size_of::<u8>() + PADDING;
 const ROOT_CHECKSUM_OFFSET: usize = ROOT_PAGE_OFFSET + size_of::<u64>();
 const FREED_ROOT_OFFSET: usize = ROOT_CHECKSUM_OFFSET + size_of::<u128>();
 const FREED_ROOT_CHECKSUM_OFFSET: usize = FREED_ROOT_OFFSET + size_of::<u64>();
-const TRANSACTION_ID_OFFSET: usize = FREED_ROOT_CHECKSUM_OFFSET + size_of::<u128>();
+const SNAPSHOT_ROOT_OFFSET: usize = FREED_ROOT_CHECKSUM_OFFSET + size_of::<u128>();
+const SNAPSHOT_ROOT_CHECKSUM_OFFSET: usize = SNAPSHOT_ROOT_OFFSET + size_of::<u64>();
+const TRANSACTION_ID_OFFSET: usize = SNAPSHOT_ROOT_CHECKSUM_OFFSET + size_of::<u128>();
 const DATA_LAYOUT_OFFSET: usize = TRANSACTION_ID_OFFSET + size_of::<u64>();
 const SLOT_CHECKSUM_OFFSET: usize = DATA_LAYOUT_OFFSET + DatabaseLayout::serialized_size();
 
@@ -377,6 +383,26 @@ impl<'a> TransactionAccessor<'a> {
         }
     }
 
+    fn get_snapshot_root_page(&self) -> Option<(PageNumber, Checksum)> {
+        if self.mem[SNAPSHOT_ROOT_NON_NULL_OFFSET] == 0 {
+            None
+        } else {
+            let num = PageNumber::from_le_bytes(
+                self.mem
+                    [SNAPSHOT_ROOT_OFFSET..(SNAPSHOT_ROOT_OFFSET + PageNumber::serialized_size())]
+                    .try_into()
+                    .unwrap(),
+            );
+            let checksum = Checksum::from_le_bytes(
+                self.mem[SNAPSHOT_ROOT_CHECKSUM_OFFSET
+                    ..(SNAPSHOT_ROOT_CHECKSUM_OFFSET + size_of::<Checksum>())]
+                    .try_into()
+                    .unwrap(),
+            );
+            Some((num, checksum))
+        }
+    }
+
     fn get_last_committed_transaction_id(&self) -> u64 {
         u64::from_le_bytes(
             self.mem[TRANSACTION_ID_OFFSET..(TRANSACTION_ID_OFFSET + size_of::<u64>())]
@@ -432,6 +458,19 @@ impl<'a> TransactionMutator<'a> {
         }
     }
 
+    fn set_snapshot_root(&mut self, page_number: Option<(PageNumber, Checksum)>) {
+        if let Some((num, checksum)) = page_number {
+            self.mem[SNAPSHOT_ROOT_OFFSET..(SNAPSHOT_ROOT_OFFSET + PageNumber::serialized_size())]
+                .copy_from_slice(&num.to_le_bytes());
+            self.mem[SNAPSHOT_ROOT_CHECKSUM_OFFSET
+                ..(SNAPSHOT_ROOT_CHECKSUM_OFFSET + size_of::<Checksum>())]
+                .copy_from_slice(&checksum.to_le_bytes());
+            self.mem[SNAPSHOT_ROOT_NON_NULL_OFFSET] = 1;
+        } else {
+            self.mem[SNAPSHOT_ROOT_NON_NULL_OFFSET] = 0;
+        }
+    }
+
     fn set_last_committed_transaction_id(&mut self, transaction_id: u64) {
         self.mem[TRANSACTION_ID_OFFSET..(TRANSACTION_ID_OFFSET + size_of::<u64>())]
             .copy_from_slice(&transaction_id.to_le_bytes());
@@ -463,6 +502,11 @@ pub(crate) struct TransactionalMemory {
     // Pages allocated since the last commit
     allocated_since_commit: Mutex<HashSet<PageNumber>>,
     log_since_commit: Mutex<Vec<AllocationOp>>,
+    // The position in the allocation log of each live savepoint, in the order they were created,
+    // and the pages allocated since the last commit which may be referenced by it. These are
+    // removed from allocated_since_commit while the savepoint is live, so that they're copied on
+    // write instead of being modified or freed in-place
+    savepoint_allocations: Mutex<Vec<(usize, HashSet<PageNumber>)>>,
     // Metadata guard lock should be held when using this to modify the page allocator state
     // May be None, if the allocator state was corrupted when the file was opened
     regional_allocators: Mutex<Option<Vec<BuddyAllocator>>>,
@@ -496,7 +540,7 @@ impl TransactionalMemory {
         dynamic_growth: bool,
         use_checksums: Option<bool>,
     ) -> Result<Self> {
-        assert!(DATA_LAYOUT_OFFSET + DatabaseLayout::serialized_size() <= TRANSACTION_SIZE);
+        assert!(SLOT_CHECKSUM_OFFSET + size_of::<Checksum>() <= TRANSACTION_SIZE);
 
         let page_size = requested_page_size.unwrap_or_else(get_page_size);
         assert!(page_size.is_power_of_two());
@@ -582,6 +626,7 @@ impl TransactionalMemory {
             let mut mutator = metadata.secondary_slot_mut();
             mutator.set_root_page(None);
             mutator.set_freed_root(None);
+            mutator.set_snapshot_root(None);
             mutator.set_last_committed_transaction_id(0);
             mutator.set_data_section_layout(&layout);
             mutator.set_version(FILE_FORMAT_VERSION);
@@ -623,6 +668,7 @@ impl TransactionalMemory {
         Ok(TransactionalMemory {
             allocated_since_commit: Mutex::new(HashSet::new()),
             log_since_commit: Mutex::new(vec![]),
+            savepoint_allocations: Mutex::new(vec![]),
             regional_allocators: Mutex::new(regional_allocators),
             mmap,
             metadata_guard: mutex,
@@ -745,6 +791,7 @@ impl TransactionalMemory {
         &self,
         data_root: Option<(PageNumber, Checksum)>,
         freed_root: Option<(PageNumber, Checksum)>,
+        snapshot_root: Option<(PageNumber, Checksum)>,
         transaction_id: u64,
         eventual: bool,
     ) -> Result {
@@ -769,6 +816,7 @@ impl TransactionalMemory {
         secondary.set_last_committed_transaction_id(transaction_id);
         secondary.set_root_page(data_root);
         secondary.set_freed_root(freed_root);
+        secondary.set_snapshot_root(snapshot_root);
         secondary.set_data_section_layout(&layout);
         secondary.update_checksum(checksum_type);
 
@@ -800,6 +848,7 @@ impl TransactionalMemory {
 
         self.log_since_commit.lock().unwrap().clear();
         self.allocated_since_commit.lock().unwrap().clear();
+        self.savepoint_allocations.lock().unwrap().clear();
         self.read_from_secondary.store(false, Ordering::Release);
 
         Ok(())
@@ -810,6 +859,7 @@ impl TransactionalMemory {
         &self,
         data_root: Option<(PageNumber, Checksum)>,
         freed_root: Option<(PageNumber, Checksum)>,
+        snapshot_root: Option<(PageNumber, Checksum)>,
         transaction_id: u64,
     ) -> Result {
         // All mutable pages must be dropped, this ensures that when a transaction completes
@@ -826,11 +876,13 @@ impl TransactionalMemory {
         secondary.set_last_committed_transaction_id(transaction_id);
         secondary.set_root_page(data_root);
         secondary.set_freed_root(freed_root);
+        secondary.set_snapshot_root(snapshot_root);
         secondary.set_data_section_layout(&layout);
         secondary.update_checksum(checksum_type);
 
         self.log_since_commit.lock().unwrap().clear();
         self.allocated_since_commit.lock().unwrap().clear();
+        self.savepoint_allocations.lock().unwrap().clear();
         self.read_from_secondary.store(true, Ordering::Release);
 
         Ok(())
@@ -874,6 +926,7 @@ impl TransactionalMemory {
             }
         }
         self.allocated_since_commit.lock().unwrap().clear();
+        self.savepoint_allocations.lock().unwrap().clear();
 
         // Shrinking only happens during commit
         assert!(restore.len() <= layout.len());
@@ -902,6 +955,83 @@ impl TransactionalMemory {
         Ok(())
     }
 
+    // Creates a savepoint, which can be passed to restore_savepoint() or release_savepoint() by its
+    // index among the live savepoints
+    //
+    // Pages allocated before the savepoint are treated as committed until it's released, so that
+    // they're copied on write instead of being modified or freed in-place
+    pub(crate) fn savepoint(&self) {
+        #[cfg(debug_assertions)]
+        debug_assert!(self.open_dirty_pages.lock().unwrap().is_empty());
+        let allocated = mem::take(&mut *self.allocated_since_commit.lock().unwrap());
+        let log_position = self.log_since_commit.lock().unwrap().len();
+        self.savepoint_allocations
+            .lock()
+            .unwrap()
+            .push((log_position, allocated));
+    }
+
+    // Releases the savepoint, so that the pages which were frozen by it can be modified in-place
+    // again, unless they're still referenced by a later savepoint
+    pub(crate) fn release_savepoint(&self, index: usize) {
+        let mut savepoints = self.savepoint_allocations.lock().unwrap();
+        let (_, allocated) = savepoints.remove(index);
+        // Pages allocated before an earlier savepoint were moved to it, so they remain frozen
+        if let Some((_, next)) = savepoints.get_mut(index) {
+            next.extend(allocated);
+        } else {
+            self.allocated_since_commit
+                .lock()
+                .unwrap()
+                .extend(allocated);
+        }
+    }
+
+    // Reverts all allocations and frees made since the savepoint, and releases all the savepoints
+    // created after it
+    pub(crate) fn restore_savepoint(&self, index: usize) -> Result {
+        #[cfg(debug_assertions)]
+        debug_assert!(self.open_dirty_pages.lock().unwrap().is_empty());
+        let mut savepoints = self.savepoint_allocations.lock().unwrap();
+        // Pages frozen by later savepoints were allocated after this one, so are reverted below
+        savepoints.truncate(index + 1);
+        let log_position = savepoints[index].0;
+        let mut metadata = self.lock_metadata();
+        let regional_guard = self.regional_allocators.lock().unwrap();
+        let layout = self.layout.lock().unwrap();
+        let (mut region_allocator, mut regions) = metadata.allocators_mut(&layout)?;
+        let mut log = self.log_since_commit.lock().unwrap();
+        assert!(log_position <= log.len());
+        for op in log.drain(log_position..).rev() {
+            match op {
+                AllocationOp::Allocate(page_number) => {
+                    let region = page_number.region as usize;
+                    region_allocator.clear(region);
+                    let mem = regions.get_regional_allocator_mut(region);
+                    regional_guard.as_ref().unwrap()[region].free(
+                        mem,
+                        page_number.page_index as u64,
+                        page_number.page_order as usize,
+                    );
+                }
+                AllocationOp::Free(page_number) | AllocationOp::FreeUncommitted(page_number) => {
+                    let region = page_number.region as usize;
+                    let mem = regions.get_regional_allocator_mut(region);
+                    regional_guard.as_ref().unwrap()[region].record_alloc(
+                        mem,
+                        page_number.page_index as u64,
+                        page_number.page_order as usize,
+                    );
+                }
+            }
+        }
+        // Everything allocated after the savepoint has been released. Everything allocated before
+        // it stays frozen, since the savepoint may be restored again
+        self.allocated_since_commit.lock().unwrap().clear();
+
+        Ok(())
+    }
+
     pub(crate) fn get_page(&self, page_number: PageNumber) -> PageImpl {
         // We must not retrieve an immutable reference to a page which already has a mutable ref to it
         #[cfg(debug_assertions)]
@@ -963,6 +1093,15 @@ impl TransactionalMemory {
         }
     }
 
+    pub(crate) fn get_snapshot_root(&self) -> Option<(PageNumber, Checksum)> {
+        let metadata = self.lock_metadata();
+        if self.read_from_secondary.load(Ordering::Acquire) {
+            metadata.secondary_slot().get_snapshot_root_page()
+        } else {
+            metadata.primary_slot().get_snapshot_root_page()
+        }
+    }
+
     pub(crate) fn get_last_committed_transaction_id(&self) -> Result<u64> {
         let metadata = self.lock_metadata();
         if self.read_from_secondary.load(Ordering::Acquire) {
@@ -1295,6 +1434,11 @@ impl TransactionalMemory {
     pub(crate) fn get_page_size(&self) -> usize {
         self.page_size
     }
+
+    // The largest allocation which can fit in a region
+    pub(crate) fn get_max_allocation_size(&self) -> usize {
+        self.region_size - self.region_header_with_padding_size
+    }
 }
 
 impl Drop for TransactionalMemory {
@@ -1304,8 +1448,15 @@ impl Drop for TransactionalMemory {
             if let Ok(non_durable_transaction_id) = self.get_last_committed_transaction_id() {
                 let root = self.get_data_root();
                 let freed_root = self.get_freed_root();
+                let snapshot_root = self.get_snapshot_root();
                 if self
-                    .commit(root, freed_root, non_durable_transaction_id, false)
+                    .commit(
+                        root,
+                        freed_root,
+                        snapshot_root,
+                        non_durable_transaction_id,
+                        false,
+                    )
                     .is_err()
                 {
                     eprintln!(
@@ -1467,6 +1618,59 @@ mod test {
         write_txn.commit().unwrap();
     }
 
+    #[test]
+    fn repair_allocator_snapshots() {
+        let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+        let max_size = 1024 * 1024;
+        let db = unsafe { Database::create(tmpfile.path(), max_size).unwrap() };
+        let write_txn = db.begin_write().unwrap();
+        {
+            let mut table = write_txn.open_table(X).unwrap();
+            table.insert(b"hello", b"world").unwrap();
+        }
+        write_txn.commit().unwrap();
+        let write_txn = db.begin_write().unwrap();
+        write_txn.create_snapshot("snapshot").unwrap();
+        {
+            let mut table = write_txn.open_table(X).unwrap();
+            table.insert(b"hello", b"world2").unwrap();
+        }
+        write_txn.commit().unwrap();
+        let write_txn = db.begin_write().unwrap();
+        let free_pages = write_txn.stats().unwrap().free_pages();
+        write_txn.abort().unwrap();
+        drop(db);
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(tmpfile.path())
+            .unwrap();
+
+        file.seek(SeekFrom::Start(GOD_BYTE_OFFSET as u64)).unwrap();
+        let mut buffer = [0u8; 1];
+        file.read_exact(&mut buffer).unwrap();
+        file.seek(SeekFrom::Start(GOD_BYTE_OFFSET as u64)).unwrap();
+        buffer[0] |= ALLOCATOR_STATE_DIRTY;
+        file.write_all(&buffer).unwrap();
+
+        let db2 = unsafe { Database::create(tmpfile.path(), max_size).unwrap() };
+        let write_txn = db2.begin_write().unwrap();
+        assert_eq!(free_pages, write_txn.stats().unwrap().free_pages());
+        // Pages referenced only by the snapshot are reclaimed by the first commit after its
+        // deletion, since it can still be opened until the deletion is committed
+        write_txn.delete_snapshot("snapshot").unwrap();
+        write_txn.commit().unwrap();
+        db2.begin_write().unwrap().commit().unwrap();
+        let write_txn = db2.begin_write().unwrap();
+        assert!(write_txn.stats().unwrap().free_pages() > free_pages);
+        write_txn.abort().unwrap();
+
+        let read_txn = db2.begin_read().unwrap();
+        let table = read_txn.open_table(X).unwrap();
+        assert_eq!(table.get(b"hello").unwrap().unwrap(), b"world2");
+    }
+
     #[test]
     fn too_small_db() {
         let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
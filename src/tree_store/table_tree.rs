@@ -1,7 +1,10 @@
 use crate::tree_store::btree::btree_stats;
 use crate::tree_store::btree_base::Checksum;
 use crate::tree_store::btree_iters::AllPageNumbersBtreeIter;
-use crate::tree_store::{BtreeMut, BtreeRangeIter, PageNumber, TransactionalMemory};
+use crate::tree_store::{
+    all_blob_pages, Btree, BtreeMut, BtreeRangeIter, ChangelogKey, ChangelogMode, PageNumber,
+    TransactionalMemory,
+};
 use crate::types::{
     AsBytesWithLifetime, OwnedAsBytesLifetime, OwnedLifetime, RedbKey, RedbValue, WithLifetime,
 };
@@ -61,6 +64,84 @@ impl RedbKey for FreedTableKey {
     }
 }
 
+// A named snapshot of a committed database state. The snapshot pins all pages reachable from
+// data_root, which was committed by transaction_id
+#[derive(Debug)]
+pub(crate) struct SnapshotRecord {
+    pub(crate) transaction_id: u64,
+    pub(crate) data_root: Option<(PageNumber, Checksum)>,
+}
+
+impl SnapshotRecord {
+    const SERIALIZED_SIZE: usize =
+        size_of::<u64>() + 1 + PageNumber::serialized_size() + size_of::<Checksum>();
+}
+
+impl RedbValue for SnapshotRecord {
+    type View = OwnedLifetime<SnapshotRecord>;
+    type ToBytes = OwnedAsBytesLifetime<[u8; SnapshotRecord::SERIALIZED_SIZE]>;
+
+    fn fixed_width() -> Option<usize> {
+        Some(Self::SERIALIZED_SIZE)
+    }
+
+    fn from_bytes(data: &[u8]) -> <Self::View as WithLifetime>::Out {
+        let mut offset = 0;
+        let transaction_id = u64::from_le_bytes(
+            data[offset..(offset + size_of::<u64>())]
+                .try_into()
+                .unwrap(),
+        );
+        offset += size_of::<u64>();
+
+        let non_null = data[offset] != 0;
+        offset += 1;
+        let data_root = if non_null {
+            let root = PageNumber::from_le_bytes(
+                data[offset..(offset + PageNumber::serialized_size())]
+                    .try_into()
+                    .unwrap(),
+            );
+            offset += PageNumber::serialized_size();
+            let checksum = Checksum::from_le_bytes(
+                data[offset..(offset + size_of::<Checksum>())]
+                    .try_into()
+                    .unwrap(),
+            );
+            Some((root, checksum))
+        } else {
+            None
+        };
+
+        Self {
+            transaction_id,
+            data_root,
+        }
+    }
+
+    fn as_bytes(&self) -> <Self::ToBytes as AsBytesWithLifetime>::Out {
+        let mut result = [0u8; Self::SERIALIZED_SIZE];
+        let mut offset = 0;
+        result[offset..(offset + size_of::<u64>())]
+            .copy_from_slice(&self.transaction_id.to_le_bytes());
+        offset += size_of::<u64>();
+        if let Some((root, checksum)) = self.data_root {
+            result[offset] = 1;
+            offset += 1;
+            result[offset..(offset + PageNumber::serialized_size())]
+                .copy_from_slice(&root.to_le_bytes());
+            offset += PageNumber::serialized_size();
+            result[offset..(offset + size_of::<Checksum>())]
+                .copy_from_slice(&checksum.to_le_bytes());
+        }
+        result
+    }
+
+    fn redb_type_name() -> String {
+        "SnapshotRecord".to_string()
+    }
+}
+
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
 pub(crate) enum TableType {
     Normal,
@@ -87,9 +168,24 @@ impl From<u8> for TableType {
     }
 }
 
+// The roots of the trees which store a table
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub(crate) struct TableRoots {
+    pub(crate) table_root: Option<(PageNumber, Checksum)>,
+    // Maps keys to the headers of their blobs
+    pub(crate) blob_root: Option<(PageNumber, Checksum)>,
+    pub(crate) changelog_root: Option<(PageNumber, Checksum)>,
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct InternalTableDefinition {
     table_root: Option<(PageNumber, Checksum)>,
+    blob_root: Option<(PageNumber, Checksum)>,
+    changelog_root: Option<(PageNumber, Checksum)>,
+    changelog_mode: ChangelogMode,
+    // Set once the table has been opened for writing with out of line values enabled. Deleting
+    // whole subtrees of a table without it doesn't need to read their leaves
+    out_of_line_values: bool,
     table_type: TableType,
     fixed_key_size: Option<usize>,
     fixed_value_size: Option<usize>,
@@ -102,6 +198,36 @@ impl InternalTableDefinition {
         self.table_root
     }
 
+    pub(crate) fn get_blob_root(&self) -> Option<(PageNumber, Checksum)> {
+        self.blob_root
+    }
+
+    pub(crate) fn get_changelog_root(&self) -> Option<(PageNumber, Checksum)> {
+        self.changelog_root
+    }
+
+    pub(crate) fn get_changelog_mode(&self) -> ChangelogMode {
+        self.changelog_mode
+    }
+
+    pub(crate) fn has_out_of_line_values(&self) -> bool {
+        self.out_of_line_values
+    }
+
+    fn roots(&self) -> TableRoots {
+        TableRoots {
+            table_root: self.table_root,
+            blob_root: self.blob_root,
+            changelog_root: self.changelog_root,
+        }
+    }
+
+    fn set_roots(&mut self, roots: TableRoots) {
+        self.table_root = roots.table_root;
+        self.blob_root = roots.blob_root;
+        self.changelog_root = roots.changelog_root;
+    }
+
     pub(crate) fn get_fixed_key_size(&self) -> Option<usize> {
         self.fixed_key_size
     }
@@ -113,6 +239,14 @@ impl InternalTableDefinition {
     pub(crate) fn get_type(&self) -> TableType {
         self.table_type
     }
+
+    pub(crate) fn get_key_type(&self) -> &str {
+        &self.key_type
+    }
+
+    pub(crate) fn get_value_type(&self) -> &str {
+        &self.value_type
+    }
 }
 
 impl RedbValue for InternalTableDefinition {
@@ -129,27 +263,16 @@ impl RedbValue for InternalTableDefinition {
         let table_type = TableType::from(data[offset]);
         offset += 1;
 
-        let non_null = data[offset] != 0;
+        let table_root = read_root(&data[offset..]);
+        offset += 1 + PageNumber::serialized_size() + size_of::<Checksum>();
+        let blob_root = read_root(&data[offset..]);
+        offset += 1 + PageNumber::serialized_size() + size_of::<Checksum>();
+        let changelog_root = read_root(&data[offset..]);
+        offset += 1 + PageNumber::serialized_size() + size_of::<Checksum>();
+        let changelog_mode = ChangelogMode::from(data[offset]);
+        offset += 1;
+        let out_of_line_values = data[offset] != 0;
         offset += 1;
-        let table_root = if non_null {
-            let table_root = PageNumber::from_le_bytes(
-                data[offset..(offset + PageNumber::serialized_size())]
-                    .try_into()
-                    .unwrap(),
-            );
-            offset += PageNumber::serialized_size();
-            let checksum = Checksum::from_le_bytes(
-                data[offset..(offset + size_of::<Checksum>())]
-                    .try_into()
-                    .unwrap(),
-            );
-            offset += size_of::<Checksum>();
-            Some((table_root, checksum))
-        } else {
-            offset += PageNumber::serialized_size();
-            offset += size_of::<Checksum>();
-            None
-        };
 
         let non_null = data[offset] != 0;
         offset += 1;
@@ -193,6 +316,10 @@ impl RedbValue for InternalTableDefinition {
 
         InternalTableDefinition {
             table_root,
+            blob_root,
+            changelog_root,
+            changelog_mode,
+            out_of_line_values,
             table_type,
             fixed_key_size,
             fixed_value_size,
@@ -203,15 +330,11 @@ impl RedbValue for InternalTableDefinition {
 
     fn as_bytes(&self) -> <Self::ToBytes as AsBytesWithLifetime>::Out {
         let mut result = vec![self.table_type.into()];
-        if let Some((root, checksum)) = self.table_root {
-            result.push(1);
-            result.extend_from_slice(&root.to_le_bytes());
-            result.extend_from_slice(&checksum.to_le_bytes());
-        } else {
-            result.push(0);
-            result.extend_from_slice(&[0; PageNumber::serialized_size()]);
-            result.extend_from_slice(&[0; size_of::<Checksum>()]);
-        }
+        write_root(&mut result, self.table_root);
+        write_root(&mut result, self.blob_root);
+        write_root(&mut result, self.changelog_root);
+        result.push(self.changelog_mode.into());
+        result.push(self.out_of_line_values.into());
         if let Some(fixed) = self.fixed_key_size {
             result.push(1);
             result.extend_from_slice(&(fixed as u32).to_le_bytes());
@@ -238,6 +361,40 @@ impl RedbValue for InternalTableDefinition {
     }
 }
 
+// Reads a nullable root, which is always serialized with the same length
+fn read_root(data: &[u8]) -> Option<(PageNumber, Checksum)> {
+    let non_null = data[0] != 0;
+    if non_null {
+        let mut offset = 1;
+        let root = PageNumber::from_le_bytes(
+            data[offset..(offset + PageNumber::serialized_size())]
+                .try_into()
+                .unwrap(),
+        );
+        offset += PageNumber::serialized_size();
+        let checksum = Checksum::from_le_bytes(
+            data[offset..(offset + size_of::<Checksum>())]
+                .try_into()
+                .unwrap(),
+        );
+        Some((root, checksum))
+    } else {
+        None
+    }
+}
+
+fn write_root(output: &mut Vec<u8>, root: Option<(PageNumber, Checksum)>) {
+    if let Some((root, checksum)) = root {
+        output.push(1);
+        output.extend_from_slice(&root.to_le_bytes());
+        output.extend_from_slice(&checksum.to_le_bytes());
+    } else {
+        output.push(0);
+        output.extend_from_slice(&[0; PageNumber::serialized_size()]);
+        output.extend_from_slice(&[0; size_of::<Checksum>()]);
+    }
+}
+
 pub struct TableNameIter<'a> {
     inner: BtreeRangeIter<'a, str, InternalTableDefinition>,
     table_type: TableType,
@@ -247,7 +404,7 @@ impl<'a> Iterator for TableNameIter<'a> {
     type Item = String;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(entry) = self.inner.next() {
+        for entry in self.inner.by_ref() {
             if InternalTableDefinition::from_bytes(entry.value()).table_type == self.table_type {
                 return Some(str::from_bytes(entry.key()).to_string());
             }
@@ -260,7 +417,7 @@ pub(crate) struct TableTree<'txn> {
     tree: BtreeMut<'txn, str, InternalTableDefinition>,
     mem: &'txn TransactionalMemory,
     // Cached updates from tables that have been closed. These must be flushed to the btree
-    pending_table_updates: HashMap<String, Option<(PageNumber, Checksum)>>,
+    pending_table_updates: HashMap<String, TableRoots>,
     freed_pages: Rc<RefCell<Vec<PageNumber>>>,
 }
 
@@ -278,30 +435,42 @@ impl<'txn> TableTree<'txn> {
         }
     }
 
-    // Queues an update to the table root
-    pub(crate) fn stage_update_table_root(
-        &mut self,
-        name: &str,
-        table_root: Option<(PageNumber, Checksum)>,
-    ) {
-        self.pending_table_updates
-            .insert(name.to_string(), table_root);
+    // Queues an update to the table roots
+    pub(crate) fn stage_update_table_root(&mut self, name: &str, roots: TableRoots) {
+        self.pending_table_updates.insert(name.to_string(), roots);
     }
 
     pub(crate) fn clear_table_root_updates(&mut self) {
         self.pending_table_updates.clear();
     }
 
+    // Returns the root of the master table, and the cached updates which have not been flushed to it
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn get_state(
+        &self,
+    ) -> (Option<(PageNumber, Checksum)>, HashMap<String, TableRoots>) {
+        (self.tree.get_root(), self.pending_table_updates.clone())
+    }
+
+    pub(crate) fn restore_state(
+        &mut self,
+        master_root: Option<(PageNumber, Checksum)>,
+        pending_table_updates: HashMap<String, TableRoots>,
+    ) {
+        self.tree = BtreeMut::new(master_root, self.mem, self.freed_pages.clone());
+        self.pending_table_updates = pending_table_updates;
+    }
+
     pub(crate) fn flush_table_root_updates(&mut self) -> Result<Option<(PageNumber, Checksum)>> {
-        for (name, table_root) in self.pending_table_updates.drain() {
+        for (name, roots) in self.pending_table_updates.drain() {
             // Bypass .get_table() since the table types are dynamic
             // TODO: optimize away this get()
             let mut definition = self.tree.get(&name).unwrap().unwrap();
-            // No-op if the root has not changed
-            if definition.table_root == table_root {
+            // No-op if the roots have not changed
+            if definition.roots() == roots {
                 continue;
             }
-            definition.table_root = table_root;
+            definition.set_roots(roots);
             // Safety: References into the master table are never returned to the user
             unsafe {
                 self.tree.insert(&name, &definition)?;
@@ -310,6 +479,35 @@ impl<'txn> TableTree<'txn> {
         Ok(self.tree.get_root())
     }
 
+    // Returns the names of the tables whose definitions differ from those in the master table
+    // rooted at base_root, including tables which were created or deleted. Table root updates
+    // must have already been flushed
+    pub(crate) fn changed_tables(
+        &self,
+        base_root: Option<(PageNumber, Checksum)>,
+    ) -> Result<Vec<String>> {
+        assert!(self.pending_table_updates.is_empty());
+        let base: Btree<str, InternalTableDefinition> = Btree::new(base_root, self.mem);
+        let mut base_definitions = HashMap::new();
+        for entry in base.range::<RangeFull, &str>(..)? {
+            base_definitions.insert(str::from_bytes(entry.key()).to_string(), entry.value());
+        }
+
+        let mut changed = vec![];
+        for entry in self.tree.range::<RangeFull, &str>(..)? {
+            let name = str::from_bytes(entry.key());
+            // The definitions include the checksums of the table roots, so any change to the
+            // contents of a table changes its definition
+            if base_definitions.remove(name) != Some(entry.value()) {
+                changed.push(name.to_string());
+            }
+        }
+        changed.extend(base_definitions.into_keys());
+        changed.sort();
+
+        Ok(changed)
+    }
+
     // root_page: the root of the master table
     pub(crate) fn list_tables(&self, table_type: TableType) -> Result<Vec<String>> {
         let iter = self.tree.range::<RangeFull, &str>(..)?;
@@ -320,19 +518,46 @@ impl<'txn> TableTree<'txn> {
         Ok(iter.collect())
     }
 
-    // root_page: the root of the master table
-    pub(crate) fn get_table<K: RedbKey + ?Sized, V: RedbValue + ?Sized>(
+    // Returns the table, which may be of any type
+    pub(crate) fn get_definition(&self, name: &str) -> Result<Option<InternalTableDefinition>> {
+        if let Some(mut definition) = self.tree.get(name)? {
+            if let Some(updated_roots) = self.pending_table_updates.get(name) {
+                definition.set_roots(*updated_roots);
+            }
+
+            Ok(Some(definition))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // Returns the table without checking its key and value types
+    pub(crate) fn get_untyped_table(
         &self,
         name: &str,
         table_type: TableType,
     ) -> Result<Option<InternalTableDefinition>> {
-        if let Some(mut definition) = self.tree.get(name)? {
+        if let Some(definition) = self.get_definition(name)? {
             if definition.get_type() != table_type {
                 return Err(Error::TableTypeMismatch(format!(
                     "{:?} is not of type {:?}",
                     name, table_type
                 )));
             }
+
+            Ok(Some(definition))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // root_page: the root of the master table
+    pub(crate) fn get_table<K: RedbKey + ?Sized, V: RedbValue + ?Sized>(
+        &self,
+        name: &str,
+        table_type: TableType,
+    ) -> Result<Option<InternalTableDefinition>> {
+        if let Some(definition) = self.get_untyped_table(name, table_type)? {
             if definition.key_type != K::redb_type_name()
                 || definition.value_type != V::redb_type_name()
             {
@@ -346,10 +571,6 @@ impl<'txn> TableTree<'txn> {
                 )));
             }
 
-            if let Some(updated_root) = self.pending_table_updates.get(name) {
-                definition.table_root = *updated_root;
-            }
-
             Ok(Some(definition))
         } else {
             Ok(None)
@@ -363,19 +584,7 @@ impl<'txn> TableTree<'txn> {
         table_type: TableType,
     ) -> Result<bool> {
         if let Some(definition) = self.get_table::<K, V>(name, table_type)? {
-            if let Some((table_root, _)) = definition.get_root() {
-                let iter = AllPageNumbersBtreeIter::new(
-                    table_root,
-                    K::fixed_width(),
-                    V::fixed_width(),
-                    self.mem,
-                );
-                let mut freed_pages = self.freed_pages.borrow_mut();
-                for page_number in iter {
-                    freed_pages.push(page_number);
-                }
-            }
-
+            self.free_table_pages(&definition);
             self.pending_table_updates.remove(name);
 
             // Safety: References into the master table are never returned to the user
@@ -386,6 +595,163 @@ impl<'txn> TableTree<'txn> {
         Ok(false)
     }
 
+    // Frees all the pages of the trees which store the table
+    fn free_table_pages(&mut self, definition: &InternalTableDefinition) {
+        if let Some((table_root, _)) = definition.get_root() {
+            let iter = AllPageNumbersBtreeIter::new(
+                table_root,
+                definition.fixed_key_size,
+                definition.fixed_value_size,
+                self.mem,
+            );
+            let mut freed_pages = self.freed_pages.borrow_mut();
+            for page_number in iter {
+                freed_pages.push(page_number);
+            }
+        }
+        if let Some((blob_root, _)) = definition.get_blob_root() {
+            let pages = all_blob_pages(blob_root, definition.fixed_key_size, self.mem);
+            self.freed_pages.borrow_mut().extend(pages);
+        }
+        self.free_changelog_pages(definition);
+    }
+
+    fn free_changelog_pages(&mut self, definition: &InternalTableDefinition) {
+        if let Some((changelog_root, _)) = definition.get_changelog_root() {
+            let iter = AllPageNumbersBtreeIter::new(
+                changelog_root,
+                ChangelogKey::fixed_width(),
+                None,
+                self.mem,
+            );
+            self.freed_pages.borrow_mut().extend(iter);
+        }
+    }
+
+    // Enables or disables the changelog of the table. Disabling it deletes all its changes
+    pub(crate) fn set_changelog_mode(&mut self, name: &str, mode: ChangelogMode) -> Result {
+        let mut definition = self
+            .get_definition(name)?
+            .ok_or_else(|| Error::TableDoesNotExist(name.to_string()))?;
+        // get_definition() applied any pending update to the roots, so it can be dropped
+        self.pending_table_updates.remove(name);
+        if mode == ChangelogMode::Disabled {
+            self.free_changelog_pages(&definition);
+            definition.changelog_root = None;
+        }
+        definition.changelog_mode = mode;
+        // Safety: References into the master table are never returned to the user
+        unsafe { self.tree.insert(name, &definition)? };
+        Ok(())
+    }
+
+    // Marks the table as possibly containing out of line values
+    pub(crate) fn enable_out_of_line_values(
+        &mut self,
+        name: &str,
+    ) -> Result<InternalTableDefinition> {
+        let mut definition = self
+            .get_definition(name)?
+            .ok_or_else(|| Error::TableDoesNotExist(name.to_string()))?;
+        // get_definition() applied any pending update to the roots, so it can be dropped
+        self.pending_table_updates.remove(name);
+        definition.out_of_line_values = true;
+        // Safety: References into the master table are never returned to the user
+        unsafe { self.tree.insert(name, &definition)? };
+        Ok(definition)
+    }
+
+    // Removes all changes made by transactions up to, and including, transaction_id from the
+    // changelog of the table
+    pub(crate) fn truncate_changelog(&mut self, name: &str, transaction_id: u64) -> Result {
+        let mut definition = self
+            .get_definition(name)?
+            .ok_or_else(|| Error::TableDoesNotExist(name.to_string()))?;
+        if definition.get_changelog_mode() == ChangelogMode::Disabled {
+            return Err(Error::ChangelogNotEnabled(name.to_string()));
+        }
+        self.pending_table_updates.remove(name);
+        let mut changelog: BtreeMut<ChangelogKey, [u8]> = BtreeMut::new(
+            definition.get_changelog_root(),
+            self.mem,
+            self.freed_pages.clone(),
+        );
+        // Safety: References into the changelog are never returned to the user
+        unsafe { changelog.delete_range(..=(transaction_id, u64::MAX))? };
+        definition.changelog_root = changelog.get_root();
+        // Safety: References into the master table are never returned to the user
+        unsafe { self.tree.insert(name, &definition)? };
+        Ok(())
+    }
+
+    // Replaces the table with one of type Table<K2, V2>, stored in the given trees. The pages of
+    // the old trees are freed
+    pub(crate) fn replace_table<
+        K1: RedbKey + ?Sized,
+        V1: RedbValue + ?Sized,
+        K2: RedbKey + ?Sized,
+        V2: RedbValue + ?Sized,
+    >(
+        &mut self,
+        name: &str,
+        roots: TableRoots,
+        out_of_line_values: bool,
+    ) -> Result {
+        let definition = self
+            .get_table::<K1, V1>(name, TableType::Normal)?
+            .ok_or_else(|| Error::TableDoesNotExist(name.to_string()))?;
+        self.free_table_pages(&definition);
+        self.pending_table_updates.remove(name);
+
+        let mut table = InternalTableDefinition {
+            table_root: None,
+            blob_root: None,
+            changelog_root: None,
+            changelog_mode: definition.get_changelog_mode(),
+            out_of_line_values,
+            table_type: TableType::Normal,
+            fixed_key_size: K2::fixed_width(),
+            fixed_value_size: V2::fixed_width(),
+            key_type: K2::redb_type_name(),
+            value_type: V2::redb_type_name(),
+        };
+        table.set_roots(roots);
+        // Safety: References into the master table are never returned to the user
+        unsafe { self.tree.insert(name, &table)? };
+        Ok(())
+    }
+
+    // Changes the type of the table to Table<K2, V2>, without modifying its contents
+    pub(crate) fn retype_table<
+        K1: RedbKey + ?Sized,
+        V1: RedbValue + ?Sized,
+        K2: RedbKey + ?Sized,
+        V2: RedbValue + ?Sized,
+    >(
+        &mut self,
+        name: &str,
+    ) -> Result {
+        if K1::fixed_width() != K2::fixed_width() || V1::fixed_width() != V2::fixed_width() {
+            return Err(Error::TableTypeMismatch(format!(
+                "Table<{}, {}> cannot be retyped to Table<{}, {}>, since their fixed widths differ",
+                K1::redb_type_name(),
+                V1::redb_type_name(),
+                K2::redb_type_name(),
+                V2::redb_type_name()
+            )));
+        }
+        let mut definition = self
+            .get_table::<K1, V1>(name, TableType::Normal)?
+            .ok_or_else(|| Error::TableDoesNotExist(name.to_string()))?;
+        // get_table() applied any pending update to the roots, so it can be dropped
+        self.pending_table_updates.remove(name);
+        definition.key_type = K2::redb_type_name();
+        definition.value_type = V2::redb_type_name();
+        // Safety: References into the master table are never returned to the user
+        unsafe { self.tree.insert(name, &definition)? };
+        Ok(())
+    }
+
     // Returns a tuple of the table id and the new root page
     // root_page: the root of the master table
     pub(crate) fn get_or_create_table<K: RedbKey + ?Sized, V: RedbValue + ?Sized>(
@@ -399,6 +765,10 @@ impl<'txn> TableTree<'txn> {
 
         let table = InternalTableDefinition {
             table_root: None,
+            blob_root: None,
+            changelog_root: None,
+            changelog_mode: ChangelogMode::Disabled,
+            out_of_line_values: false,
             table_type,
             fixed_key_size: K::fixed_width(),
             fixed_value_size: V::fixed_width(),
@@ -422,12 +792,13 @@ impl<'txn> TableTree<'txn> {
             master_tree_stats.metadata_bytes + master_tree_stats.stored_leaf_bytes;
         let mut total_fragmented = master_tree_stats.fragmented_bytes;
 
-        let mut iter = self.tree.range::<RangeFull, &str>(..)?;
-        while let Some(entry) = iter.next() {
+        let iter = self.tree.range::<RangeFull, &str>(..)?;
+        for entry in iter {
             let mut definition = InternalTableDefinition::from_bytes(entry.value());
-            if let Some(updated_root) = self.pending_table_updates.get(str::from_bytes(entry.key()))
+            if let Some(updated_roots) =
+                self.pending_table_updates.get(str::from_bytes(entry.key()))
             {
-                definition.table_root = *updated_root;
+                definition.set_roots(*updated_roots);
             }
             let subtree_stats = btree_stats(
                 definition.table_root.map(|(p, _)| p),
@@ -2,6 +2,7 @@ use std::cmp::Ordering;
 use std::convert::TryInto;
 use std::fmt::Debug;
 use std::marker::PhantomData;
+use std::mem::size_of;
 
 pub trait AsBytesWithLifetime<'a> {
     type Out: AsRef<[u8]>;
@@ -31,6 +32,14 @@ impl<'a, T: 'a + Debug> WithLifetime<'a> for OwnedLifetime<T> {
     type Out = T;
 }
 
+// The view of a tuple is a tuple of the views of its elements
+pub struct TupleLifetime<T>(PhantomData<T>);
+
+pub struct OptionLifetime<T: ?Sized>(PhantomData<T>);
+impl<'a, T: RedbValue + ?Sized> WithLifetime<'a> for OptionLifetime<T> {
+    type Out = Option<<<T as RedbValue>::View as WithLifetime<'a>>::Out>;
+}
+
 pub trait RedbValue: Debug {
     // TODO: need GATs, so that we can replace all this HRTB stuff
     type View: for<'a> WithLifetime<'a>;
@@ -187,3 +196,334 @@ be_impl!(i64);
 be_impl!(i128);
 be_value!(f32);
 be_value!(f64);
+
+// Floats are ordered by the IEEE 754 totalOrder predicate, the same as total_cmp(). That is:
+// -NaN < -Infinity < negative numbers < -0.0 < 0.0 < positive numbers < Infinity < NaN
+// TODO: use total_cmp() once the minimum supported Rust version is 1.62
+macro_rules! float_key {
+    ($t:ty, $bits:ty, $unsigned_bits:ty) => {
+        impl RedbKey for $t {
+            fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+                // Flip all the bits except the sign of negative numbers, so that they compare
+                // correctly as signed integers
+                let mut bits1 = <$bits>::from_le_bytes(data1.try_into().unwrap());
+                let mut bits2 = <$bits>::from_le_bytes(data2.try_into().unwrap());
+                bits1 ^= (((bits1 >> (<$bits>::BITS - 1)) as $unsigned_bits) >> 1) as $bits;
+                bits2 ^= (((bits2 >> (<$bits>::BITS - 1)) as $unsigned_bits) >> 1) as $bits;
+                bits1.cmp(&bits2)
+            }
+        }
+    };
+}
+
+float_key!(f32, i32, u32);
+float_key!(f64, i64, u64);
+
+impl RedbValue for bool {
+    type View = OwnedLifetime<bool>;
+    type ToBytes = OwnedAsBytesLifetime<[u8; 1]>;
+
+    fn fixed_width() -> Option<usize> {
+        Some(1)
+    }
+
+    fn from_bytes(data: &[u8]) -> <Self::View as WithLifetime>::Out {
+        match data[0] {
+            0 => false,
+            1 => true,
+            _ => unreachable!(),
+        }
+    }
+
+    fn as_bytes(&self) -> <Self::ToBytes as AsBytesWithLifetime>::Out {
+        [*self as u8]
+    }
+
+    fn redb_type_name() -> String {
+        "bool".to_string()
+    }
+}
+
+impl RedbKey for bool {
+    fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+        Self::from_bytes(data1).cmp(&Self::from_bytes(data2))
+    }
+}
+
+impl RedbValue for char {
+    type View = OwnedLifetime<char>;
+    type ToBytes = OwnedAsBytesLifetime<[u8; 4]>;
+
+    fn fixed_width() -> Option<usize> {
+        Some(size_of::<char>())
+    }
+
+    fn from_bytes(data: &[u8]) -> <Self::View as WithLifetime>::Out {
+        char::from_u32(u32::from_le_bytes(data.try_into().unwrap())).unwrap()
+    }
+
+    fn as_bytes(&self) -> <Self::ToBytes as AsBytesWithLifetime>::Out {
+        (*self as u32).to_le_bytes()
+    }
+
+    fn redb_type_name() -> String {
+        "char".to_string()
+    }
+}
+
+impl RedbKey for char {
+    fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+        Self::from_bytes(data1).cmp(&Self::from_bytes(data2))
+    }
+}
+
+impl RedbValue for () {
+    type View = OwnedLifetime<()>;
+    type ToBytes = OwnedAsBytesLifetime<[u8; 0]>;
+
+    fn fixed_width() -> Option<usize> {
+        Some(0)
+    }
+
+    fn from_bytes(_data: &[u8]) -> <Self::View as WithLifetime>::Out {}
+
+    fn as_bytes(&self) -> <Self::ToBytes as AsBytesWithLifetime>::Out {
+        []
+    }
+
+    fn redb_type_name() -> String {
+        "()".to_string()
+    }
+}
+
+impl RedbKey for () {
+    fn compare(_data1: &[u8], _data2: &[u8]) -> Ordering {
+        Ordering::Equal
+    }
+}
+
+impl RedbValue for String {
+    type View = OwnedLifetime<String>;
+    type ToBytes = RefAsBytesLifetime<str>;
+
+    fn fixed_width() -> Option<usize> {
+        None
+    }
+
+    fn from_bytes(data: &[u8]) -> <Self::View as WithLifetime>::Out {
+        str::from_bytes(data).to_string()
+    }
+
+    fn as_bytes(&self) -> <Self::ToBytes as AsBytesWithLifetime>::Out {
+        self.as_str()
+    }
+
+    fn redb_type_name() -> String {
+        "String".to_string()
+    }
+}
+
+impl RedbKey for String {
+    fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+        str::compare(data1, data2)
+    }
+}
+
+impl RedbValue for Vec<u8> {
+    type View = OwnedLifetime<Vec<u8>>;
+    type ToBytes = RefAsBytesLifetime<[u8]>;
+
+    fn fixed_width() -> Option<usize> {
+        None
+    }
+
+    fn from_bytes(data: &[u8]) -> <Self::View as WithLifetime>::Out {
+        data.to_vec()
+    }
+
+    fn as_bytes(&self) -> <Self::ToBytes as AsBytesWithLifetime>::Out {
+        self.as_slice()
+    }
+
+    fn redb_type_name() -> String {
+        "Vec<u8>".to_string()
+    }
+}
+
+impl RedbKey for Vec<u8> {
+    fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+        data1.cmp(data2)
+    }
+}
+
+// Serialized as a tag byte, which is 0 for None and 1 for Some, followed by the value.
+// If T is fixed width, None is padded with zeros to the same width
+impl<T: RedbValue> RedbValue for Option<T> {
+    type View = OptionLifetime<T>;
+    type ToBytes = OwnedAsBytesLifetime<Vec<u8>>;
+
+    fn fixed_width() -> Option<usize> {
+        T::fixed_width().map(|width| width + 1)
+    }
+
+    fn from_bytes(data: &[u8]) -> <Self::View as WithLifetime>::Out {
+        match data[0] {
+            0 => None,
+            1 => Some(T::from_bytes(&data[1..])),
+            _ => unreachable!(),
+        }
+    }
+
+    fn as_bytes(&self) -> <Self::ToBytes as AsBytesWithLifetime>::Out {
+        match self {
+            None => vec![0; Self::fixed_width().unwrap_or(1)],
+            Some(value) => {
+                let mut output = vec![1];
+                output.extend_from_slice(value.as_bytes().as_ref());
+                output
+            }
+        }
+    }
+
+    fn redb_type_name() -> String {
+        format!("Option<{}>", T::redb_type_name())
+    }
+}
+
+impl<T: RedbKey> RedbKey for Option<T> {
+    fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+        match (data1[0], data2[0]) {
+            (0, 0) => Ordering::Equal,
+            (0, _) => Ordering::Less,
+            (_, 0) => Ordering::Greater,
+            _ => T::compare(&data1[1..], &data2[1..]),
+        }
+    }
+}
+
+impl<T: RedbValue + ?Sized> RedbValue for &T {
+    type View = T::View;
+    type ToBytes = T::ToBytes;
+
+    fn fixed_width() -> Option<usize> {
+        T::fixed_width()
+    }
+
+    fn from_bytes(data: &[u8]) -> <Self::View as WithLifetime>::Out {
+        T::from_bytes(data)
+    }
+
+    fn as_bytes(&self) -> <Self::ToBytes as AsBytesWithLifetime>::Out {
+        T::as_bytes(self)
+    }
+
+    fn redb_type_name() -> String {
+        T::redb_type_name()
+    }
+}
+
+impl<T: RedbKey + ?Sized> RedbKey for &T {
+    fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+        T::compare(data1, data2)
+    }
+}
+
+// Appends an element to a serialized tuple. Variable width elements are prefixed with their length
+pub fn push_element<T: RedbValue>(output: &mut Vec<u8>, element: &T) {
+    let bytes = element.as_bytes();
+    let bytes = bytes.as_ref();
+    if T::fixed_width().is_none() {
+        output.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    }
+    output.extend_from_slice(bytes);
+}
+
+// Splits the first element off of a serialized tuple, and returns it along with the remaining elements
+pub fn split_element<T: RedbValue>(data: &[u8]) -> (&[u8], &[u8]) {
+    if let Some(width) = T::fixed_width() {
+        data.split_at(width)
+    } else {
+        let (len, data) = data.split_at(size_of::<u32>());
+        data.split_at(u32::from_le_bytes(len.try_into().unwrap()) as usize)
+    }
+}
+
+// Tuples are serialized as the concatenation of their elements, and compared lexicographically
+// using the comparison of each element type
+macro_rules! tuple_impl {
+    ($($name:ident $index:tt),+) => {
+        impl<'a, $($name: RedbValue),+> WithLifetime<'a> for TupleLifetime<($($name,)+)> {
+            type Out = ($(<<$name as RedbValue>::View as WithLifetime<'a>>::Out,)+);
+        }
+
+        impl<$($name: RedbValue),+> RedbValue for ($($name,)+) {
+            type View = TupleLifetime<($($name,)+)>;
+            type ToBytes = OwnedAsBytesLifetime<Vec<u8>>;
+
+            fn fixed_width() -> Option<usize> {
+                let mut width = 0;
+                $(
+                    width += $name::fixed_width()?;
+                )+
+                Some(width)
+            }
+
+            #[allow(unused_assignments)]
+            fn from_bytes(data: &[u8]) -> <Self::View as WithLifetime>::Out {
+                let mut remaining = data;
+                ($(
+                    {
+                        let (element, rest) = split_element::<$name>(remaining);
+                        remaining = rest;
+                        $name::from_bytes(element)
+                    },
+                )+)
+            }
+
+            fn as_bytes(&self) -> <Self::ToBytes as AsBytesWithLifetime>::Out {
+                let mut output = vec![];
+                $(
+                    push_element(&mut output, &self.$index);
+                )+
+                output
+            }
+
+            fn redb_type_name() -> String {
+                let names = [$($name::redb_type_name()),+];
+                format!("({})", names.join(","))
+            }
+        }
+
+        impl<$($name: RedbKey),+> RedbKey for ($($name,)+) {
+            #[allow(unused_assignments)]
+            fn compare(data1: &[u8], data2: &[u8]) -> Ordering {
+                let mut remaining1 = data1;
+                let mut remaining2 = data2;
+                $(
+                    let (element1, rest1) = split_element::<$name>(remaining1);
+                    let (element2, rest2) = split_element::<$name>(remaining2);
+                    match $name::compare(element1, element2) {
+                        Ordering::Equal => {}
+                        ordering => return ordering,
+                    }
+                    remaining1 = rest1;
+                    remaining2 = rest2;
+                )+
+                Ordering::Equal
+            }
+        }
+    };
+}
+
+tuple_impl!(T0 0);
+tuple_impl!(T0 0, T1 1);
+tuple_impl!(T0 0, T1 1, T2 2);
+tuple_impl!(T0 0, T1 1, T2 2, T3 3);
+tuple_impl!(T0 0, T1 1, T2 2, T3 3, T4 4);
+tuple_impl!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5);
+tuple_impl!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6);
+tuple_impl!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7);
+tuple_impl!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8);
+tuple_impl!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9);
+tuple_impl!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10);
+tuple_impl!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10, T11 11);
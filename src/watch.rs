@@ -0,0 +1,204 @@
+use crate::tree_store::{
+    range_differs, InternalTableDefinition, PageNumber, TableTree, TableType, TransactionalMemory,
+};
+use crate::types::{RedbKey, RedbValue};
+use crate::{Database, ReadTransaction, Result, TableDefinition};
+use std::borrow::Borrow;
+use std::ops::{Bound, RangeBounds};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::time::Duration;
+
+// Shared between a Watch and the Watcher registered with the database
+struct WatchState {
+    triggered: Mutex<bool>,
+    triggered_condvar: Condvar,
+}
+
+// Returns true if the entries in the range differ between the two trees
+type RangeChangedFn = fn(
+    Bound<&[u8]>,
+    Bound<&[u8]>,
+    Option<PageNumber>,
+    Option<PageNumber>,
+    &TransactionalMemory,
+) -> bool;
+
+fn owned_bound<T: AsRef<[u8]>>(bound: Bound<T>) -> Bound<Vec<u8>> {
+    match bound {
+        Bound::Included(x) => Bound::Included(x.as_ref().to_vec()),
+        Bound::Excluded(x) => Bound::Excluded(x.as_ref().to_vec()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+fn slice_bound(bound: &Bound<Vec<u8>>) -> Bound<&[u8]> {
+    match bound {
+        Bound::Included(x) => Bound::Included(x),
+        Bound::Excluded(x) => Bound::Excluded(x),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+// A range of a table, which is checked for changes after each commit
+pub(crate) struct Watcher {
+    table: String,
+    key_type: String,
+    value_type: String,
+    start: Bound<Vec<u8>>,
+    end: Bound<Vec<u8>>,
+    range_changed: RangeChangedFn,
+    state: Weak<WatchState>,
+}
+
+impl Watcher {
+    // Returns true if the watched range differs between the two master tables. A table which does
+    // not exist is treated as empty
+    fn check(&self, old: &TableTree, new: &TableTree, mem: &TransactionalMemory) -> Result<bool> {
+        let old = old.get_definition(&self.table)?;
+        let new = new.get_definition(&self.table)?;
+        let matches_type = |definition: &Option<InternalTableDefinition>| {
+            definition.as_ref().map_or(true, |definition| {
+                definition.get_type() == TableType::Normal
+                    && definition.get_key_type() == self.key_type
+                    && definition.get_value_type() == self.value_type
+            })
+        };
+        let old_root = old.as_ref().and_then(|x| x.get_root());
+        let new_root = new.as_ref().and_then(|x| x.get_root());
+        if old_root == new_root {
+            Ok(false)
+        } else if matches_type(&old) && matches_type(&new) {
+            Ok((self.range_changed)(
+                slice_bound(&self.start),
+                slice_bound(&self.end),
+                old_root.map(|(p, _)| p),
+                new_root.map(|(p, _)| p),
+                mem,
+            ))
+        } else {
+            Ok(true)
+        }
+    }
+}
+
+// The watches to wake once a commit becomes visible
+pub(crate) struct PendingNotifications<'a> {
+    watchers: &'a Mutex<Vec<Watcher>>,
+    // The number of watchers which were checked. Watchers are only removed by committing
+    // transactions, so any after these were registered while the transaction committed
+    checked: usize,
+    triggered: Vec<Arc<WatchState>>,
+}
+
+impl<'a> PendingNotifications<'a> {
+    // Checks the watchers against a transaction which is about to commit. The pages of the master
+    // table rooted at old may be freed by the commit, so this must be called before it
+    pub(crate) fn new(
+        watchers: &'a Mutex<Vec<Watcher>>,
+        old: &TableTree,
+        new: &TableTree,
+        mem: &TransactionalMemory,
+    ) -> Result<Self> {
+        let mut registered = watchers.lock().unwrap();
+        registered.retain(|watcher| watcher.state.strong_count() > 0);
+        let mut triggered = vec![];
+        for watcher in registered.iter() {
+            if watcher.check(old, new, mem)? {
+                if let Some(state) = watcher.state.upgrade() {
+                    triggered.push(state);
+                }
+            }
+        }
+
+        Ok(Self {
+            watchers,
+            checked: registered.len(),
+            triggered,
+        })
+    }
+
+    // Wakes the watches. Must only be called after the transaction has committed
+    pub(crate) fn notify(self) {
+        // A watch which was registered while the transaction committed may have been created
+        // before the commit became visible, so it's woken without being checked
+        let registered: Vec<Arc<WatchState>> = self.watchers.lock().unwrap()[self.checked..]
+            .iter()
+            .filter_map(|watcher| watcher.state.upgrade())
+            .collect();
+        for state in self.triggered.into_iter().chain(registered) {
+            *state.triggered.lock().unwrap() = true;
+            state.triggered_condvar.notify_all();
+        }
+    }
+}
+
+// Creates the Watcher to register with the database, and the Watch to return to the user
+pub(crate) fn new_watch<
+    'db,
+    K: RedbKey + ?Sized,
+    V: RedbValue + ?Sized,
+    T: RangeBounds<KR>,
+    KR: Borrow<K>,
+>(
+    db: &'db Database,
+    definition: TableDefinition<K, V>,
+    range: T,
+) -> (Watcher, Watch<'db>) {
+    let start = match range.start_bound() {
+        Bound::Included(k) => Bound::Included(k.borrow().as_bytes()),
+        Bound::Excluded(k) => Bound::Excluded(k.borrow().as_bytes()),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(k) => Bound::Included(k.borrow().as_bytes()),
+        Bound::Excluded(k) => Bound::Excluded(k.borrow().as_bytes()),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+    let state = Arc::new(WatchState {
+        triggered: Mutex::new(false),
+        triggered_condvar: Condvar::new(),
+    });
+    let watcher = Watcher {
+        table: definition.name().to_string(),
+        key_type: K::redb_type_name(),
+        value_type: V::redb_type_name(),
+        start: owned_bound(start),
+        end: owned_bound(end),
+        range_changed: range_differs::<K, V>,
+        state: Arc::downgrade(&state),
+    };
+
+    (watcher, Watch { db, state })
+}
+
+/// A handle which waits for commits that modify a range of keys in a table
+///
+/// Created by [`Database::watch`]. Dropping it unregisters the watch
+pub struct Watch<'db> {
+    db: &'db Database,
+    state: Arc<WatchState>,
+}
+
+impl<'db> Watch<'db> {
+    /// Blocks until a transaction which modified the watched range has committed, or `timeout`
+    /// elapses
+    ///
+    /// Returns a [`ReadTransaction`] which includes the modification, or `None` if the timeout
+    /// elapsed. Commits made since the watch was created, or since the last call to `wait` returned
+    /// a transaction, are reported, so none are missed between calls. Multiple commits may be
+    /// reported by a single call
+    pub fn wait(&self, timeout: Duration) -> Result<Option<ReadTransaction<'db>>> {
+        let triggered = self.state.triggered.lock().unwrap();
+        let (mut triggered, _) = self
+            .state
+            .triggered_condvar
+            .wait_timeout_while(triggered, timeout, |triggered| !*triggered)
+            .unwrap();
+        if !*triggered {
+            return Ok(None);
+        }
+        *triggered = false;
+        // Commits set the flag after they become visible, so this includes all of them
+        self.db.begin_read().map(Some)
+    }
+}
@@ -0,0 +1,161 @@
+use crate::types::{RedbKey, RedbValue};
+use crate::{Result, TableDefinition, WriteTransaction};
+use std::collections::BTreeMap;
+
+// A serialized key, and the value to insert for it, or None to remove it
+type Write = (Vec<u8>, Option<Vec<u8>>);
+
+// Applies the writes to the named table, which is opened with the types that they were serialized
+// from. The writes are sorted by key first
+type ApplyWritesFn = fn(&WriteTransaction, &str, Vec<Write>) -> Result;
+type DeleteTableFn = fn(&WriteTransaction, &str) -> Result<bool>;
+// Writes which have not been applied yet, grouped by table name, key type, and value type
+type PendingWrites = BTreeMap<(String, String, String), (ApplyWritesFn, Vec<Write>)>;
+
+fn apply_writes<K: RedbKey + ?Sized, V: RedbValue + ?Sized>(
+    txn: &WriteTransaction,
+    name: &str,
+    mut writes: Vec<Write>,
+) -> Result {
+    // The sort is stable, so writes to the same key remain in the order they were made
+    writes.sort_by(|(key1, _), (key2, _)| K::compare(key1, key2));
+    let mut table = txn.open_table(TableDefinition::<K, V>::new(name))?;
+    for (key, value) in writes {
+        if let Some(value) = value {
+            table.insert_serialized(&key, &value)?;
+        } else {
+            table.remove_serialized(&key)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn delete_table<K: RedbKey + ?Sized, V: RedbValue + ?Sized>(
+    txn: &WriteTransaction,
+    name: &str,
+) -> Result<bool> {
+    txn.delete_table(TableDefinition::<K, V>::new(name))
+}
+
+enum Operation {
+    Write {
+        table: String,
+        key_type: String,
+        value_type: String,
+        apply: ApplyWritesFn,
+        write: Write,
+    },
+    DeleteTable {
+        table: String,
+        delete: DeleteTableFn,
+    },
+}
+
+/// A set of writes, which is built without a transaction and applied atomically by
+/// [`Database::apply`](crate::Database::apply)
+///
+/// Keys and values are serialized when they are added, so a batch can be built on any thread, and
+/// sent to the one which applies it
+#[derive(Default)]
+pub struct WriteBatch {
+    operations: Vec<Operation>,
+}
+
+impl WriteBatch {
+    /// Creates an empty batch
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Inserts the key-value pair into the given table, which is created if it does not exist
+    pub fn insert<K: RedbKey + ?Sized, V: RedbValue + ?Sized>(
+        &mut self,
+        definition: TableDefinition<K, V>,
+        key: &K,
+        value: &V,
+    ) {
+        self.push_write(definition, key, Some(value.as_bytes().as_ref().to_vec()));
+    }
+
+    /// Removes the key from the given table, which is created if it does not exist
+    pub fn remove<K: RedbKey + ?Sized, V: RedbValue + ?Sized>(
+        &mut self,
+        definition: TableDefinition<K, V>,
+        key: &K,
+    ) {
+        self.push_write(definition, key, None);
+    }
+
+    /// Deletes the given table, if it exists
+    pub fn delete_table<K: RedbKey + ?Sized, V: RedbValue + ?Sized>(
+        &mut self,
+        definition: TableDefinition<K, V>,
+    ) {
+        self.operations.push(Operation::DeleteTable {
+            table: definition.name().to_string(),
+            delete: delete_table::<K, V>,
+        });
+    }
+
+    /// Returns the number of operations in the batch
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    /// Returns true if the batch contains no operations
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    fn push_write<K: RedbKey + ?Sized, V: RedbValue + ?Sized>(
+        &mut self,
+        definition: TableDefinition<K, V>,
+        key: &K,
+        value: Option<Vec<u8>>,
+    ) {
+        self.operations.push(Operation::Write {
+            table: definition.name().to_string(),
+            key_type: K::redb_type_name(),
+            value_type: V::redb_type_name(),
+            apply: apply_writes::<K, V>,
+            write: (key.as_bytes().as_ref().to_vec(), value),
+        });
+    }
+
+    // Applies the operations to the transaction. Writes between table deletions are grouped by
+    // table, and applied in key order. Writes to different keys are independent, so this has the
+    // same result as applying the operations in the order they were added
+    pub(crate) fn apply_to(self, txn: &WriteTransaction) -> Result {
+        let mut pending = PendingWrites::new();
+        for operation in self.operations {
+            match operation {
+                Operation::Write {
+                    table,
+                    key_type,
+                    value_type,
+                    apply,
+                    write,
+                } => {
+                    pending
+                        .entry((table, key_type, value_type))
+                        .or_insert_with(|| (apply, vec![]))
+                        .1
+                        .push(write);
+                }
+                Operation::DeleteTable { table, delete } => {
+                    flush_writes(txn, &mut pending)?;
+                    delete(txn, &table)?;
+                }
+            }
+        }
+        flush_writes(txn, &mut pending)
+    }
+}
+
+fn flush_writes(txn: &WriteTransaction, pending: &mut PendingWrites) -> Result {
+    for ((table, _, _), (apply, writes)) in std::mem::take(pending) {
+        apply(txn, &table, writes)?;
+    }
+    Ok(())
+}
@@ -1,5 +1,8 @@
-use redb::{Database, MultimapTableDefinition, RangeIter, ReadableTable, TableDefinition};
-use std::ops::{Range, RangeFull};
+use redb::{
+    Database, Entry, Error, MultimapTableDefinition, RangeIter, ReadableTable, TableDefinition,
+};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::ops::{Bound, Range, RangeFull};
 use tempfile::NamedTempFile;
 
 const SLICE_TABLE: TableDefinition<[u8], [u8]> = TableDefinition::new("x");
@@ -235,38 +238,825 @@ fn insert_reserve() {
     assert_eq!(value, table.get(b"hello").unwrap().unwrap());
 }
 
+#[test]
+fn insert_reserve_write() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        let mut reserved = table.insert_reserve(b"hello", 10).unwrap();
+        assert_eq!(10, reserved.len());
+        reserved.write_all(b"hello").unwrap();
+        assert_eq!(5, reserved.remaining());
+        write!(reserved, "{}", 12345).unwrap();
+        assert_eq!(0, reserved.remaining());
+        assert_eq!(b"hello12345", reserved.as_ref());
+        let err = reserved.write_all(b"!").unwrap_err();
+        assert_eq!(ErrorKind::WriteZero, err.kind());
+        reserved.as_mut()[0] = b'j';
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(SLICE_TABLE).unwrap();
+    assert_eq!(b"jello12345", table.get(b"hello").unwrap().unwrap());
+}
+
+#[test]
+fn blob() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    // Use a small region size, to check that blobs can be larger than a region
+    let db = unsafe {
+        Database::builder()
+            .set_region_size(64 * 1024)
+            .create(tmpfile.path(), 20 * 1024 * 1024)
+            .unwrap()
+    };
+    let mut expected: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        table.insert(b"hello", b"world").unwrap();
+        let mut writer = table.insert_blob(b"hello").unwrap();
+        for chunk in expected.chunks(10_000) {
+            writer.write_all(chunk).unwrap();
+        }
+        assert_eq!(expected.len() as u64, writer.len());
+        writer.finish().unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(SLICE_TABLE).unwrap();
+    assert_eq!(b"world", table.get(b"hello").unwrap().unwrap());
+    assert!(table.get_blob(b"world").unwrap().is_none());
+    let mut reader = table.get_blob(b"hello").unwrap().unwrap();
+    assert_eq!(expected.len() as u64, reader.len());
+    let mut buf = vec![];
+    reader.read_to_end(&mut buf).unwrap();
+    assert_eq!(expected, buf);
+    reader.seek(SeekFrom::Start(500_000)).unwrap();
+    let mut buf = [0u8; 100];
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(&expected[500_000..500_100], &buf);
+    drop(read_txn);
+
+    // Overwrite a range spanning two chunks, and append past the end
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        let mut writer = table.open_blob(b"hello").unwrap().unwrap();
+        writer.seek(SeekFrom::Start(65_530)).unwrap();
+        writer.write_all(b"overwritten").unwrap();
+        writer.seek(SeekFrom::End(10)).unwrap();
+        writer.write_all(b"tail").unwrap();
+        writer.finish().unwrap();
+        assert!(table.open_blob(b"world").unwrap().is_none());
+    }
+    write_txn.commit().unwrap();
+    expected[65_530..65_541].copy_from_slice(b"overwritten");
+    expected.extend_from_slice(&[0; 10]);
+    expected.extend_from_slice(b"tail");
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(SLICE_TABLE).unwrap();
+    let mut buf = vec![];
+    let mut reader = table.get_blob(b"hello").unwrap().unwrap();
+    reader.read_to_end(&mut buf).unwrap();
+    assert_eq!(expected, buf);
+    drop(read_txn);
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        assert!(table.remove_blob(b"hello").unwrap());
+        assert!(!table.remove_blob(b"hello").unwrap());
+        assert!(table.get_blob(b"hello").unwrap().is_none());
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(SLICE_TABLE).unwrap();
+    assert!(table.get_blob(b"hello").unwrap().is_none());
+    assert_eq!(b"world", table.get(b"hello").unwrap().unwrap());
+}
+
+#[test]
+fn out_of_line_values() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe {
+        Database::builder()
+            .set_max_inline_value_size(Some(64))
+            .create(tmpfile.path(), 16 * 1024 * 1024)
+            .unwrap()
+    };
+    let large = vec![7u8; 3000];
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        for i in 0..100u32 {
+            table.insert(&i.to_be_bytes(), &large).unwrap();
+        }
+        table.insert(b"small", b"value").unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    // The table's leaf only contains references to the large values. The other leaf holds the
+    // table definitions
+    assert_eq!(2, write_txn.stats().unwrap().leaf_pages());
+    assert!(write_txn.stats().unwrap().stored_bytes() > 100 * large.len());
+    {
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        assert_eq!(large, table.get(&5u32.to_be_bytes()).unwrap().unwrap());
+        assert_eq!(b"value", table.get(b"small").unwrap().unwrap());
+        let old = table.insert(&5u32.to_be_bytes(), b"now inline").unwrap();
+        assert_eq!(large, old.unwrap().to_value());
+        let old = table.insert(b"small", &[1u8; 100]).unwrap();
+        assert_eq!(b"value", old.unwrap().to_value());
+        assert!(!table
+            .compare_and_swap(&6u32.to_be_bytes(), Some(b"wrong"), b"new")
+            .unwrap());
+        assert!(table
+            .compare_and_swap(&6u32.to_be_bytes(), Some(&large), b"new")
+            .unwrap());
+        assert_eq!(
+            large,
+            table
+                .remove(&7u32.to_be_bytes())
+                .unwrap()
+                .unwrap()
+                .to_value()
+        );
+        let drained: Vec<Vec<u8>> = table
+            .drain(90u32.to_be_bytes().as_slice()..100u32.to_be_bytes().as_slice())
+            .unwrap()
+            .map(|(_, value)| value.to_value().to_vec())
+            .collect();
+        assert_eq!(10, drained.len());
+        assert!(drained.iter().all(|value| *value == large));
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(SLICE_TABLE).unwrap();
+    assert_eq!(90, table.len().unwrap());
+    assert_eq!(
+        b"now inline",
+        table.get(&5u32.to_be_bytes()).unwrap().unwrap()
+    );
+    assert_eq!(b"new", table.get(&6u32.to_be_bytes()).unwrap().unwrap());
+    assert_eq!([1u8; 100], table.get(b"small").unwrap().unwrap());
+    let mut iter = table.range::<RangeFull, &[u8]>(..).unwrap();
+    assert_eq!(large, iter.next().unwrap().1);
+    assert_eq!([1u8; 100], iter.next_back().unwrap().1);
+}
+
+#[test]
+fn out_of_line_reserved_values() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe {
+        Database::builder()
+            .set_max_inline_value_size(Some(64))
+            .create(tmpfile.path(), 16 * 1024 * 1024)
+            .unwrap()
+    };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        for i in 0..100u32 {
+            let mut reserved = table.insert_reserve(&i.to_be_bytes(), 3000).unwrap();
+            reserved.as_mut().fill(i as u8);
+        }
+        table
+            .insert_reserve(b"small", 5)
+            .unwrap()
+            .write_all(b"value")
+            .unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    // The table's leaf only contains references to the reserved values
+    assert_eq!(2, write_txn.stats().unwrap().leaf_pages());
+    write_txn.abort().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(SLICE_TABLE).unwrap();
+    for i in 0..100u32 {
+        assert_eq!(
+            vec![i as u8; 3000],
+            table.get(&i.to_be_bytes()).unwrap().unwrap()
+        );
+    }
+    assert_eq!(b"value", table.get(b"small").unwrap().unwrap());
+}
+
+#[test]
+fn out_of_line_values_clear() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe {
+        Database::builder()
+            .set_max_inline_value_size(Some(64))
+            .create(tmpfile.path(), 16 * 1024 * 1024)
+            .unwrap()
+    };
+    let write_txn = db.begin_write().unwrap();
+    write_txn.open_table(SLICE_TABLE).unwrap();
+    write_txn.commit().unwrap();
+    let write_txn = db.begin_write().unwrap();
+    let free_pages = write_txn.stats().unwrap().free_pages();
+    write_txn.abort().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        for i in 0..1000u32 {
+            table.insert(&i.to_be_bytes(), &[7u8; 100]).unwrap();
+        }
+    }
+    write_txn.commit().unwrap();
+    drop(db);
+
+    // The value pages are freed along with the leaves, even once out of line values are disabled
+    let db = unsafe { Database::create(tmpfile.path(), 16 * 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        table.clear().unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    // Extra commit to finalize the cleanup of the freed pages
+    let write_txn = db.begin_write().unwrap();
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    assert_eq!(free_pages, write_txn.stats().unwrap().free_pages());
+    write_txn.abort().unwrap();
+}
+
 #[test]
 fn delete() {
     let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
     let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
     let write_txn = db.begin_write().unwrap();
     {
-        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
-        table.insert(b"hello", b"world").unwrap();
-        table.insert(b"hello2", b"world").unwrap();
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        table.insert(b"hello", b"world").unwrap();
+        table.insert(b"hello2", b"world").unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(SLICE_TABLE).unwrap();
+    assert_eq!(b"world", table.get(b"hello").unwrap().unwrap());
+    assert_eq!(table.len().unwrap(), 2);
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        assert_eq!(
+            b"world",
+            table.remove(b"hello").unwrap().unwrap().to_value()
+        );
+        assert!(table.remove(b"hello").unwrap().is_none());
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(SLICE_TABLE).unwrap();
+    assert!(table.get(b"hello").unwrap().is_none());
+    assert_eq!(table.len().unwrap(), 1);
+}
+
+#[test]
+fn drain() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        for i in 0..10 {
+            table.insert(&i, &(i * 10)).unwrap();
+        }
+    }
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        let mut iter = table.drain(3..6).unwrap();
+        for i in 3..6 {
+            let (key, value) = iter.next().unwrap();
+            assert_eq!(i, key.to_value());
+            assert_eq!(i * 10, value.to_value());
+        }
+        assert!(iter.next().is_none());
+        drop(iter);
+        assert_eq!(table.len().unwrap(), 7);
+
+        let removed: Vec<u64> = table
+            .drain_filter(|key, _| key % 2 == 0)
+            .unwrap()
+            .map(|(key, _)| key.to_value())
+            .collect();
+        assert_eq!(removed, vec![0, 2, 6, 8]);
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(U64_TABLE).unwrap();
+    assert_eq!(table.len().unwrap(), 3);
+    let mut iter = table.range::<RangeFull, u64>(..).unwrap();
+    for i in [1, 7, 9] {
+        assert_eq!((i, i * 10), iter.next().unwrap());
+    }
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn delete_range() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        for i in 0..1000 {
+            table.insert(&i, &i).unwrap();
+        }
+        table.delete_range(10..=989).unwrap();
+        assert_eq!(table.len().unwrap(), 20);
+        table.delete_range(995..).unwrap();
+        assert_eq!(table.len().unwrap(), 15);
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(U64_TABLE).unwrap();
+    let mut iter = table.range::<RangeFull, u64>(..).unwrap();
+    for i in (0..10).chain(990..995) {
+        assert_eq!((i, i), iter.next().unwrap());
+    }
+    assert!(iter.next().is_none());
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        table.clear().unwrap();
+        assert!(table.is_empty().unwrap());
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(U64_TABLE).unwrap();
+    assert!(table.is_empty().unwrap());
+}
+
+#[test]
+fn retain() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        table.insert(b"hello", b"world").unwrap();
+        table.insert(b"hello2", b"world2").unwrap();
+        table.insert(b"hi", b"world").unwrap();
+        table
+            .retain(|key, value| key != b"hi" && value == b"world")
+            .unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(SLICE_TABLE).unwrap();
+    assert_eq!(table.len().unwrap(), 1);
+    assert_eq!(b"world", table.get(b"hello").unwrap().unwrap());
+}
+
+#[test]
+fn first_last() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        assert!(table.first().unwrap().is_none());
+        assert!(table.last().unwrap().is_none());
+        for i in (0..1000).rev() {
+            table.insert(&i, &(i + 1)).unwrap();
+        }
+        assert_eq!((0, 1), table.first().unwrap().unwrap());
+        assert_eq!((999, 1000), table.last().unwrap().unwrap());
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(U64_TABLE).unwrap();
+    assert_eq!((0, 1), table.first().unwrap().unwrap());
+    assert_eq!((999, 1000), table.last().unwrap().unwrap());
+}
+
+#[test]
+fn double_ended_iter() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        for i in 0..1000 {
+            table.insert(&i, &(i * 2)).unwrap();
+        }
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(U64_TABLE).unwrap();
+    let values: Vec<(u64, u64)> = table.range(10..20).unwrap().collect();
+    let expected: Vec<(u64, u64)> = (10..20).map(|i| (i, i * 2)).collect();
+    assert_eq!(expected, values);
+
+    let values: Vec<(u64, u64)> = table.range(10..20).unwrap().rev().collect();
+    let expected: Vec<(u64, u64)> = (10..20).rev().map(|i| (i, i * 2)).collect();
+    assert_eq!(expected, values);
+
+    // Alternate between the two ends until they meet
+    let mut iter = table.range::<RangeFull, u64>(..).unwrap();
+    for i in 0..500 {
+        assert_eq!(Some((i, i * 2)), iter.next());
+        assert_eq!(Some((999 - i, (999 - i) * 2)), iter.next_back());
+    }
+    assert!(iter.next().is_none());
+    assert!(iter.next_back().is_none());
+
+    let mut iter = table.range(3..=4).unwrap();
+    assert_eq!(Some((4, 8)), iter.next_back());
+    assert_eq!(Some((3, 6)), iter.next());
+    assert!(iter.next_back().is_none());
+    assert!(iter.next().is_none());
+
+    assert_eq!(
+        (0..1000).filter(|i| i % 3 == 0).sum::<u64>(),
+        table
+            .range::<RangeFull, u64>(..)
+            .unwrap()
+            .filter(|(k, _)| k % 3 == 0)
+            .map(|(k, _)| k)
+            .sum()
+    );
+}
+
+#[test]
+fn keys_values() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        for i in 0..10 {
+            table.insert(&i, &(i + 100)).unwrap();
+        }
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(U64_TABLE).unwrap();
+    let keys: Vec<u64> = table.range(2..5).unwrap().keys().collect();
+    assert_eq!(vec![2, 3, 4], keys);
+    let values: Vec<u64> = table.range(2..5).unwrap().values().rev().collect();
+    assert_eq!(vec![104, 103, 102], values);
+
+    let mut keys = table.range::<RangeFull, u64>(..).unwrap().keys();
+    assert_eq!(Some(9), keys.next_back());
+    assert_eq!(Some(0), keys.next());
+    assert_eq!(8, keys.count());
+}
+
+#[test]
+fn pop() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        table.insert(b"a", b"1").unwrap();
+        table.insert(b"b", b"2").unwrap();
+        table.insert(b"c", b"3").unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        {
+            let (key, value) = table.pop_first().unwrap().unwrap();
+            assert_eq!(b"a", key.to_value());
+            assert_eq!(b"1", value.to_value());
+        }
+        let (key, value) = table.pop_last().unwrap().unwrap();
+        assert_eq!(b"c", key.to_value());
+        assert_eq!(b"3", value.to_value());
+    }
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        assert_eq!(b"b", table.pop_last().unwrap().unwrap().0.to_value());
+        assert!(table.pop_first().unwrap().is_none());
+        assert!(table.pop_last().unwrap().is_none());
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(SLICE_TABLE).unwrap();
+    assert!(table.is_empty().unwrap());
+}
+
+#[test]
+fn floor_ceiling() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        assert!(table.floor(&0).unwrap().is_none());
+        assert!(table.ceiling(&0).unwrap().is_none());
+        for i in 1..1000 {
+            table.insert(&(i * 10), &i).unwrap();
+        }
+        assert_eq!((50, 5), table.floor(&50).unwrap().unwrap());
+        assert_eq!((50, 5), table.floor(&59).unwrap().unwrap());
+        assert_eq!((50, 5), table.ceiling(&50).unwrap().unwrap());
+        assert_eq!((60, 6), table.ceiling(&51).unwrap().unwrap());
+        assert_eq!((40, 4), table.predecessor(&50).unwrap().unwrap());
+        assert_eq!((60, 6), table.successor(&50).unwrap().unwrap());
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(U64_TABLE).unwrap();
+    assert!(table.floor(&9).unwrap().is_none());
+    assert_eq!((10, 1), table.floor(&10).unwrap().unwrap());
+    assert!(table.predecessor(&10).unwrap().is_none());
+    assert_eq!((10, 1), table.ceiling(&0).unwrap().unwrap());
+    assert_eq!((10, 1), table.successor(&0).unwrap().unwrap());
+    assert_eq!((9990, 999), table.floor(&100_000).unwrap().unwrap());
+    assert_eq!((9990, 999), table.predecessor(&100_000).unwrap().unwrap());
+    assert_eq!((9990, 999), table.ceiling(&9990).unwrap().unwrap());
+    assert!(table.ceiling(&9991).unwrap().is_none());
+    assert!(table.successor(&9990).unwrap().is_none());
+    for i in 2..999 {
+        let key = i * 10;
+        assert_eq!((key, i), table.floor(&(key + 5)).unwrap().unwrap());
+        assert_eq!(
+            (key + 10, i + 1),
+            table.ceiling(&(key + 5)).unwrap().unwrap()
+        );
+        assert_eq!((key - 10, i - 1), table.predecessor(&key).unwrap().unwrap());
+        assert_eq!((key + 10, i + 1), table.successor(&key).unwrap().unwrap());
+    }
+}
+
+#[test]
+fn rank_and_index() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        assert_eq!(0, table.rank(&0).unwrap());
+        assert!(table.get_by_index(0).unwrap().is_none());
+        for i in 0..1000 {
+            table.insert(&(i * 10), &i).unwrap();
+        }
+        assert_eq!(1000, table.len().unwrap());
+        table.delete_range(100..200).unwrap();
+        assert_eq!(990, table.len().unwrap());
+        assert_eq!((200, 20), table.get_by_index(10).unwrap().unwrap());
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(U64_TABLE).unwrap();
+    assert_eq!(990, table.len().unwrap());
+    assert_eq!(0, table.rank(&0).unwrap());
+    assert_eq!(1, table.rank(&5).unwrap());
+    assert_eq!(10, table.rank(&150).unwrap());
+    assert_eq!(990, table.rank(&100_000).unwrap());
+    assert!(table.get_by_index(990).unwrap().is_none());
+    for i in (0..990).step_by(7) {
+        let (key, _) = table.get_by_index(i).unwrap().unwrap();
+        assert_eq!(i, table.rank(&key).unwrap());
+    }
+
+    assert_eq!(990, table.range_len::<RangeFull, u64>(..).unwrap());
+    assert_eq!(10, table.range_len(0..100).unwrap());
+    assert_eq!(11, table.range_len(0..=200).unwrap());
+    assert_eq!(0, table.range_len(100..200).unwrap());
+    assert_eq!(1, table.range_len(95..=205).unwrap());
+    assert_eq!(
+        0,
+        table
+            .range_len((Bound::Included(500), Bound::Excluded(400)))
+            .unwrap()
+    );
+    assert_eq!(
+        979,
+        table
+            .range_len((Bound::Excluded(200), Bound::Unbounded))
+            .unwrap()
+    );
+}
+
+#[test]
+fn entry() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        for _ in 0..3 {
+            let count = table
+                .entry(&1)
+                .unwrap()
+                .and_modify(|entry| {
+                    let count = entry.get()?;
+                    entry.insert(&(count + 1))?;
+                    Ok(())
+                })
+                .unwrap()
+                .or_insert(&1)
+                .unwrap();
+            assert!(count.to_value() <= 3);
+        }
+        assert_eq!(3, table.get(&1).unwrap().unwrap());
+
+        match table.entry(&2).unwrap() {
+            Entry::Occupied(_) => unreachable!(),
+            Entry::Vacant(entry) => {
+                assert_eq!(2, *entry.key());
+                assert_eq!(20, entry.insert(&20).unwrap().to_value());
+            }
+        }
+        assert_eq!(
+            20,
+            table.entry(&2).unwrap().or_insert(&0).unwrap().to_value()
+        );
+    }
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        match table.entry(&1).unwrap() {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(3, entry.insert(&4).unwrap().to_value());
+                assert_eq!(4, entry.get().unwrap());
+                assert_eq!(4, entry.remove().unwrap().to_value());
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert!(table.get(&1).unwrap().is_none());
+        assert_eq!(1, table.len().unwrap());
+    }
+    write_txn.commit().unwrap();
+}
+
+#[test]
+fn compare_and_swap() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        assert!(!table.compare_and_swap(&1, Some(&0), &1).unwrap());
+        assert!(table.is_empty().unwrap());
+        assert!(table.insert_if_absent(&1, &1).unwrap());
+        assert!(!table.insert_if_absent(&1, &2).unwrap());
+        assert_eq!(1, table.get(&1).unwrap().unwrap());
+        for i in 2..1000 {
+            assert!(table.insert_if_absent(&i, &i).unwrap());
+        }
+    }
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        assert!(!table.compare_and_swap(&50, Some(&49), &0).unwrap());
+        assert!(table.compare_and_swap(&50, Some(&50), &0).unwrap());
+        assert!(!table.compare_and_swap(&50, Some(&50), &1).unwrap());
+        assert_eq!(0, table.get(&50).unwrap().unwrap());
+        assert!(!table.compare_and_swap(&50, None, &1).unwrap());
+        assert!(table.compare_and_swap(&1000, None, &1000).unwrap());
+        assert_eq!(1000, table.len().unwrap());
     }
     write_txn.commit().unwrap();
 
     let read_txn = db.begin_read().unwrap();
-    let table = read_txn.open_table(SLICE_TABLE).unwrap();
-    assert_eq!(b"world", table.get(b"hello").unwrap().unwrap());
-    assert_eq!(table.len().unwrap(), 2);
+    let table = read_txn.open_table(U64_TABLE).unwrap();
+    assert_eq!(0, table.get(&50).unwrap().unwrap());
+    assert_eq!(1000, table.get(&1000).unwrap().unwrap());
+}
 
+#[test]
+fn cursor() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
     let write_txn = db.begin_write().unwrap();
     {
-        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
-        assert_eq!(
-            b"world",
-            table.remove(b"hello").unwrap().unwrap().to_value()
-        );
-        assert!(table.remove(b"hello").unwrap().is_none());
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        let mut cursor = table.cursor().unwrap();
+        assert!(cursor.seek_first().is_none());
+        assert!(cursor.next().is_none());
+        for i in 0..1000 {
+            table.insert(&(i * 2), &i).unwrap();
+        }
     }
     write_txn.commit().unwrap();
 
     let read_txn = db.begin_read().unwrap();
-    let table = read_txn.open_table(SLICE_TABLE).unwrap();
-    assert!(table.get(b"hello").unwrap().is_none());
-    assert_eq!(table.len().unwrap(), 1);
+    let table = read_txn.open_table(U64_TABLE).unwrap();
+    let mut cursor = table.cursor().unwrap();
+    assert!(cursor.current().is_none());
+    assert_eq!(Some((0, 0)), cursor.next());
+    assert!(cursor.prev().is_none());
+    assert!(cursor.current().is_none());
+    assert_eq!(Some((1998, 999)), cursor.prev());
+
+    assert_eq!(Some((500, 250)), cursor.seek(&500));
+    assert_eq!(Some((502, 251)), cursor.seek(&501));
+    assert_eq!(Some((500, 250)), cursor.prev());
+    assert_eq!(Some((502, 251)), cursor.next());
+    assert_eq!(Some((502, 251)), cursor.current());
+    assert!(cursor.seek(&1999).is_none());
+    assert_eq!(Some((0, 0)), cursor.seek_first());
+    assert_eq!(Some((1998, 999)), cursor.seek_last());
+
+    cursor.seek_first();
+    for i in 1..1000 {
+        assert_eq!(Some((i * 2, i)), cursor.next());
+    }
+    assert!(cursor.next().is_none());
+    cursor.seek_last();
+    for i in (0..999).rev() {
+        assert_eq!(Some((i * 2, i)), cursor.prev());
+    }
+    assert!(cursor.prev().is_none());
+}
+
+#[test]
+fn cursor_mut() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        for i in 0..1000 {
+            table.insert(&i, &i).unwrap();
+        }
+    }
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        let mut cursor = table.cursor_mut();
+        assert!(cursor.delete_current().unwrap().is_none());
+        assert!(cursor.update_current(&0).unwrap().is_none());
+
+        cursor.seek_first();
+        while let Some((key, _)) = cursor.current() {
+            if key % 2 == 0 {
+                let removed = cursor.delete_current().unwrap().unwrap();
+                assert_eq!(key, removed.to_value());
+            } else {
+                let old = cursor.update_current(&(key * 10)).unwrap().unwrap();
+                assert_eq!(key, old.to_value());
+                drop(old);
+                assert_eq!(Some((key, key * 10)), cursor.current());
+                cursor.next();
+            }
+        }
+
+        assert_eq!(Some((999, 9990)), cursor.seek_last());
+        cursor.delete_current().unwrap();
+        assert!(cursor.current().is_none());
+        assert_eq!(Some((997, 9970)), cursor.prev());
+
+        assert_eq!(499, table.len().unwrap());
+        for i in 0..998 {
+            if i % 2 == 0 {
+                assert!(table.get(&i).unwrap().is_none());
+            } else {
+                assert_eq!(i * 10, table.get(&i).unwrap().unwrap());
+            }
+        }
+    }
+    write_txn.commit().unwrap();
 }
 
 #[test]
@@ -403,6 +1193,153 @@ fn f32_type() {
     assert_eq!(0.3, table.get(&0).unwrap().unwrap());
 }
 
+#[test]
+fn f64_key_type() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+
+    let definition: TableDefinition<f64, u8> = TableDefinition::new("x");
+
+    let keys = [
+        f64::NAN,
+        1.5,
+        -0.0,
+        f64::NEG_INFINITY,
+        0.0,
+        -2.5,
+        f64::INFINITY,
+        f64::MIN_POSITIVE,
+    ];
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        for (i, key) in keys.iter().enumerate() {
+            table.insert(key, &(i as u8)).unwrap();
+        }
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(definition).unwrap();
+    assert_eq!(1, table.get(&1.5).unwrap().unwrap());
+    let values: Vec<u8> = table
+        .range::<RangeFull, f64>(..)
+        .unwrap()
+        .map(|(_, value)| value)
+        .collect();
+    assert_eq!(vec![3, 5, 2, 4, 7, 1, 6, 0], values);
+    let values: Vec<u8> = table
+        .range(-1.0..f64::INFINITY)
+        .unwrap()
+        .map(|(_, value)| value)
+        .collect();
+    assert_eq!(vec![2, 4, 7, 1], values);
+}
+
+#[test]
+fn bool_char_unit_types() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+
+    let bools: TableDefinition<bool, char> = TableDefinition::new("bools");
+    // A set of chars
+    let chars: TableDefinition<char, ()> = TableDefinition::new("chars");
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(bools).unwrap();
+        table.insert(&true, &'t').unwrap();
+        table.insert(&false, &'f').unwrap();
+        let mut table = write_txn.open_table(chars).unwrap();
+        for c in "hello, wörld".chars() {
+            table.insert(&c, &()).unwrap();
+        }
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(bools).unwrap();
+    assert_eq!('t', table.get(&true).unwrap().unwrap());
+    let mut iter = table.range::<RangeFull, bool>(..).unwrap();
+    assert_eq!((false, 'f'), iter.next().unwrap());
+    assert_eq!((true, 't'), iter.next().unwrap());
+    assert!(iter.next().is_none());
+
+    let table = read_txn.open_table(chars).unwrap();
+    assert_eq!(10, table.len().unwrap());
+    assert!(table.get(&'ö').unwrap().is_some());
+    assert!(table.get(&'x').unwrap().is_none());
+    let chars: String = table
+        .range::<RangeFull, char>(..)
+        .unwrap()
+        .map(|(c, _)| c)
+        .collect();
+    assert_eq!(" ,dehlorwö", chars);
+}
+
+#[test]
+fn owned_types() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+
+    let definition: TableDefinition<String, Vec<u8>> = TableDefinition::new("x");
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        table
+            .insert(&"hello".to_string(), &b"world".to_vec())
+            .unwrap();
+        table.insert(&"a".to_string(), &vec![]).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(definition).unwrap();
+    let value: Vec<u8> = table.get(&"hello".to_string()).unwrap().unwrap();
+    assert_eq!(b"world".to_vec(), value);
+    let mut iter = table.range::<RangeFull, String>(..).unwrap();
+    assert_eq!(("a".to_string(), vec![]), iter.next().unwrap());
+
+    // The types are part of the table definition
+    let str_definition: TableDefinition<str, [u8]> = TableDefinition::new("x");
+    assert!(matches!(
+        read_txn.open_table(str_definition),
+        Err(Error::TableTypeMismatch(_))
+    ));
+}
+
+#[test]
+fn option_type() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+
+    let definition: TableDefinition<Option<i32>, Option<&str>> = TableDefinition::new("x");
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        table.insert(&Some(5), &Some("five")).unwrap();
+        table.insert(&None, &Some("none")).unwrap();
+        table.insert(&Some(-1), &None).unwrap();
+        table.insert(&Some(0), &Some("")).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(definition).unwrap();
+    assert_eq!(Some("none"), table.get(&None).unwrap().unwrap());
+    assert_eq!(None, table.get(&Some(-1)).unwrap().unwrap());
+    assert_eq!(Some(""), table.get(&Some(0)).unwrap().unwrap());
+    assert!(table.get(&Some(1)).unwrap().is_none());
+    let keys: Vec<Option<i32>> = table
+        .range::<RangeFull, Option<i32>>(..)
+        .unwrap()
+        .map(|(key, _)| key)
+        .collect();
+    assert_eq!(vec![None, Some(-1), Some(0), Some(5)], keys);
+}
+
 #[test]
 fn str_type() {
     let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
@@ -459,6 +1396,64 @@ fn array_type() {
     assert!(iter.next().is_none());
 }
 
+#[test]
+fn tuple_type() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+
+    let definition: TableDefinition<(u64, i64, &str), (u8, &[u8])> = TableDefinition::new("x");
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        table.insert(&(1, 5, "b"), &(1, b"one".as_slice())).unwrap();
+        table.insert(&(1, -5, "c"), &(2, b"two")).unwrap();
+        table.insert(&(1, 5, "a"), &(3, b"three")).unwrap();
+        table.insert(&(2, i64::MIN, ""), &(4, b"four")).unwrap();
+        table.insert(&(0, i64::MAX, "z"), &(5, b"five")).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(definition).unwrap();
+    assert_eq!(
+        (3, b"three".as_slice()),
+        table.get(&(1, 5, "a")).unwrap().unwrap()
+    );
+    assert!(table.get(&(1, 5, "")).unwrap().is_none());
+
+    // Elements are compared in order, using their own comparison
+    let keys: Vec<(u64, i64, &str)> = table
+        .range::<RangeFull, (u64, i64, &str)>(..)
+        .unwrap()
+        .map(|(key, _)| key)
+        .collect();
+    assert_eq!(
+        vec![
+            (0, i64::MAX, "z"),
+            (1, -5, "c"),
+            (1, 5, "a"),
+            (1, 5, "b"),
+            (2, i64::MIN, ""),
+        ],
+        keys
+    );
+
+    // Query all the keys with a given prefix
+    let values: Vec<u8> = table
+        .range((1, i64::MIN, "")..(2, i64::MIN, ""))
+        .unwrap()
+        .map(|(_, (value, _))| value)
+        .collect();
+    assert_eq!(vec![2, 3, 1], values);
+    let values: Vec<u8> = table
+        .range((1, 5, "")..(1, 6, ""))
+        .unwrap()
+        .map(|(_, (value, _))| value)
+        .collect();
+    assert_eq!(vec![3, 1], values);
+}
+
 #[test]
 fn owned_get_signatures() {
     let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
@@ -544,3 +1539,233 @@ fn ref_get_signatures() {
     }
     assert!(iter.next().is_none());
 }
+
+#[test]
+fn migrate_table() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 16 * 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        for i in 0..1000 {
+            table.insert(&i, &(i * 2)).unwrap();
+        }
+    }
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let _table = write_txn.open_table(U64_TABLE).unwrap();
+        assert!(matches!(
+            write_txn.migrate_table::<u64, u64, String, u64, _>("u64", |k, v| (k.to_string(), v)),
+            Err(Error::TableAlreadyOpen(_, _))
+        ));
+    }
+    assert!(matches!(
+        write_txn
+            .migrate_table::<u64, u32, String, u64, _>("u64", |k, v| (k.to_string(), v.into())),
+        Err(Error::TableTypeMismatch(_))
+    ));
+    assert!(matches!(
+        write_txn.migrate_table::<u64, u64, String, u64, _>("missing", |k, v| (k.to_string(), v)),
+        Err(Error::TableDoesNotExist(_))
+    ));
+    write_txn
+        .migrate_table::<u64, u64, String, u64, _>("u64", |k, v| (format!("{:04}", k), v + 1))
+        .unwrap();
+    write_txn.commit().unwrap();
+
+    let definition: TableDefinition<String, u64> = TableDefinition::new("u64");
+    let read_txn = db.begin_read().unwrap();
+    assert!(read_txn.open_table(U64_TABLE).is_err());
+    let table = read_txn.open_table(definition).unwrap();
+    assert_eq!(1000, table.len().unwrap());
+    assert_eq!(1, table.get(&"0000".to_string()).unwrap().unwrap());
+    assert_eq!(1999, table.get(&"0999".to_string()).unwrap().unwrap());
+    let first = table.first().unwrap().unwrap();
+    assert_eq!(("0000".to_string(), 1), first);
+}
+
+#[test]
+fn migrate_table_with_changelog() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 16 * 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        table.insert(&1, &1).unwrap();
+    }
+    write_txn.enable_changelog("u64", true).unwrap();
+    {
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        table.insert(&2, &2).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    // The unconsumed changes are kept
+    let write_txn = db.begin_write().unwrap();
+    assert!(matches!(
+        write_txn.migrate_table::<u64, u64, String, u64, _>("u64", |k, v| (k.to_string(), v)),
+        Err(Error::TableTypeMismatch(_))
+    ));
+    write_txn.commit().unwrap();
+    let read_txn = db.begin_read().unwrap();
+    assert_eq!(1, read_txn.changes_since("u64", 0).unwrap().count());
+    drop(read_txn);
+
+    let write_txn = db.begin_write().unwrap();
+    write_txn.disable_changelog("u64").unwrap();
+    write_txn
+        .migrate_table::<u64, u64, String, u64, _>("u64", |k, v| (k.to_string(), v))
+        .unwrap();
+    write_txn.commit().unwrap();
+
+    let definition: TableDefinition<String, u64> = TableDefinition::new("u64");
+    let read_txn = db.begin_read().unwrap();
+    assert_eq!(2, read_txn.open_table(definition).unwrap().len().unwrap());
+}
+
+#[test]
+fn migrate_table_frees_pages() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe {
+        Database::builder()
+            .set_max_inline_value_size(Some(100))
+            .create(tmpfile.path(), 16 * 1024 * 1024)
+            .unwrap()
+    };
+    let definition: TableDefinition<u64, [u8]> = TableDefinition::new("x");
+    let migrated: TableDefinition<u64, Vec<u8>> = TableDefinition::new("x");
+    let write_txn = db.begin_write().unwrap();
+    let free_pages = write_txn.stats().unwrap().free_pages();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        for i in 0..100u64 {
+            table.insert(&i, &[i as u8; 1000]).unwrap();
+        }
+    }
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    write_txn
+        .migrate_table::<u64, [u8], u64, Vec<u8>, _>("x", |k, v| (k, v[..10].to_vec()))
+        .unwrap();
+    {
+        let table = write_txn.open_table(migrated).unwrap();
+        assert_eq!(vec![5; 10], table.get(&5).unwrap().unwrap());
+    }
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    write_txn.delete_table(migrated).unwrap();
+    write_txn.commit().unwrap();
+
+    // Extra commit to finalize the cleanup of the freed pages
+    let write_txn = db.begin_write().unwrap();
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    assert_eq!(free_pages, write_txn.stats().unwrap().free_pages());
+    write_txn.abort().unwrap();
+}
+
+#[test]
+fn retype_table() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 16 * 1024 * 1024).unwrap() };
+    let definition: TableDefinition<str, [u8]> = TableDefinition::new("x");
+    let retyped: TableDefinition<String, Vec<u8>> = TableDefinition::new("x");
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        table.insert("hello", b"world").unwrap();
+        table.insert("a", b"b").unwrap();
+        let mut table = write_txn.open_table(U64_TABLE).unwrap();
+        table.insert(&1, &1).unwrap();
+    }
+    assert!(matches!(
+        write_txn.retype_table::<u64, u64, u64, u32>("u64"),
+        Err(Error::TableTypeMismatch(_))
+    ));
+    write_txn
+        .retype_table::<str, [u8], String, Vec<u8>>("x")
+        .unwrap();
+    {
+        let mut table = write_txn.open_table(retyped).unwrap();
+        table
+            .insert(&"new".to_string(), &b"value".to_vec())
+            .unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    assert!(read_txn.open_table(definition).is_err());
+    let table = read_txn.open_table(retyped).unwrap();
+    let keys: Vec<String> = table
+        .range::<RangeFull, String>(..)
+        .unwrap()
+        .keys()
+        .collect();
+    assert_eq!(vec!["a", "hello", "new"], keys);
+    assert_eq!(
+        b"world".to_vec(),
+        table.get(&"hello".to_string()).unwrap().unwrap()
+    );
+}
+
+#[test]
+fn untyped_table() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe {
+        Database::builder()
+            .set_max_inline_value_size(Some(100))
+            .create(tmpfile.path(), 16 * 1024 * 1024)
+            .unwrap()
+    };
+    let definition: TableDefinition<u64, str> = TableDefinition::new("x");
+    let multimap: MultimapTableDefinition<u64, u64> = MultimapTableDefinition::new("multimap");
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        for i in 0..1000u64 {
+            table.insert(&i, &i.to_string()).unwrap();
+        }
+        table.insert(&1000, &"a".repeat(1000)).unwrap();
+        write_txn.open_table(U64_TABLE).unwrap();
+        write_txn.open_multimap_table(multimap).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_untyped_table("x").unwrap();
+    assert_eq!("u64", table.key_type());
+    assert_eq!("str", table.value_type());
+    assert_eq!(Some(8), table.fixed_key_size());
+    assert_eq!(None, table.fixed_value_size());
+    assert_eq!(1001, table.len().unwrap());
+
+    let mut iter = table.iter().unwrap();
+    for i in 0..1000u64 {
+        let (key, value) = iter.next().unwrap();
+        assert_eq!(&i.to_le_bytes(), key);
+        assert_eq!(i.to_string().as_bytes(), value);
+    }
+    let (key, value) = iter.next_back().unwrap();
+    assert_eq!(&1000u64.to_le_bytes(), key);
+    assert_eq!("a".repeat(1000).as_bytes(), value);
+    assert!(iter.next().is_none());
+    assert!(iter.next_back().is_none());
+
+    let table = read_txn.open_untyped_table("u64").unwrap();
+    assert!(table.is_empty().unwrap());
+    assert!(table.iter().unwrap().next().is_none());
+
+    assert!(matches!(
+        read_txn.open_untyped_table("multimap"),
+        Err(Error::TableTypeMismatch(_))
+    ));
+    assert!(matches!(
+        read_txn.open_untyped_table("missing"),
+        Err(Error::TableDoesNotExist(_))
+    ));
+}
@@ -1,12 +1,15 @@
+use std::collections::BTreeMap;
 use std::fs;
-use std::io::ErrorKind;
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::ops::{Bound, RangeFull};
+use std::time::Duration;
 use tempfile::NamedTempFile;
 
 use rand::prelude::SliceRandom;
 use rand::Rng;
 use redb::{
-    Database, DatabaseBuilder, Durability, Error, MultimapTableDefinition, ReadableTable,
-    TableDefinition,
+    Change, ChangeKind, Database, DatabaseBuilder, Durability, Error, MultimapTableDefinition,
+    ReadableTable, RedbValue, TableDefinition, WriteBatch, WriteTransaction,
 };
 
 const ELEMENTS: usize = 100;
@@ -200,227 +203,856 @@ fn free() {
 }
 
 #[test]
-fn large_values() {
+fn floor_ceiling_random() {
     let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe {
+        Database::builder()
+            .set_page_size(1024)
+            .create(tmpfile.path(), 16 * 1024 * 1024)
+            .unwrap()
+    };
 
-    let db_size = 50 * 1024 * 1024;
-    let db = unsafe { Database::create(tmpfile.path(), db_size).unwrap() };
+    let mut rng = rand::thread_rng();
+    let mut expected = BTreeMap::new();
     let txn = db.begin_write().unwrap();
-
-    let mut key = vec![0; 1024];
-    let value = vec![0; 2_000_000];
     {
         let mut table = txn.open_table(SLICE_TABLE).unwrap();
-        for i in 0..5 {
-            key[0] = i;
+        for i in 0..2000u32 {
+            let key_len = rng.gen_range(4..64);
+            let mut key = vec![0u8; key_len];
+            key[0..4].copy_from_slice(&(i * 2).to_be_bytes());
+            let value = vec![0u8; rng.gen_range(0..200)];
             table.insert(&key, &value).unwrap();
+            expected.insert(key, value);
+        }
+        // Remove some entries, so that the branch separators no longer match the leaves
+        for i in (0..2000u32).filter(|i| i % 3 != 0) {
+            let key = expected
+                .range((i * 2).to_be_bytes().to_vec()..)
+                .next()
+                .unwrap()
+                .0
+                .clone();
+            table.remove(&key).unwrap();
+            expected.remove(&key);
         }
     }
     txn.commit().unwrap();
 
-    let txn = db.begin_write().unwrap();
-    {
-        let mut table = txn.open_table(SLICE_TABLE).unwrap();
-        for i in 0..5 {
-            key[0] = i;
-            table.remove(&key).unwrap();
+    let txn = db.begin_read().unwrap();
+    let table = txn.open_table(SLICE_TABLE).unwrap();
+    let mut queries: Vec<Vec<u8>> = expected.keys().step_by(5).cloned().collect();
+    for _ in 0..500 {
+        queries.push(rng.gen_range(0..4001u32).to_be_bytes().to_vec());
+    }
+    for query in queries {
+        let floor = table.floor(&query).unwrap().map(|(k, _)| k.to_vec());
+        let ceiling = table.ceiling(&query).unwrap().map(|(k, _)| k.to_vec());
+        let predecessor = table.predecessor(&query).unwrap().map(|(k, _)| k.to_vec());
+        let successor = table.successor(&query).unwrap().map(|(k, _)| k.to_vec());
+        let key = |entry: Option<(&Vec<u8>, &Vec<u8>)>| entry.map(|(k, _)| k.clone());
+        assert_eq!(key(expected.range(..=query.clone()).next_back()), floor);
+        assert_eq!(key(expected.range(query.clone()..).next()), ceiling);
+        assert_eq!(
+            key(expected.range(..query.clone()).next_back()),
+            predecessor
+        );
+        let mut above = expected.range(query.clone()..);
+        let mut next = above.next();
+        if next.map(|(k, _)| k == &query).unwrap_or(false) {
+            next = above.next();
         }
+        assert_eq!(key(next), successor);
     }
-    txn.commit().unwrap();
 }
 
 #[test]
-fn large_keys() {
+fn rank_random() {
     let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe {
+        Database::builder()
+            .set_page_size(1024)
+            .create(tmpfile.path(), 16 * 1024 * 1024)
+            .unwrap()
+    };
 
-    let db_size = 1024_1024;
-    let db = unsafe { Database::create(tmpfile.path(), db_size).unwrap() };
-    let txn = db.begin_write().unwrap();
-
-    let mut key = vec![0; 1024];
-    let value = vec![0; 1];
-    {
-        let mut table = txn.open_table(SLICE_TABLE).unwrap();
-        for i in 0..100 {
-            key[0] = i;
-            table.insert(&key, &value).unwrap();
+    let mut rng = rand::thread_rng();
+    let mut expected = BTreeMap::new();
+    for round in 0..4 {
+        let txn = db.begin_write().unwrap();
+        {
+            let mut table = txn.open_table(SLICE_TABLE).unwrap();
+            for _ in 0..1000 {
+                let key = rng.gen_range(0..5000u32).to_be_bytes().to_vec();
+                let value = vec![0u8; rng.gen_range(0..200)];
+                table.insert(&key, &value).unwrap();
+                expected.insert(key, value);
+            }
+            for _ in 0..300 {
+                let key = rng.gen_range(0..5000u32).to_be_bytes().to_vec();
+                table.remove(&key).unwrap();
+                expected.remove(&key);
+            }
+            if round % 2 == 1 {
+                let modulus = rng.gen_range(2..5);
+                table.retain(|_, value| value.len() % modulus != 0).unwrap();
+                expected.retain(|_, value| value.len() % modulus != 0);
+            }
+            assert_eq!(expected.len(), table.len().unwrap());
+        }
+        txn.commit().unwrap();
+
+        let txn = db.begin_read().unwrap();
+        let table = txn.open_table(SLICE_TABLE).unwrap();
+        assert_eq!(expected.len(), table.len().unwrap());
+        for _ in 0..200 {
+            let start = rng.gen_range(0..5000u32).to_be_bytes().to_vec();
+            let end = rng.gen_range(0..5000u32).to_be_bytes().to_vec();
+            assert_eq!(
+                expected.range(..start.clone()).count(),
+                table.rank(&start).unwrap()
+            );
+            if start <= end {
+                assert_eq!(
+                    expected.range(start.clone()..end.clone()).count(),
+                    table.range_len(start.as_slice()..end.as_slice()).unwrap()
+                );
+            }
+        }
+        for (i, (key, value)) in expected.iter().enumerate().step_by(11) {
+            let (k, v) = table.get_by_index(i).unwrap().unwrap();
+            assert_eq!(key.as_slice(), k);
+            assert_eq!(value.as_slice(), v);
         }
+        assert!(table.get_by_index(expected.len()).unwrap().is_none());
     }
-    txn.commit().unwrap();
+}
 
-    let txn = db.begin_write().unwrap();
-    {
-        let mut table = txn.open_table(SLICE_TABLE).unwrap();
-        for i in 0..100 {
-            key[0] = i;
-            table.remove(&key).unwrap();
+#[test]
+fn blob_random_writes() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe {
+        Database::builder()
+            .set_page_size(1024)
+            .create(tmpfile.path(), 16 * 1024 * 1024)
+            .unwrap()
+    };
+
+    let mut rng = rand::thread_rng();
+    let mut expected: Vec<u8> = vec![];
+    for round in 0..20 {
+        let txn = db.begin_write().unwrap();
+        let mut modified = expected.clone();
+        {
+            let mut table = txn.open_table(SLICE_TABLE).unwrap();
+            // Periodically replace the blob, to check that the old one is freed
+            let mut writer = if round % 5 == 0 {
+                modified.clear();
+                table.insert_blob(b"blob").unwrap()
+            } else {
+                table.open_blob(b"blob").unwrap().unwrap()
+            };
+            for _ in 0..10 {
+                let start = rng.gen_range(0..(modified.len() + 200_000));
+                let data: Vec<u8> = (0..rng.gen_range(0..100_000)).map(|_| rng.gen()).collect();
+                writer.seek(SeekFrom::Start(start as u64)).unwrap();
+                writer.write_all(&data).unwrap();
+                if modified.len() < start + data.len() {
+                    modified.resize(start + data.len(), 0);
+                }
+                modified[start..(start + data.len())].copy_from_slice(&data);
+            }
+            assert_eq!(modified.len() as u64, writer.len());
+        }
+        if round % 3 == 2 {
+            txn.abort().unwrap();
+        } else {
+            txn.commit().unwrap();
+            expected = modified;
         }
+
+        let txn = db.begin_read().unwrap();
+        let table = txn.open_table(SLICE_TABLE).unwrap();
+        let mut reader = table.get_blob(b"blob").unwrap().unwrap();
+        let mut buf = vec![];
+        reader.read_to_end(&mut buf).unwrap();
+        assert_eq!(expected, buf);
     }
+
+    let txn = db.begin_write().unwrap();
+    assert!(txn.delete_table(SLICE_TABLE).unwrap());
     txn.commit().unwrap();
 }
 
 #[test]
-fn dynamic_growth() {
+fn out_of_line_values_random() {
     let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
-    let table_definition: TableDefinition<u64, [u8]> = TableDefinition::new("x");
-    let big_value = vec![0; 1024];
+    let db = unsafe {
+        Database::builder()
+            .set_page_size(1024)
+            .set_max_inline_value_size(Some(100))
+            .create(tmpfile.path(), 64 * 1024 * 1024)
+            .unwrap()
+    };
+    let txn = db.begin_write().unwrap();
+    txn.open_table(SLICE_TABLE).unwrap();
+    txn.commit().unwrap();
 
-    let db_size = 10 * 1024 * 1024;
-    let db = unsafe { Database::create(tmpfile.path(), db_size).unwrap() };
     let txn = db.begin_write().unwrap();
-    {
-        let mut table = txn.open_table(table_definition).unwrap();
-        table.insert(&0, &big_value).unwrap();
+    let free_pages = txn.stats().unwrap().free_pages();
+    txn.abort().unwrap();
+
+    let mut rng = rand::thread_rng();
+    let mut expected: BTreeMap<Vec<u8>, Vec<u8>> = BTreeMap::new();
+    for round in 0..30 {
+        let txn = db.begin_write().unwrap();
+        let mut modified = expected.clone();
+        {
+            let mut table = txn.open_table(SLICE_TABLE).unwrap();
+            for _ in 0..100 {
+                let key = rng.gen_range(0..300u32).to_be_bytes().to_vec();
+                if rng.gen_bool(0.3) {
+                    let removed = table.remove(&key).unwrap().map(|x| x.to_value().to_vec());
+                    assert_eq!(modified.remove(&key), removed);
+                } else {
+                    let value = vec![rng.gen(); rng.gen_range(0..3000)];
+                    let old = table.insert(&key, &value).unwrap();
+                    let old = old.map(|x| x.to_value().to_vec());
+                    assert_eq!(modified.insert(key, value), old);
+                }
+            }
+            if round % 4 == 3 {
+                table.retain(|_, value| value.len() % 2 == 0).unwrap();
+                modified.retain(|_, value| value.len() % 2 == 0);
+            }
+        }
+        if round % 3 == 2 {
+            txn.abort().unwrap();
+        } else {
+            txn.commit().unwrap();
+            expected = modified;
+        }
+
+        let txn = db.begin_read().unwrap();
+        let table = txn.open_table(SLICE_TABLE).unwrap();
+        let entries: Vec<(Vec<u8>, Vec<u8>)> = table
+            .range::<RangeFull, &[u8]>(..)
+            .unwrap()
+            .map(|(key, value)| (key.to_vec(), value.to_vec()))
+            .collect();
+        assert_eq!(expected.clone().into_iter().collect::<Vec<_>>(), entries);
+        for (key, value) in expected.iter() {
+            assert_eq!(value.as_slice(), table.get(key).unwrap().unwrap());
+        }
     }
+
+    // All the values' pages should be freed along with their entries
+    let txn = db.begin_write().unwrap();
+    txn.open_table(SLICE_TABLE).unwrap().clear().unwrap();
     txn.commit().unwrap();
+    let txn = db.begin_write().unwrap();
+    assert_eq!(free_pages, txn.stats().unwrap().free_pages());
+    txn.abort().unwrap();
+}
 
-    let initial_file_size = tmpfile.as_file().metadata().unwrap().len();
-    assert!(initial_file_size < (db_size / 2) as u64);
+#[test]
+fn watch_random() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe {
+        Database::builder()
+            .set_page_size(1024)
+            .create(tmpfile.path(), 16 * 1024 * 1024)
+            .unwrap()
+    };
 
+    let mut rng = rand::thread_rng();
+    let mut expected = BTreeMap::new();
     let txn = db.begin_write().unwrap();
     {
-        let mut table = txn.open_table(table_definition).unwrap();
-        for i in 0..2048 {
-            table.insert(&i, &big_value).unwrap();
+        let mut table = txn.open_table(U64_TABLE).unwrap();
+        for i in (0..4000u64).step_by(2) {
+            table.insert(&i, &i).unwrap();
+            expected.insert(i, i);
         }
     }
     txn.commit().unwrap();
 
-    let file_size = tmpfile.as_file().metadata().unwrap().len();
-
-    assert!(file_size > initial_file_size);
+    let ranges: Vec<(u64, u64)> = (0..20)
+        .map(|_| {
+            let start = rng.gen_range(0..4000u64);
+            (start, start + rng.gen_range(0..200u64))
+        })
+        .collect();
+    let watches: Vec<_> = ranges
+        .iter()
+        .map(|&(start, end)| {
+            (
+                db.watch(U64_TABLE, start..end),
+                db.watch(U64_TABLE, start..=end),
+            )
+        })
+        .collect();
+    for _ in 0..50 {
+        let before = expected.clone();
+        let txn = db.begin_write().unwrap();
+        {
+            let mut table = txn.open_table(U64_TABLE).unwrap();
+            for _ in 0..rng.gen_range(1..20) {
+                let key = rng.gen_range(0..4000u64);
+                match rng.gen_range(0..3) {
+                    0 => {
+                        table.remove(&key).unwrap();
+                        expected.remove(&key);
+                    }
+                    1 => {
+                        table.insert(&key, &key).unwrap();
+                        expected.insert(key, key);
+                    }
+                    _ => {
+                        let value = rng.gen();
+                        table.insert(&key, &value).unwrap();
+                        expected.insert(key, value);
+                    }
+                }
+            }
+        }
+        txn.commit().unwrap();
+
+        let changed =
+            |range: (Bound<&u64>, Bound<&u64>)| !before.range(range).eq(expected.range(range));
+        for (&(start, end), (exclusive, inclusive)) in ranges.iter().zip(watches.iter()) {
+            let woken = exclusive.wait(Duration::ZERO).unwrap().is_some();
+            assert_eq!(
+                changed((Bound::Included(&start), Bound::Excluded(&end))),
+                woken
+            );
+            let woken = inclusive.wait(Duration::ZERO).unwrap().is_some();
+            assert_eq!(
+                changed((Bound::Included(&start), Bound::Included(&end))),
+                woken
+            );
+        }
+    }
 }
 
 #[test]
-fn dynamic_shrink() {
+fn pop_random() {
     let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
-    let table_definition: TableDefinition<u64, [u8]> = TableDefinition::new("x");
-    let big_value = vec![0; 1024];
-
-    let db_size = 20 * 1024 * 1024;
     let db = unsafe {
         Database::builder()
-            .set_region_size(1024 * 1024)
-            .create(tmpfile.path(), db_size)
+            .set_page_size(1024)
+            .create(tmpfile.path(), 16 * 1024 * 1024)
             .unwrap()
     };
 
+    let mut rng = rand::thread_rng();
+    let mut expected = BTreeMap::new();
     let txn = db.begin_write().unwrap();
     {
-        let mut table = txn.open_table(table_definition).unwrap();
-        for i in 0..2048 {
-            table.insert(&i, &big_value).unwrap();
+        let mut table = txn.open_table(SLICE_TABLE).unwrap();
+        for i in 0..2000u32 {
+            let key_len = rng.gen_range(4..64);
+            let mut key = vec![0u8; key_len];
+            key[0..4].copy_from_slice(&i.to_be_bytes());
+            let value = vec![0u8; rng.gen_range(0..200)];
+            table.insert(&key, &value).unwrap();
+            expected.insert(key, value);
+        }
+
+        while !expected.is_empty() {
+            let (key, value) = if rng.gen() {
+                let (key, value) = table.pop_first().unwrap().unwrap();
+                assert_eq!(expected.keys().next().unwrap().as_slice(), key.to_value());
+                (key.to_value().to_vec(), value.to_value().to_vec())
+            } else {
+                let (key, value) = table.pop_last().unwrap().unwrap();
+                assert_eq!(expected.keys().last().unwrap().as_slice(), key.to_value());
+                (key.to_value().to_vec(), value.to_value().to_vec())
+            };
+            assert_eq!(expected.remove(&key).unwrap(), value);
+            if expected.len() % 100 == 0 {
+                assert_eq!(table.len().unwrap(), expected.len());
+            }
         }
+        assert!(table.pop_first().unwrap().is_none());
+        assert!(table.pop_last().unwrap().is_none());
     }
     txn.commit().unwrap();
+}
 
-    let file_size = tmpfile.as_file().metadata().unwrap().len();
+#[test]
+fn cursor_random() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe {
+        Database::builder()
+            .set_page_size(1024)
+            .create(tmpfile.path(), 16 * 1024 * 1024)
+            .unwrap()
+    };
 
+    let mut rng = rand::thread_rng();
+    let mut expected = BTreeMap::new();
     let txn = db.begin_write().unwrap();
     {
-        let mut table = txn.open_table(table_definition).unwrap();
-        for i in 0..2048 {
-            table.remove(&i).unwrap();
+        let mut table = txn.open_table(SLICE_TABLE).unwrap();
+        for i in 0..2000u32 {
+            let key_len = rng.gen_range(4..64);
+            let mut key = vec![0u8; key_len];
+            key[0..4].copy_from_slice(&(i * 2).to_be_bytes());
+            let value = vec![0u8; rng.gen_range(0..200)];
+            table.insert(&key, &value).unwrap();
+            expected.insert(key, value);
         }
     }
     txn.commit().unwrap();
 
-    // Perform a couple more commits to be sure the database has a chance to compact
-    let txn = db.begin_write().unwrap();
-    {
-        let mut table = txn.open_table(table_definition).unwrap();
-        table.insert(&0, &[]).unwrap();
-    }
-    txn.commit().unwrap();
     let txn = db.begin_write().unwrap();
     {
-        let mut table = txn.open_table(table_definition).unwrap();
-        table.remove(&0).unwrap();
+        let mut table = txn.open_table(SLICE_TABLE).unwrap();
+        let mut cursor = table.cursor_mut();
+        for _ in 0..2000 {
+            let query = rng.gen_range(0..4000u32).to_be_bytes();
+            let (key, value) = cursor.seek(&query).unwrap_or((&[], &[]));
+            let mut next = expected.range(query.to_vec()..);
+            match next.next() {
+                Some((expected_key, expected_value)) => {
+                    assert_eq!(expected_key.as_slice(), key);
+                    assert_eq!(expected_value.as_slice(), value);
+                }
+                None => {
+                    assert!(key.is_empty());
+                    continue;
+                }
+            }
+            let key = key.to_vec();
+            match rng.gen_range(0..4) {
+                0 => {
+                    cursor.delete_current().unwrap().unwrap();
+                    expected.remove(&key);
+                    let actual = cursor.current().map(|(k, _)| k.to_vec());
+                    let next_key = expected.range(key.clone()..).next().map(|(k, _)| k.clone());
+                    assert_eq!(next_key, actual);
+                }
+                1 => {
+                    let value = vec![1u8; rng.gen_range(0..200)];
+                    cursor.update_current(&value).unwrap().unwrap();
+                    expected.insert(key.clone(), value);
+                    assert_eq!(key.as_slice(), cursor.current().unwrap().0);
+                }
+                2 => {
+                    let actual = cursor.next().map(|(k, _)| k.to_vec());
+                    let mut range = expected.range(key.clone()..);
+                    range.next();
+                    assert_eq!(range.next().map(|(k, _)| k.clone()), actual);
+                }
+                _ => {
+                    let actual = cursor.prev().map(|(k, _)| k.to_vec());
+                    let previous = expected.range(..key.clone()).next_back();
+                    assert_eq!(previous.map(|(k, _)| k.clone()), actual);
+                }
+            }
+        }
+
+        let mut cursor = table.cursor().unwrap();
+        for (key, value) in expected.iter().rev() {
+            let (actual_key, actual_value) = cursor.prev().unwrap();
+            assert_eq!(key.as_slice(), actual_key);
+            assert_eq!(value.as_slice(), actual_value);
+        }
+        assert!(cursor.prev().is_none());
     }
     txn.commit().unwrap();
-
-    let final_file_size = tmpfile.as_file().metadata().unwrap().len();
-    assert!(final_file_size < file_size);
 }
 
 #[test]
-fn multi_page_kv() {
+fn drain_filter_random() {
     let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
-    let elements = 4;
-    let page_size = 4096;
-
-    let db_size = 1024_1024;
     let db = unsafe {
-        DatabaseBuilder::new()
-            .set_page_size(page_size)
-            .create(tmpfile.path(), db_size)
+        Database::builder()
+            .set_page_size(1024)
+            .create(tmpfile.path(), 16 * 1024 * 1024)
             .unwrap()
     };
     let txn = db.begin_write().unwrap();
+    {
+        let _table = txn.open_table(SLICE_TABLE).unwrap();
+    }
+    txn.commit().unwrap();
+    let txn = db.begin_write().unwrap();
+    let free_pages = txn.stats().unwrap().free_pages();
+    txn.abort().unwrap();
 
-    let mut key = vec![0; page_size + 1];
-    let mut value = vec![0; page_size + 1];
+    let mut rng = rand::thread_rng();
+    let mut expected = BTreeMap::new();
+    let txn = db.begin_write().unwrap();
     {
         let mut table = txn.open_table(SLICE_TABLE).unwrap();
-        for i in 0..elements {
-            key[0] = i;
-            value[0] = i;
+        for i in 0..2000u32 {
+            let key_len = rng.gen_range(4..64);
+            let mut key = vec![0u8; key_len];
+            key[0..4].copy_from_slice(&i.to_be_bytes());
+            let value = vec![0u8; rng.gen_range(0..200)];
             table.insert(&key, &value).unwrap();
+            expected.insert(key, value);
         }
     }
     txn.commit().unwrap();
 
-    let txn = db.begin_read().unwrap();
-    let table = txn.open_table(SLICE_TABLE).unwrap();
-    for i in 0..elements {
-        key[0] = i;
-        value[0] = i;
-        assert_eq!(&value, table.get(&key).unwrap().unwrap());
+    for round in 0..20u32 {
+        let txn = db.begin_write().unwrap();
+        {
+            let mut table = txn.open_table(SLICE_TABLE).unwrap();
+            let start = rng.gen_range(0..2000u32).to_be_bytes().to_vec();
+            let end = rng.gen_range(0..2000u32).to_be_bytes().to_vec();
+            if round % 2 == 0 {
+                let removed: Vec<Vec<u8>> = table
+                    .drain(start.as_slice()..end.as_slice())
+                    .unwrap()
+                    .map(|(key, _)| key.to_value().to_vec())
+                    .collect();
+                let expected_removed: Vec<Vec<u8>> = expected
+                    .range(start.clone()..end.clone().max(start.clone()))
+                    .map(|(key, _)| key.clone())
+                    .collect();
+                assert_eq!(removed, expected_removed);
+                for key in removed {
+                    expected.remove(&key);
+                }
+            } else {
+                let modulus = rng.gen_range(2..5);
+                table.retain(|key, _| key.len() % modulus != 0).unwrap();
+                expected.retain(|key, _| key.len() % modulus != 0);
+            }
+
+            assert_eq!(table.len().unwrap(), expected.len());
+            let mut iter = table.range::<RangeFull, &[u8]>(..).unwrap();
+            for (key, value) in expected.iter() {
+                let (actual_key, actual_value) = iter.next().unwrap();
+                assert_eq!(key.as_slice(), actual_key);
+                assert_eq!(value.as_slice(), actual_value);
+            }
+            assert!(iter.next().is_none());
+            for key in expected.keys().step_by(7) {
+                assert!(table.get(key).unwrap().is_some());
+            }
+        }
+        txn.commit().unwrap();
     }
 
     let txn = db.begin_write().unwrap();
     {
         let mut table = txn.open_table(SLICE_TABLE).unwrap();
-        for i in 0..elements {
-            key[0] = i;
-            table.remove(&key).unwrap();
-        }
+        table.retain(|_, _| false).unwrap();
+        assert!(table.is_empty().unwrap());
     }
     txn.commit().unwrap();
+    // Commit twice to be sure that the freed pages are reclaimed
+    let txn = db.begin_write().unwrap();
+    txn.commit().unwrap();
+
+    let txn = db.begin_write().unwrap();
+    assert_eq!(free_pages, txn.stats().unwrap().free_pages());
+    txn.abort().unwrap();
 }
 
 #[test]
-// Test for a bug in the deletion code, where deleting a key accidentally deleted other keys
-fn regression() {
+fn bulk_delete_random() {
     let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
-
-    let db_size = 1024 * 1024;
-    let db = unsafe { Database::create(tmpfile.path(), db_size).unwrap() };
+    let db = unsafe {
+        Database::builder()
+            .set_page_size(1024)
+            .create(tmpfile.path(), 16 * 1024 * 1024)
+            .unwrap()
+    };
     let txn = db.begin_write().unwrap();
     {
-        let mut table = txn.open_table(U64_TABLE).unwrap();
-        table.insert(&1, &1).unwrap();
+        let _table = txn.open_table(SLICE_TABLE).unwrap();
     }
     txn.commit().unwrap();
+    let txn = db.begin_write().unwrap();
+    let free_pages = txn.stats().unwrap().free_pages();
+    txn.abort().unwrap();
 
+    let mut rng = rand::thread_rng();
+    let mut expected = BTreeMap::new();
     let txn = db.begin_write().unwrap();
     {
-        let mut table = txn.open_table(U64_TABLE).unwrap();
-        table.insert(&6, &9).unwrap();
+        let mut table = txn.open_table(SLICE_TABLE).unwrap();
+        for i in 0..2000u32 {
+            let key_len = rng.gen_range(4..64);
+            let mut key = vec![0u8; key_len];
+            key[0..4].copy_from_slice(&i.to_be_bytes());
+            let value = vec![0u8; rng.gen_range(0..200)];
+            table.insert(&key, &value).unwrap();
+            expected.insert(key, value);
+        }
     }
     txn.commit().unwrap();
 
-    let txn = db.begin_write().unwrap();
-    {
-        let mut table = txn.open_table(U64_TABLE).unwrap();
-        table.insert(&12, &10).unwrap();
+    for _ in 0..20 {
+        let txn = db.begin_write().unwrap();
+        {
+            let mut table = txn.open_table(SLICE_TABLE).unwrap();
+            let start = rng.gen_range(0..2000u32).to_be_bytes().to_vec();
+            let end = rng.gen_range(0..2000u32).to_be_bytes().to_vec();
+            table
+                .delete_range(start.as_slice()..=end.as_slice())
+                .unwrap();
+            expected.retain(|key, _| key < &start || key > &end);
+
+            assert_eq!(table.len().unwrap(), expected.len());
+            let mut iter = table.range::<RangeFull, &[u8]>(..).unwrap();
+            for (key, value) in expected.iter() {
+                let (actual_key, actual_value) = iter.next().unwrap();
+                assert_eq!(key.as_slice(), actual_key);
+                assert_eq!(value.as_slice(), actual_value);
+            }
+            assert!(iter.next().is_none());
+            for key in expected.keys().step_by(7) {
+                assert!(table.get(key).unwrap().is_some());
+            }
+        }
+        txn.commit().unwrap();
     }
-    txn.commit().unwrap();
 
     let txn = db.begin_write().unwrap();
     {
-        let mut table = txn.open_table(U64_TABLE).unwrap();
-        table.insert(&18, &27).unwrap();
+        let mut table = txn.open_table(SLICE_TABLE).unwrap();
+        table.clear().unwrap();
+        assert!(table.is_empty().unwrap());
+    }
+    txn.commit().unwrap();
+    // Commit twice to be sure that the freed pages are reclaimed
+    let txn = db.begin_write().unwrap();
+    txn.commit().unwrap();
+
+    let txn = db.begin_write().unwrap();
+    assert_eq!(free_pages, txn.stats().unwrap().free_pages());
+    txn.abort().unwrap();
+}
+
+#[test]
+fn large_values() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+
+    let db_size = 50 * 1024 * 1024;
+    let db = unsafe { Database::create(tmpfile.path(), db_size).unwrap() };
+    let txn = db.begin_write().unwrap();
+
+    let mut key = vec![0; 1024];
+    let value = vec![0; 2_000_000];
+    {
+        let mut table = txn.open_table(SLICE_TABLE).unwrap();
+        for i in 0..5 {
+            key[0] = i;
+            table.insert(&key, &value).unwrap();
+        }
+    }
+    txn.commit().unwrap();
+
+    let txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(SLICE_TABLE).unwrap();
+        for i in 0..5 {
+            key[0] = i;
+            table.remove(&key).unwrap();
+        }
+    }
+    txn.commit().unwrap();
+}
+
+#[test]
+fn large_keys() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+
+    let db_size = 1024_1024;
+    let db = unsafe { Database::create(tmpfile.path(), db_size).unwrap() };
+    let txn = db.begin_write().unwrap();
+
+    let mut key = vec![0; 1024];
+    let value = vec![0; 1];
+    {
+        let mut table = txn.open_table(SLICE_TABLE).unwrap();
+        for i in 0..100 {
+            key[0] = i;
+            table.insert(&key, &value).unwrap();
+        }
+    }
+    txn.commit().unwrap();
+
+    let txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(SLICE_TABLE).unwrap();
+        for i in 0..100 {
+            key[0] = i;
+            table.remove(&key).unwrap();
+        }
+    }
+    txn.commit().unwrap();
+}
+
+#[test]
+fn dynamic_growth() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let table_definition: TableDefinition<u64, [u8]> = TableDefinition::new("x");
+    let big_value = vec![0; 1024];
+
+    let db_size = 10 * 1024 * 1024;
+    let db = unsafe { Database::create(tmpfile.path(), db_size).unwrap() };
+    let txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(table_definition).unwrap();
+        table.insert(&0, &big_value).unwrap();
+    }
+    txn.commit().unwrap();
+
+    let initial_file_size = tmpfile.as_file().metadata().unwrap().len();
+    assert!(initial_file_size < (db_size / 2) as u64);
+
+    let txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(table_definition).unwrap();
+        for i in 0..2048 {
+            table.insert(&i, &big_value).unwrap();
+        }
+    }
+    txn.commit().unwrap();
+
+    let file_size = tmpfile.as_file().metadata().unwrap().len();
+
+    assert!(file_size > initial_file_size);
+}
+
+#[test]
+fn dynamic_shrink() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let table_definition: TableDefinition<u64, [u8]> = TableDefinition::new("x");
+    let big_value = vec![0; 1024];
+
+    let db_size = 20 * 1024 * 1024;
+    let db = unsafe {
+        Database::builder()
+            .set_region_size(1024 * 1024)
+            .create(tmpfile.path(), db_size)
+            .unwrap()
+    };
+
+    let txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(table_definition).unwrap();
+        for i in 0..2048 {
+            table.insert(&i, &big_value).unwrap();
+        }
+    }
+    txn.commit().unwrap();
+
+    let file_size = tmpfile.as_file().metadata().unwrap().len();
+
+    let txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(table_definition).unwrap();
+        for i in 0..2048 {
+            table.remove(&i).unwrap();
+        }
+    }
+    txn.commit().unwrap();
+
+    // Perform a couple more commits to be sure the database has a chance to compact
+    let txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(table_definition).unwrap();
+        table.insert(&0, &[]).unwrap();
+    }
+    txn.commit().unwrap();
+    let txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(table_definition).unwrap();
+        table.remove(&0).unwrap();
+    }
+    txn.commit().unwrap();
+
+    let final_file_size = tmpfile.as_file().metadata().unwrap().len();
+    assert!(final_file_size < file_size);
+}
+
+#[test]
+fn multi_page_kv() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let elements = 4;
+    let page_size = 4096;
+
+    let db_size = 1024_1024;
+    let db = unsafe {
+        DatabaseBuilder::new()
+            .set_page_size(page_size)
+            .create(tmpfile.path(), db_size)
+            .unwrap()
+    };
+    let txn = db.begin_write().unwrap();
+
+    let mut key = vec![0; page_size + 1];
+    let mut value = vec![0; page_size + 1];
+    {
+        let mut table = txn.open_table(SLICE_TABLE).unwrap();
+        for i in 0..elements {
+            key[0] = i;
+            value[0] = i;
+            table.insert(&key, &value).unwrap();
+        }
+    }
+    txn.commit().unwrap();
+
+    let txn = db.begin_read().unwrap();
+    let table = txn.open_table(SLICE_TABLE).unwrap();
+    for i in 0..elements {
+        key[0] = i;
+        value[0] = i;
+        assert_eq!(&value, table.get(&key).unwrap().unwrap());
+    }
+
+    let txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(SLICE_TABLE).unwrap();
+        for i in 0..elements {
+            key[0] = i;
+            table.remove(&key).unwrap();
+        }
+    }
+    txn.commit().unwrap();
+}
+
+#[test]
+// Test for a bug in the deletion code, where deleting a key accidentally deleted other keys
+fn regression() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+
+    let db_size = 1024 * 1024;
+    let db = unsafe { Database::create(tmpfile.path(), db_size).unwrap() };
+    let txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(U64_TABLE).unwrap();
+        table.insert(&1, &1).unwrap();
+    }
+    txn.commit().unwrap();
+
+    let txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(U64_TABLE).unwrap();
+        table.insert(&6, &9).unwrap();
+    }
+    txn.commit().unwrap();
+
+    let txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(U64_TABLE).unwrap();
+        table.insert(&12, &10).unwrap();
+    }
+    txn.commit().unwrap();
+
+    let txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(U64_TABLE).unwrap();
+        table.insert(&18, &27).unwrap();
     }
     txn.commit().unwrap();
 
@@ -830,22 +1462,21 @@ fn range_query_reversed() {
     }
     assert!(iter.next().is_none());
 
-    // Test reversing multiple times
+    // Test consuming from both ends
     let mut iter = table.range(3..7).unwrap();
     let (key, _) = iter.next().unwrap();
     assert_eq!(3, key);
 
-    iter = iter.rev();
-    let (key, _) = iter.next().unwrap();
+    let (key, _) = iter.next_back().unwrap();
     assert_eq!(6, key);
-    let (key, _) = iter.next().unwrap();
+    let (key, _) = iter.next_back().unwrap();
     assert_eq!(5, key);
 
-    iter = iter.rev();
     let (key, _) = iter.next().unwrap();
     assert_eq!(4, key);
 
     assert!(iter.next().is_none());
+    assert!(iter.next_back().is_none());
 }
 
 #[test]
@@ -896,7 +1527,12 @@ fn delete_table() {
 #[test]
 fn leaked_write() {
     let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
-    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let db = unsafe {
+        Database::builder()
+            .set_leaked_write_transaction_detection(true)
+            .create(tmpfile.path(), 1024 * 1024)
+            .unwrap()
+    };
 
     let write_txn = db.begin_write().unwrap();
     drop(write_txn);
@@ -909,22 +1545,56 @@ fn leaked_write() {
 }
 
 #[test]
-fn non_page_size_multiple() {
+fn dropped_write() {
     let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
 
-    let db_size = 1024 * 1024 + 1;
-    let db = unsafe { Database::create(tmpfile.path(), db_size).unwrap() };
-    let txn = db.begin_write().unwrap();
-    let key = vec![0; 1024];
-    let value = vec![0; 1];
+    let write_txn = db.begin_write().unwrap();
     {
-        let mut table = txn.open_table(SLICE_TABLE).unwrap();
-        table.insert(&key, &value).unwrap();
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        table.insert(b"hello", b"world").unwrap();
     }
-    txn.commit().unwrap();
+    write_txn.commit().unwrap();
+    let write_txn = db.begin_write().unwrap();
+    let free_pages = write_txn.stats().unwrap().free_pages();
+    write_txn.abort().unwrap();
 
-    let read_txn = db.begin_read().unwrap();
-    let table = read_txn.open_table(SLICE_TABLE).unwrap();
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        table.insert(b"hello", b"world2").unwrap();
+        table.insert(b"hello2", b"world2").unwrap();
+    }
+    drop(write_txn);
+
+    // The dropped transaction was rolled back
+    let write_txn = db.begin_write().unwrap();
+    assert_eq!(free_pages, write_txn.stats().unwrap().free_pages());
+    {
+        let table = write_txn.open_table(SLICE_TABLE).unwrap();
+        assert_eq!(table.len().unwrap(), 1);
+        assert_eq!(b"world", table.get(b"hello").unwrap().unwrap());
+    }
+    write_txn.commit().unwrap();
+}
+
+#[test]
+fn non_page_size_multiple() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+
+    let db_size = 1024 * 1024 + 1;
+    let db = unsafe { Database::create(tmpfile.path(), db_size).unwrap() };
+    let txn = db.begin_write().unwrap();
+    let key = vec![0; 1024];
+    let value = vec![0; 1];
+    {
+        let mut table = txn.open_table(SLICE_TABLE).unwrap();
+        table.insert(&key, &value).unwrap();
+    }
+    txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(SLICE_TABLE).unwrap();
     assert_eq!(table.len().unwrap(), 1);
 }
 
@@ -1053,3 +1723,719 @@ fn tree_balance() {
         expected
     );
 }
+
+#[test]
+fn savepoint() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 16 * 1024 * 1024).unwrap() };
+    let definition: TableDefinition<u64, [u8]> = TableDefinition::new("x");
+    let definition2: TableDefinition<u64, [u8]> = TableDefinition::new("y");
+
+    let mut txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(definition).unwrap();
+        for i in 0..100u64 {
+            table.insert(&i, &[i as u8; 100]).unwrap();
+        }
+    }
+    let savepoint = txn.savepoint().unwrap();
+    {
+        let mut table = txn.open_table(definition).unwrap();
+        for i in 0..50u64 {
+            table.remove(&i).unwrap();
+        }
+        for i in 100..1000u64 {
+            table.insert(&i, &[i as u8; 100]).unwrap();
+        }
+        let mut table2 = txn.open_table(definition2).unwrap();
+        table2.insert(&0, b"hello").unwrap();
+    }
+    let savepoint2 = txn.savepoint().unwrap();
+    txn.restore_savepoint(&savepoint).unwrap();
+    assert!(matches!(
+        txn.restore_savepoint(&savepoint2),
+        Err(Error::InvalidSavepoint)
+    ));
+    {
+        let table = txn.open_table(definition).unwrap();
+        assert_eq!(table.len().unwrap(), 100);
+        for i in 0..100u64 {
+            assert_eq!(&[i as u8; 100], table.get(&i).unwrap().unwrap());
+        }
+    }
+    assert_eq!(txn.list_tables().unwrap().count(), 1);
+
+    // The savepoint may be restored more than once
+    {
+        let mut table = txn.open_table(definition).unwrap();
+        table.remove(&0).unwrap();
+    }
+    txn.restore_savepoint(&savepoint).unwrap();
+    {
+        let mut table = txn.open_table(definition).unwrap();
+        assert_eq!(table.len().unwrap(), 100);
+        table.insert(&100, b"world").unwrap();
+    }
+    txn.commit().unwrap();
+
+    {
+        let read_txn = db.begin_read().unwrap();
+        let table = read_txn.open_table(definition).unwrap();
+        assert_eq!(table.len().unwrap(), 101);
+        assert_eq!(b"world", table.get(&100).unwrap().unwrap());
+        assert!(matches!(
+            read_txn.open_table(definition2),
+            Err(Error::TableDoesNotExist(_))
+        ));
+    }
+
+    let mut txn = db.begin_write().unwrap();
+    assert!(matches!(
+        txn.restore_savepoint(&savepoint),
+        Err(Error::InvalidSavepoint)
+    ));
+    txn.abort().unwrap();
+}
+
+#[test]
+fn savepoint_frees_pages() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 16 * 1024 * 1024).unwrap() };
+
+    let txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(U64_TABLE).unwrap();
+        table.insert(&0, &0).unwrap();
+    }
+    txn.commit().unwrap();
+    let txn = db.begin_write().unwrap();
+    let free_pages = txn.stats().unwrap().free_pages();
+    txn.abort().unwrap();
+
+    let mut txn = db.begin_write().unwrap();
+    let savepoint = txn.savepoint().unwrap();
+    {
+        let mut table = txn.open_table(U64_TABLE).unwrap();
+        for i in 0..10000u64 {
+            table.insert(&i, &i).unwrap();
+        }
+        for i in 0..10000u64 {
+            table.remove(&i).unwrap();
+        }
+    }
+    txn.restore_savepoint(&savepoint).unwrap();
+    txn.commit().unwrap();
+
+    let txn = db.begin_write().unwrap();
+    assert_eq!(free_pages, txn.stats().unwrap().free_pages());
+    {
+        let table = txn.open_table(U64_TABLE).unwrap();
+        assert_eq!(table.get(&0).unwrap().unwrap(), 0);
+    }
+    txn.abort().unwrap();
+}
+
+#[test]
+fn release_savepoint() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 16 * 1024 * 1024).unwrap() };
+
+    let mut txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(U64_TABLE).unwrap();
+        for i in 0..1000u64 {
+            table.insert(&i, &i).unwrap();
+        }
+    }
+    let overwrite = |txn: &WriteTransaction| {
+        let mut table = txn.open_table(U64_TABLE).unwrap();
+        for i in 0..1000u64 {
+            table.insert(&i, &(i + 1)).unwrap();
+        }
+    };
+
+    // Pages written before a live savepoint are copied on write
+    let savepoint = txn.savepoint().unwrap();
+    let free_pages = txn.stats().unwrap().free_pages();
+    overwrite(&txn);
+    assert!(txn.stats().unwrap().free_pages() < free_pages);
+    txn.restore_savepoint(&savepoint).unwrap();
+    assert_eq!(free_pages, txn.stats().unwrap().free_pages());
+
+    // Once it's released, they're modified in place again
+    let savepoint2 = txn.savepoint().unwrap();
+    txn.release_savepoint(savepoint2).unwrap();
+    txn.release_savepoint(savepoint).unwrap();
+    overwrite(&txn);
+    assert_eq!(free_pages, txn.stats().unwrap().free_pages());
+
+    // Releasing a savepoint doesn't affect earlier ones
+    let savepoint = txn.savepoint().unwrap();
+    {
+        let mut table = txn.open_table(U64_TABLE).unwrap();
+        table.remove(&0).unwrap();
+    }
+    let savepoint2 = txn.savepoint().unwrap();
+    {
+        let mut table = txn.open_table(U64_TABLE).unwrap();
+        table.remove(&1).unwrap();
+    }
+    let savepoint3 = txn.savepoint().unwrap();
+    txn.release_savepoint(savepoint2).unwrap();
+    txn.restore_savepoint(&savepoint).unwrap();
+    assert!(matches!(
+        txn.release_savepoint(savepoint3),
+        Err(Error::InvalidSavepoint)
+    ));
+    txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(U64_TABLE).unwrap();
+    assert_eq!(table.len().unwrap(), 1000);
+    assert_eq!(table.get(&0).unwrap().unwrap(), 1);
+    assert_eq!(table.get(&1).unwrap().unwrap(), 2);
+}
+
+#[test]
+fn snapshot() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 16 * 1024 * 1024).unwrap() };
+
+    let txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(U64_TABLE).unwrap();
+        for i in 0..100u64 {
+            table.insert(&i, &i).unwrap();
+        }
+    }
+    txn.commit().unwrap();
+
+    let txn = db.begin_write().unwrap();
+    txn.create_snapshot("day1").unwrap();
+    assert!(matches!(
+        txn.create_snapshot("day1"),
+        Err(Error::SnapshotAlreadyExists(_))
+    ));
+    {
+        // Writes in the same transaction are not part of the snapshot
+        let mut table = txn.open_table(U64_TABLE).unwrap();
+        table.insert(&0, &1000).unwrap();
+    }
+    assert_eq!(
+        vec!["day1".to_string()],
+        txn.list_snapshots().unwrap().collect::<Vec<_>>()
+    );
+    txn.commit().unwrap();
+
+    // Overwrite all the data a few times, so that any unpinned pages would be reused
+    for _ in 0..3 {
+        let txn = db.begin_write().unwrap();
+        {
+            let mut table = txn.open_table(U64_TABLE).unwrap();
+            for i in 0..100u64 {
+                table.insert(&i, &(i + 1)).unwrap();
+            }
+        }
+        txn.commit().unwrap();
+    }
+
+    {
+        let read_txn = db.begin_read_snapshot("day1").unwrap();
+        let table = read_txn.open_table(U64_TABLE).unwrap();
+        assert_eq!(table.len().unwrap(), 100);
+        for i in 0..100u64 {
+            assert_eq!(i, table.get(&i).unwrap().unwrap());
+        }
+    }
+    assert!(matches!(
+        db.begin_read_snapshot("day2"),
+        Err(Error::SnapshotDoesNotExist(_))
+    ));
+    drop(db);
+
+    let db = unsafe { Database::open(tmpfile.path()).unwrap() };
+    assert_eq!(
+        vec!["day1".to_string()],
+        db.list_snapshots().unwrap().collect::<Vec<_>>()
+    );
+    let read_txn = db.begin_read_snapshot("day1").unwrap();
+
+    // Deleting a snapshot doesn't affect reads which are already in progress
+    let txn = db.begin_write().unwrap();
+    assert!(txn.delete_snapshot("day1").unwrap());
+    assert!(!txn.delete_snapshot("day1").unwrap());
+    {
+        let mut table = txn.open_table(U64_TABLE).unwrap();
+        for i in 0..100u64 {
+            table.insert(&i, &(i + 2)).unwrap();
+        }
+    }
+    txn.commit().unwrap();
+    let txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(U64_TABLE).unwrap();
+        table.insert(&0, &0).unwrap();
+    }
+    txn.commit().unwrap();
+
+    {
+        let table = read_txn.open_table(U64_TABLE).unwrap();
+        for i in 0..100u64 {
+            assert_eq!(i, table.get(&i).unwrap().unwrap());
+        }
+    }
+    drop(read_txn);
+    assert_eq!(db.list_snapshots().unwrap().count(), 0);
+    assert!(matches!(
+        db.begin_read_snapshot("day1"),
+        Err(Error::SnapshotDoesNotExist(_))
+    ));
+}
+
+#[test]
+fn snapshot_frees_pages() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 16 * 1024 * 1024).unwrap() };
+
+    let txn = db.begin_write().unwrap();
+    {
+        let mut table = txn.open_table(U64_TABLE).unwrap();
+        table.insert(&0, &0).unwrap();
+    }
+    txn.commit().unwrap();
+    let txn = db.begin_write().unwrap();
+    let free_pages = txn.stats().unwrap().free_pages();
+    txn.abort().unwrap();
+
+    let txn = db.begin_write().unwrap();
+    txn.create_snapshot("x").unwrap();
+    txn.commit().unwrap();
+
+    for _ in 0..2 {
+        let txn = db.begin_write().unwrap();
+        {
+            let mut table = txn.open_table(U64_TABLE).unwrap();
+            for i in 0..1000u64 {
+                table.insert(&i, &i).unwrap();
+            }
+            for i in 1..1000u64 {
+                table.remove(&i).unwrap();
+            }
+        }
+        txn.commit().unwrap();
+    }
+    let txn = db.begin_write().unwrap();
+    assert!(txn.stats().unwrap().free_pages() < free_pages);
+    txn.delete_snapshot("x").unwrap();
+    txn.commit().unwrap();
+    // Pages freed by the previous commit are reclaimed by the next one
+    let txn = db.begin_write().unwrap();
+    txn.commit().unwrap();
+
+    let txn = db.begin_write().unwrap();
+    assert_eq!(free_pages, txn.stats().unwrap().free_pages());
+    {
+        let table = txn.open_table(U64_TABLE).unwrap();
+        assert_eq!(table.get(&0).unwrap().unwrap(), 0);
+    }
+    txn.abort().unwrap();
+}
+
+#[test]
+fn commit_subscription() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let x: TableDefinition<u64, u64> = TableDefinition::new("x");
+    let y: TableDefinition<u64, u64> = TableDefinition::new("y");
+    let z: MultimapTableDefinition<u64, u64> = MultimapTableDefinition::new("z");
+
+    let receiver = db.subscribe();
+    let dropped = db.subscribe();
+    drop(dropped);
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(x).unwrap();
+        table.insert(&1, &1).unwrap();
+        write_txn.open_table(y).unwrap();
+        let mut table = write_txn.open_multimap_table(z).unwrap();
+        table.insert(&1, &1).unwrap();
+    }
+    write_txn.commit().unwrap();
+    let event = receiver.try_recv().unwrap();
+    assert_eq!(&["x", "y", "z"], event.changed_tables());
+    assert!(matches!(event.durability(), Durability::Immediate));
+    let first_id = event.transaction_id();
+
+    // Modifications which are rolled back, or leave the table unchanged, are not reported
+    let mut write_txn = db.begin_write().unwrap();
+    write_txn.set_durability(Durability::None);
+    let savepoint = write_txn.savepoint().unwrap();
+    {
+        let mut table = write_txn.open_table(x).unwrap();
+        table.insert(&2, &2).unwrap();
+    }
+    write_txn.restore_savepoint(&savepoint).unwrap();
+    {
+        let mut table = write_txn.open_table(y).unwrap();
+        table.insert(&1, &1).unwrap();
+        write_txn.open_multimap_table(z).unwrap();
+    }
+    write_txn.commit().unwrap();
+    let event = receiver.try_recv().unwrap();
+    assert_eq!(&["y"], event.changed_tables());
+    assert!(matches!(event.durability(), Durability::None));
+    assert!(event.transaction_id() > first_id);
+
+    // Aborted transactions are not reported
+    let write_txn = db.begin_write().unwrap();
+    write_txn.delete_table(x).unwrap();
+    write_txn.abort().unwrap();
+    assert!(receiver.try_recv().is_err());
+
+    let write_txn = db.begin_write().unwrap();
+    write_txn.delete_table(x).unwrap();
+    write_txn.commit().unwrap();
+    assert_eq!(&["x"], receiver.try_recv().unwrap().changed_tables());
+
+    let write_txn = db.begin_write().unwrap();
+    write_txn.commit().unwrap();
+    assert!(receiver.try_recv().unwrap().changed_tables().is_empty());
+
+    // Commits are still published, after a subscriber has been dropped
+    drop(receiver);
+    let receiver = db.subscribe();
+    let write_txn = db.begin_write().unwrap();
+    write_txn.commit().unwrap();
+    assert!(receiver.try_recv().is_ok());
+}
+
+#[test]
+fn changelog() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let x: TableDefinition<u64, u64> = TableDefinition::new("x");
+    let receiver = db.subscribe();
+
+    let write_txn = db.begin_write().unwrap();
+    assert!(matches!(
+        write_txn.enable_changelog("x", true),
+        Err(Error::TableDoesNotExist(_))
+    ));
+    {
+        let mut table = write_txn.open_table(x).unwrap();
+        table.insert(&1, &1).unwrap();
+        assert!(matches!(
+            write_txn.enable_changelog("x", true),
+            Err(Error::TableAlreadyOpen(_, _))
+        ));
+    }
+    write_txn.enable_changelog("x", true).unwrap();
+    write_txn.commit().unwrap();
+    let first_id = receiver.try_recv().unwrap().transaction_id();
+
+    // Changes are only recorded after the changelog is enabled
+    let read_txn = db.begin_read().unwrap();
+    assert_eq!(read_txn.changes_since("x", 0).unwrap().count(), 0);
+    drop(read_txn);
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(x).unwrap();
+        table.insert(&1, &2).unwrap();
+        table.insert(&2, &3).unwrap();
+        table.remove(&1).unwrap();
+        // Not present, so nothing is recorded
+        table.remove(&5).unwrap();
+    }
+    {
+        let mut table = write_txn.open_table(x).unwrap();
+        table.insert(&3, &4).unwrap();
+    }
+    write_txn.commit().unwrap();
+    let second_id = receiver.try_recv().unwrap().transaction_id();
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(x).unwrap();
+        table.clear().unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let changes: Vec<Change> = read_txn.changes_since("x", first_id).unwrap().collect();
+    let summary: Vec<(ChangeKind, u64, Option<u64>, Option<u64>)> = changes
+        .iter()
+        .map(|change| {
+            (
+                change.kind(),
+                u64::from_bytes(change.key()),
+                change.old_value().map(u64::from_bytes),
+                change.new_value().map(u64::from_bytes),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (ChangeKind::Update, 1, Some(1), Some(2)),
+            (ChangeKind::Insert, 2, None, Some(3)),
+            (ChangeKind::Remove, 1, Some(2), None),
+            (ChangeKind::Insert, 3, None, Some(4)),
+            (ChangeKind::Remove, 2, Some(3), None),
+            (ChangeKind::Remove, 3, Some(4), None),
+        ]
+    );
+    assert!(changes[..4]
+        .iter()
+        .all(|change| change.transaction_id() == second_id));
+    assert!(changes[4].transaction_id() > second_id);
+
+    let changes: Vec<Change> = read_txn.changes_since("x", second_id).unwrap().collect();
+    assert_eq!(changes.len(), 2);
+    drop(read_txn);
+
+    // Truncation removes the changes up to, and including, the given transaction
+    let write_txn = db.begin_write().unwrap();
+    write_txn.truncate_changelog("x", second_id).unwrap();
+    write_txn.commit().unwrap();
+    let read_txn = db.begin_read().unwrap();
+    assert_eq!(read_txn.changes_since("x", 0).unwrap().count(), 2);
+    drop(read_txn);
+
+    let write_txn = db.begin_write().unwrap();
+    write_txn.disable_changelog("x").unwrap();
+    assert!(matches!(
+        write_txn.truncate_changelog("x", second_id),
+        Err(Error::ChangelogNotEnabled(_))
+    ));
+    write_txn.commit().unwrap();
+    let read_txn = db.begin_read().unwrap();
+    assert!(matches!(
+        read_txn.changes_since("x", 0),
+        Err(Error::ChangelogNotEnabled(_))
+    ));
+    assert!(matches!(
+        read_txn.changes_since("y", 0),
+        Err(Error::TableDoesNotExist(_))
+    ));
+}
+
+#[test]
+fn changelog_insert_reserve() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        table.insert(b"a", b"1").unwrap();
+    }
+    write_txn.enable_changelog("x", true).unwrap();
+    {
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        table.insert_reserve(b"a", 1).unwrap().as_mut()[0] = b'2';
+        table.insert_reserve(b"b", 1).unwrap().as_mut()[0] = b'3';
+    }
+    write_txn.commit().unwrap();
+
+    // The reserved values are not recorded
+    let read_txn = db.begin_read().unwrap();
+    let changes: Vec<Change> = read_txn.changes_since("x", 0).unwrap().collect();
+    let summary: Vec<(ChangeKind, &[u8])> = changes
+        .iter()
+        .map(|change| (change.kind(), change.key()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (ChangeKind::Update, b"a".as_slice()),
+            (ChangeKind::Insert, b"b".as_slice())
+        ]
+    );
+    assert!(changes
+        .iter()
+        .all(|change| change.old_value().is_none() && change.new_value().is_none()));
+}
+
+#[test]
+fn changelog_keys_only() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let x: TableDefinition<u64, u64> = TableDefinition::new("x");
+    let y: MultimapTableDefinition<u64, u64> = MultimapTableDefinition::new("y");
+
+    let write_txn = db.begin_write().unwrap();
+    write_txn.open_table(x).unwrap();
+    write_txn.open_multimap_table(y).unwrap();
+    write_txn.enable_changelog("x", false).unwrap();
+    write_txn.enable_changelog("y", true).unwrap();
+    {
+        let mut table = write_txn.open_table(x).unwrap();
+        table.insert(&1, &1).unwrap();
+        table.insert(&1, &2).unwrap();
+        table.retain(|_, _| false).unwrap();
+
+        let mut table = write_txn.open_multimap_table(y).unwrap();
+        table.insert(&1, &1).unwrap();
+        table.insert(&1, &2).unwrap();
+        // Already present, so nothing is recorded
+        table.insert(&1, &2).unwrap();
+        table.remove(&1, &1).unwrap();
+        table.insert(&2, &3).unwrap();
+        table.remove_all(&2).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let summary: Vec<(ChangeKind, u64)> = read_txn
+        .changes_since("x", 0)
+        .unwrap()
+        .map(|change| {
+            assert!(change.old_value().is_none());
+            assert!(change.new_value().is_none());
+            (change.kind(), u64::from_bytes(change.key()))
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (ChangeKind::Insert, 1),
+            (ChangeKind::Update, 1),
+            (ChangeKind::Remove, 1),
+        ]
+    );
+
+    let summary: Vec<(ChangeKind, u64, Option<u64>, Option<u64>)> = read_txn
+        .changes_since("y", 0)
+        .unwrap()
+        .map(|change| {
+            (
+                change.kind(),
+                u64::from_bytes(change.key()),
+                change.old_value().map(u64::from_bytes),
+                change.new_value().map(u64::from_bytes),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (ChangeKind::Insert, 1, None, Some(1)),
+            (ChangeKind::Insert, 1, None, Some(2)),
+            (ChangeKind::Remove, 1, Some(1), None),
+            (ChangeKind::Insert, 2, None, Some(3)),
+            (ChangeKind::Remove, 2, Some(3), None),
+        ]
+    );
+}
+
+#[test]
+fn changelog_savepoint() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let x: TableDefinition<u64, u64> = TableDefinition::new("x");
+
+    let write_txn = db.begin_write().unwrap();
+    write_txn.open_table(x).unwrap();
+    write_txn.enable_changelog("x", true).unwrap();
+    write_txn.commit().unwrap();
+
+    // Changes which are rolled back are removed from the changelog
+    let mut write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(x).unwrap();
+        table.insert(&1, &1).unwrap();
+    }
+    let savepoint = write_txn.savepoint().unwrap();
+    {
+        let mut table = write_txn.open_table(x).unwrap();
+        table.insert(&2, &2).unwrap();
+    }
+    write_txn.restore_savepoint(&savepoint).unwrap();
+    {
+        let mut table = write_txn.open_table(x).unwrap();
+        table.insert(&3, &3).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(x).unwrap();
+        table.insert(&4, &4).unwrap();
+    }
+    write_txn.abort().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let keys: Vec<u64> = read_txn
+        .changes_since("x", 0)
+        .unwrap()
+        .map(|change| u64::from_bytes(change.key()))
+        .collect();
+    assert_eq!(keys, vec![1, 3]);
+}
+
+#[test]
+fn write_batch() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let x: TableDefinition<u64, u64> = TableDefinition::new("x");
+    let y: TableDefinition<str, [u8]> = TableDefinition::new("y");
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(x).unwrap();
+        table.insert(&1, &1).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    // Batches can be built on other threads
+    let batch = std::thread::spawn(move || {
+        let mut batch = WriteBatch::new();
+        batch.insert(x, &5, &5);
+        batch.insert(y, "hello", b"world");
+        batch.insert(x, &3, &3);
+        batch.insert(x, &5, &6);
+        batch.remove(x, &3);
+        // Table deletions apply to the earlier writes only
+        batch.delete_table(x);
+        batch.insert(x, &2, &2);
+        batch
+    })
+    .join()
+    .unwrap();
+    assert_eq!(batch.len(), 7);
+    db.apply(batch, Durability::Immediate).unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(x).unwrap();
+    let entries: Vec<(u64, u64)> = table.range::<RangeFull, u64>(..).unwrap().collect();
+    assert_eq!(entries, vec![(2, 2)]);
+    let table = read_txn.open_table(y).unwrap();
+    assert_eq!(table.get("hello").unwrap().unwrap(), b"world");
+    drop(read_txn);
+
+    let mut batch = WriteBatch::new();
+    batch.insert(x, &3, &3);
+    batch.insert(x, &1, &1);
+    batch.remove(x, &2);
+    db.apply(batch, Durability::None).unwrap();
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(x).unwrap();
+    let entries: Vec<(u64, u64)> = table.range::<RangeFull, u64>(..).unwrap().collect();
+    assert_eq!(entries, vec![(1, 1), (3, 3)]);
+    drop(read_txn);
+
+    // Batches are applied atomically
+    let wrong_type: TableDefinition<u64, u32> = TableDefinition::new("x");
+    let mut batch = WriteBatch::new();
+    batch.remove(x, &1);
+    batch.insert(wrong_type, &4, &4);
+    assert!(matches!(
+        db.apply(batch, Durability::Immediate),
+        Err(Error::TableTypeMismatch(_))
+    ));
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(x).unwrap();
+    assert_eq!(table.len().unwrap(), 2);
+}
@@ -113,6 +113,101 @@ fn range_query() {
     assert!(iter.next().is_none());
 }
 
+#[test]
+fn double_ended_iter() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_multimap_table(SLICE_TABLE).unwrap();
+        for i in 0..4u8 {
+            table.insert(b"0", &[i]).unwrap();
+            table.insert(b"1", &[i + 10]).unwrap();
+        }
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_multimap_table(SLICE_TABLE).unwrap();
+
+    let values: Vec<Vec<u8>> = table.get(b"0").unwrap().rev().map(|v| v.to_vec()).collect();
+    assert_eq!(vec![vec![3], vec![2], vec![1], vec![0]], values);
+
+    let mut iter = table.get(b"1").unwrap();
+    assert_eq!(Some([10u8].as_ref()), iter.next());
+    assert_eq!(Some([13u8].as_ref()), iter.next_back());
+    assert_eq!(Some([12u8].as_ref()), iter.next_back());
+    assert_eq!(Some([11u8].as_ref()), iter.next());
+    assert!(iter.next().is_none());
+    assert!(iter.next_back().is_none());
+
+    let mut iter = table.range::<std::ops::RangeFull>(..).unwrap();
+    assert_eq!(Some((b"0".as_ref(), [0u8].as_ref())), iter.next());
+    assert_eq!(Some((b"1".as_ref(), [13u8].as_ref())), iter.next_back());
+    assert_eq!(6, iter.count());
+
+    let keys: Vec<&[u8]> = table
+        .range::<std::ops::RangeFull>(..)
+        .unwrap()
+        .keys()
+        .rev()
+        .collect();
+    assert_eq!(vec![b"1".as_ref(); 4], keys[..4]);
+    assert_eq!(vec![b"0".as_ref(); 4], keys[4..]);
+    let values: Vec<&[u8]> = table
+        .range::<std::ops::RangeFull>(..)
+        .unwrap()
+        .values()
+        .collect();
+    assert_eq!(8, values.len());
+    assert_eq!([0u8].as_ref(), values[0]);
+    assert_eq!([13u8].as_ref(), values[7]);
+}
+
+#[test]
+fn floor_ceiling() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_multimap_table(SLICE_TABLE).unwrap();
+        assert!(table.floor(b"a").unwrap().is_none());
+        for key in [b"b", b"d", b"f"] {
+            for i in 0..3u8 {
+                table.insert(key, &[i]).unwrap();
+            }
+        }
+        let (key, values) = table.floor(b"c").unwrap().unwrap();
+        assert_eq!(b"b", key);
+        assert_eq!(3, values.count());
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_multimap_table(SLICE_TABLE).unwrap();
+    let floor = |key: &[u8]| table.floor(key).unwrap().map(|(k, _)| k);
+    let ceiling = |key: &[u8]| table.ceiling(key).unwrap().map(|(k, _)| k);
+    let predecessor = |key: &[u8]| table.predecessor(key).unwrap().map(|(k, _)| k);
+    let successor = |key: &[u8]| table.successor(key).unwrap().map(|(k, _)| k);
+
+    assert_eq!(None, floor(b"a"));
+    assert_eq!(Some(b"b".as_ref()), floor(b"b"));
+    assert_eq!(Some(b"d".as_ref()), floor(b"e"));
+    assert_eq!(Some(b"f".as_ref()), floor(b"z"));
+    assert_eq!(Some(b"b".as_ref()), ceiling(b"a"));
+    assert_eq!(Some(b"d".as_ref()), ceiling(b"d"));
+    assert_eq!(None, ceiling(b"g"));
+    assert_eq!(Some(b"b".as_ref()), predecessor(b"d"));
+    assert_eq!(None, predecessor(b"b"));
+    assert_eq!(Some(b"f".as_ref()), successor(b"d"));
+    assert_eq!(None, successor(b"f"));
+
+    let (key, values) = table.successor(b"b").unwrap().unwrap();
+    assert_eq!(b"d", key);
+    let values: Vec<Vec<u8>> = values.map(|v| v.to_vec()).collect();
+    assert_eq!(vec![vec![0], vec![1], vec![2]], values);
+}
+
 #[test]
 fn delete() {
     let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
@@ -166,6 +261,99 @@ fn delete() {
     assert_eq!(empty, get_vec(&table, b"hello"));
 }
 
+#[test]
+fn drain() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_multimap_table(SLICE_TABLE).unwrap();
+        table.insert(b"a", b"world").unwrap();
+        table.insert(b"b", b"world").unwrap();
+        table.insert(b"b", b"world2").unwrap();
+        table.insert(b"c", b"world").unwrap();
+        table.insert(b"d", b"world").unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_multimap_table(SLICE_TABLE).unwrap();
+        let start = b"b".as_ref();
+        let end = b"c".as_ref();
+        let removed: Vec<(Vec<u8>, Vec<u8>)> = table
+            .drain(start..=end)
+            .unwrap()
+            .map(|(key, value)| (key.to_value().to_vec(), value.to_value().to_vec()))
+            .collect();
+        assert_eq!(
+            removed,
+            vec![
+                (b"b".to_vec(), b"world".to_vec()),
+                (b"b".to_vec(), b"world2".to_vec()),
+                (b"c".to_vec(), b"world".to_vec()),
+            ]
+        );
+        let mut iter = table.drain_filter(|key, _| key == b"d").unwrap();
+        assert_eq!(b"d", iter.next().unwrap().0.to_value());
+        assert!(iter.next().is_none());
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_multimap_table(SLICE_TABLE).unwrap();
+    assert_eq!(table.len().unwrap(), 1);
+    assert_eq!(vec![b"world".to_vec()], get_vec(&table, b"a"));
+}
+
+#[test]
+fn retain() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_multimap_table(SLICE_TABLE).unwrap();
+        table.insert(b"hello", b"world").unwrap();
+        table.insert(b"hello", b"world2").unwrap();
+        table.insert(b"hi", b"world").unwrap();
+        table.retain(|_, value| value == b"world").unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_multimap_table(SLICE_TABLE).unwrap();
+    assert_eq!(table.len().unwrap(), 2);
+    assert_eq!(vec![b"world".to_vec()], get_vec(&table, b"hello"));
+    assert_eq!(vec![b"world".to_vec()], get_vec(&table, b"hi"));
+}
+
+#[test]
+fn first_last() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_multimap_table(SLICE_TABLE).unwrap();
+        assert!(table.first().unwrap().is_none());
+        table.insert(b"a", b"world2").unwrap();
+        table.insert(b"a", b"world").unwrap();
+        table.insert(b"b", b"world").unwrap();
+        table.insert(b"b", b"world3").unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_multimap_table(SLICE_TABLE).unwrap();
+    assert_eq!(
+        (b"a".as_ref(), b"world".as_ref()),
+        table.first().unwrap().unwrap()
+    );
+    assert_eq!(
+        (b"b".as_ref(), b"world3".as_ref()),
+        table.last().unwrap().unwrap()
+    );
+}
+
 #[test]
 fn wrong_types() {
     let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
@@ -1,6 +1,9 @@
-use redb::{Database, ReadableTable, TableDefinition};
+use redb::{Database, Durability, Error, ReadableTable, TableDefinition};
+use std::ops::RangeFull;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
+use std::time::Duration;
 use tempfile::NamedTempFile;
 
 const SLICE_TABLE: TableDefinition<[u8], [u8]> = TableDefinition::new("x");
@@ -31,3 +34,236 @@ fn len() {
     let table = read_txn.open_table(SLICE_TABLE).unwrap();
     assert_eq!(table.len().unwrap(), 3);
 }
+
+#[test]
+fn concurrent_write() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let db = Arc::new(db);
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+        table.insert(b"hello", b"world").unwrap();
+    }
+
+    assert!(matches!(
+        db.try_begin_write(),
+        Err(Error::WriteTransactionInProgress)
+    ));
+    assert!(matches!(
+        db.begin_write_timeout(Duration::from_millis(10)),
+        Err(Error::WriteTransactionInProgress)
+    ));
+
+    let db2 = db.clone();
+    let t = thread::spawn(move || {
+        // Blocks until the first write has committed
+        let write_txn = db2.begin_write().unwrap();
+        {
+            let mut table = write_txn.open_table(SLICE_TABLE).unwrap();
+            assert_eq!(table.get(b"hello").unwrap().unwrap(), b"world");
+            table.insert(b"hello2", b"world2").unwrap();
+        }
+        write_txn.commit().unwrap();
+    });
+    thread::sleep(Duration::from_millis(10));
+    write_txn.commit().unwrap();
+    t.join().unwrap();
+
+    let write_txn = db.try_begin_write().unwrap();
+    {
+        let table = write_txn.open_table(SLICE_TABLE).unwrap();
+        assert_eq!(table.len().unwrap(), 2);
+    }
+    write_txn.abort().unwrap();
+}
+
+#[test]
+fn watch() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let db = Arc::new(db);
+    let definition: TableDefinition<u64, u64> = TableDefinition::new("jobs");
+
+    let watch = db.watch(definition, 10..20);
+    assert!(watch.wait(Duration::from_millis(1)).unwrap().is_none());
+
+    // Writes outside of the range don't wake the watch
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        table.insert(&1, &1).unwrap();
+        table.insert(&20, &1).unwrap();
+    }
+    write_txn.commit().unwrap();
+    assert!(watch.wait(Duration::from_millis(1)).unwrap().is_none());
+
+    // Nor do writes which are rolled back, or leave the range unchanged
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        table.insert(&15, &1).unwrap();
+    }
+    write_txn.abort().unwrap();
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        table.insert(&15, &1).unwrap();
+        table.remove(&15).unwrap();
+    }
+    write_txn.commit().unwrap();
+    assert!(watch.wait(Duration::from_millis(1)).unwrap().is_none());
+
+    let db2 = db.clone();
+    let t = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        let write_txn = db2.begin_write().unwrap();
+        {
+            let mut table = write_txn.open_table(definition).unwrap();
+            table.insert(&15, &2).unwrap();
+        }
+        write_txn.commit().unwrap();
+    });
+    {
+        let read_txn = watch.wait(Duration::from_secs(60)).unwrap().unwrap();
+        let table = read_txn.open_table(definition).unwrap();
+        assert_eq!(table.get(&15).unwrap().unwrap(), 2);
+    }
+    t.join().unwrap();
+
+    // Commits made while not waiting are reported by the next call
+    let write_txn = db.begin_write().unwrap();
+    write_txn.delete_table(definition).unwrap();
+    write_txn.commit().unwrap();
+    assert!(watch.wait(Duration::from_millis(1)).unwrap().is_some());
+    assert!(watch.wait(Duration::from_millis(1)).unwrap().is_none());
+    drop(watch);
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        table.insert(&15, &3).unwrap();
+    }
+    write_txn.commit().unwrap();
+}
+
+#[test]
+fn group_commit() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 1024 * 1024).unwrap() };
+    let db = Arc::new(db);
+    let definition: TableDefinition<u64, u64> = TableDefinition::new("x");
+
+    let threads: Vec<_> = (0..16u64)
+        .map(|i| {
+            let db = db.clone();
+            // Callers requesting different durabilities can share a commit
+            let durability = if i % 2 == 0 {
+                Durability::Immediate
+            } else {
+                Durability::None
+            };
+            thread::spawn(move || {
+                db.group_commit(
+                    move |txn| {
+                        let mut table = txn.open_table(definition)?;
+                        table.insert(&i, &i)?;
+                        if i % 4 == 1 {
+                            return Err(Error::OutOfSpace);
+                        }
+                        if i % 4 == 2 {
+                            panic!("work {} panicked", i);
+                        }
+                        Ok(())
+                    },
+                    durability,
+                )
+            })
+        })
+        .collect();
+    for (i, t) in threads.into_iter().enumerate() {
+        match i % 4 {
+            1 => assert!(matches!(t.join().unwrap(), Err(Error::OutOfSpace))),
+            2 => assert!(t.join().is_err()),
+            _ => t.join().unwrap().unwrap(),
+        }
+    }
+
+    // Only the work which succeeded was committed
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(definition).unwrap();
+    let keys: Vec<u64> = table
+        .range::<RangeFull, u64>(..)
+        .unwrap()
+        .map(|(k, _)| k)
+        .collect();
+    assert_eq!(keys, vec![0, 3, 4, 7, 8, 11, 12, 15]);
+}
+
+#[test]
+fn snapshot_delete_while_opening() {
+    let tmpfile: NamedTempFile = NamedTempFile::new().unwrap();
+    let db = unsafe { Database::create(tmpfile.path(), 16 * 1024 * 1024).unwrap() };
+    let db = Arc::new(db);
+    let definition: TableDefinition<u64, u64> = TableDefinition::new("x");
+
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(definition).unwrap();
+        for i in 0..100u64 {
+            table.insert(&i, &0).unwrap();
+        }
+    }
+    write_txn.commit().unwrap();
+
+    let done = Arc::new(AtomicBool::new(false));
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let db = db.clone();
+            let done = done.clone();
+            thread::spawn(move || {
+                while !done.load(Ordering::Acquire) {
+                    let read_txn = match db.begin_read_snapshot("snapshot") {
+                        Ok(txn) => txn,
+                        Err(Error::SnapshotDoesNotExist(_)) => continue,
+                        Err(err) => panic!("{}", err),
+                    };
+                    // Every write sets all the values to the same thing, so a snapshot whose
+                    // pages were reclaimed would show a mixture of them, or change while it's read
+                    let table = read_txn.open_table(definition).unwrap();
+                    let read_values = || -> Vec<u64> {
+                        table
+                            .range::<RangeFull, u64>(..)
+                            .unwrap()
+                            .map(|(_, v)| v)
+                            .collect()
+                    };
+                    let values = read_values();
+                    assert_eq!(values.len(), 100);
+                    assert!(values.iter().all(|v| *v == values[0]));
+                    thread::sleep(Duration::from_millis(1));
+                    assert_eq!(values, read_values());
+                }
+            })
+        })
+        .collect();
+
+    for i in 1..100u64 {
+        let write_txn = db.begin_write().unwrap();
+        {
+            let mut table = write_txn.open_table(definition).unwrap();
+            for j in 0..100u64 {
+                table.insert(&j, &i).unwrap();
+            }
+        }
+        write_txn.delete_snapshot("snapshot").unwrap();
+        if i % 3 != 0 {
+            write_txn.create_snapshot("snapshot").unwrap();
+        }
+        write_txn.commit().unwrap();
+    }
+    done.store(true, Ordering::Release);
+    for reader in readers {
+        reader.join().unwrap();
+    }
+}